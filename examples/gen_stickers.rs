@@ -0,0 +1,196 @@
+// stickers/ 以下の PNG を描き直すスクリプト (cargo run --example gen_stickers)
+// ステッカーはすべてここで図形とフォント (同梱の DejaVu Sans Bold) から描いたもので、外部の絵文字セットは使っていない
+// 512x512 で描いてから 128x128 に縮める
+use image::{imageops, Rgba, RgbaImage};
+use imageproc::drawing::*;
+use imageproc::point::Point;
+use imageproc::rect::Rect;
+use rusttype::{point, Font, Scale};
+
+const S: u32 = 512;
+
+fn hex(c: u32) -> Rgba<u8> {
+    Rgba([(c >> 16) as u8, (c >> 8) as u8, c as u8, 255])
+}
+
+fn canvas() -> RgbaImage {
+    RgbaImage::from_pixel(S, S, Rgba([0, 0, 0, 0]))
+}
+
+fn rounded_rect(img: &mut RgbaImage, x0: i32, y0: i32, x1: i32, y1: i32, r: i32, c: Rgba<u8>) {
+    draw_filled_rect_mut(img, Rect::at(x0 + r, y0).of_size((x1 - x0 - 2 * r) as u32, (y1 - y0) as u32), c);
+    draw_filled_rect_mut(img, Rect::at(x0, y0 + r).of_size((x1 - x0) as u32, (y1 - y0 - 2 * r) as u32), c);
+    for (cx, cy) in [(x0 + r, y0 + r), (x1 - r - 1, y0 + r), (x0 + r, y1 - r - 1), (x1 - r - 1, y1 - r - 1)] {
+        draw_filled_circle_mut(img, (cx, cy), r, c);
+    }
+}
+
+// グリフを (cx, cy) を中心に size の箱に収まるように描く
+fn glyph_text(img: &mut RgbaImage, font: &Font, text: &str, cx: f32, cy: f32, size: f32, c: Rgba<u8>) {
+    let probe = Scale::uniform(100.0);
+    let glyphs: Vec<_> = font.layout(text, probe, point(0.0, 0.0)).collect();
+    let (mut minx, mut miny, mut maxx, mut maxy) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for g in &glyphs {
+        if let Some(bb) = g.pixel_bounding_box() {
+            minx = minx.min(bb.min.x); miny = miny.min(bb.min.y); maxx = maxx.max(bb.max.x); maxy = maxy.max(bb.max.y);
+        }
+    }
+    let (w, h) = ((maxx - minx) as f32, (maxy - miny) as f32);
+    let k = size / w.max(h);
+    let scale = Scale::uniform(100.0 * k);
+    let glyphs: Vec<_> = font.layout(text, scale, point(0.0, 0.0)).collect();
+    let (mut minx, mut miny, mut maxx, mut maxy) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for g in &glyphs {
+        if let Some(bb) = g.pixel_bounding_box() {
+            minx = minx.min(bb.min.x); miny = miny.min(bb.min.y); maxx = maxx.max(bb.max.x); maxy = maxy.max(bb.max.y);
+        }
+    }
+    let ox = cx - (minx + maxx) as f32 / 2.0;
+    let oy = cy - (miny + maxy) as f32 / 2.0;
+    for g in font.layout(text, scale, point(ox, oy)) {
+        if let Some(bb) = g.pixel_bounding_box() {
+            g.draw(|x, y, v| {
+                let (px, py) = (bb.min.x + x as i32, bb.min.y + y as i32);
+                if px >= 0 && py >= 0 && (px as u32) < S && (py as u32) < S {
+                    let p = img.get_pixel_mut(px as u32, py as u32);
+                    let a = v.clamp(0.0, 1.0);
+                    for i in 0..3 { p[i] = (p[i] as f32 * (1.0 - a) + c[i] as f32 * a) as u8; }
+                    p[3] = (p[3] as f32 + (255.0 - p[3] as f32) * a) as u8;
+                }
+            });
+        }
+    }
+}
+
+fn thick_line(img: &mut RgbaImage, a: (f32, f32), b: (f32, f32), w: f32, c: Rgba<u8>) {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    let (nx, ny) = (-dy / len * w / 2.0, dx / len * w / 2.0);
+    let pts = [
+        Point::new((a.0 + nx) as i32, (a.1 + ny) as i32),
+        Point::new((b.0 + nx) as i32, (b.1 + ny) as i32),
+        Point::new((b.0 - nx) as i32, (b.1 - ny) as i32),
+        Point::new((a.0 - nx) as i32, (a.1 - ny) as i32),
+    ];
+    draw_polygon_mut(img, &pts, c);
+    draw_filled_circle_mut(img, (a.0 as i32, a.1 as i32), (w / 2.0) as i32, c);
+    draw_filled_circle_mut(img, (b.0 as i32, b.1 as i32), (w / 2.0) as i32, c);
+}
+
+fn thumbsup() -> RgbaImage {
+    let mut img = canvas();
+    let skin = hex(0xFFCC4D);
+    let shade = hex(0xEF9645);
+    // 袖
+    rounded_rect(&mut img, 30, 236, 140, 480, 20, hex(0x5DADEC));
+    // 親指
+    rounded_rect(&mut img, 168, 40, 282, 290, 56, shade);
+    rounded_rect(&mut img, 178, 50, 272, 280, 46, skin);
+    // こぶし
+    rounded_rect(&mut img, 130, 220, 440, 480, 50, shade);
+    rounded_rect(&mut img, 140, 230, 430, 470, 42, skin);
+    // 指の境目
+    for y in [292, 352, 412] {
+        thick_line(&mut img, (300.0, y as f32), (428.0, y as f32), 12.0, shade);
+    }
+    img
+}
+
+fn tada() -> RgbaImage {
+    let mut img = canvas();
+    // クラッカー
+    let cone = [Point::new(40, 480), Point::new(150, 200), Point::new(320, 370)];
+    draw_polygon_mut(&mut img, &cone, hex(0xF4900C));
+    thick_line(&mut img, (150.0, 200.0), (320.0, 370.0), 30.0, hex(0xFFCC4D));
+    thick_line(&mut img, (95.0, 340.0), (180.0, 425.0), 16.0, hex(0xDD2E44));
+    thick_line(&mut img, (122.0, 270.0), (250.0, 398.0), 16.0, hex(0xDD2E44));
+    // 紙吹雪
+    let confetti = [
+        (300, 90, 0xDD2E44), (420, 160, 0x55ACEE), (360, 230, 0x77B255), (250, 60, 0xAA8ED6),
+        (460, 300, 0xFFCC4D), (440, 60, 0x77B255), (200, 120, 0x55ACEE), (380, 110, 0xFFCC4D),
+    ];
+    for (x, y, c) in confetti {
+        draw_filled_circle_mut(&mut img, (x, y), 22, hex(c));
+    }
+    thick_line(&mut img, (250.0, 190.0), (330.0, 140.0), 14.0, hex(0x55ACEE));
+    thick_line(&mut img, (330.0, 290.0), (420.0, 250.0), 14.0, hex(0xAA8ED6));
+    img
+}
+
+fn check(font: &Font) -> RgbaImage {
+    let mut img = canvas();
+    rounded_rect(&mut img, 24, 24, 488, 488, 70, hex(0x77B255));
+    glyph_text(&mut img, font, "✔", 256.0, 262.0, 320.0, hex(0xFFFFFF));
+    img
+}
+
+fn glyph_sticker(font: &Font, glyph: &str, color: u32) -> RgbaImage {
+    let mut img = canvas();
+    glyph_text(&mut img, font, glyph, 256.0, 256.0, 470.0, hex(color));
+    img
+}
+
+fn smile() -> RgbaImage {
+    let mut img = canvas();
+    draw_filled_circle_mut(&mut img, (256, 256), 236, hex(0xFFCC4D));
+    let dark = hex(0x664500);
+    draw_filled_ellipse_mut(&mut img, (180, 190), 28, 42, dark);
+    draw_filled_ellipse_mut(&mut img, (332, 190), 28, 42, dark);
+    let mut mouth = Vec::new();
+    for i in 0..=32 {
+        let t = std::f32::consts::PI * i as f32 / 32.0;
+        mouth.push(Point::new((256.0 + 150.0 * t.cos()) as i32, (290.0 + 120.0 * t.sin()) as i32));
+    }
+    mouth.dedup();
+    draw_polygon_mut(&mut img, &mouth, dark);
+    img
+}
+
+fn hundred(font: &Font) -> RgbaImage {
+    let mut img = canvas();
+    let red = hex(0xDD2E44);
+    glyph_text(&mut img, font, "100", 256.0, 210.0, 440.0, red);
+    thick_line(&mut img, (40.0, 370.0), (470.0, 350.0), 30.0, red);
+    thick_line(&mut img, (60.0, 440.0), (450.0, 425.0), 30.0, red);
+    img
+}
+
+fn ok(font: &Font) -> RgbaImage {
+    let mut img = canvas();
+    rounded_rect(&mut img, 24, 24, 488, 488, 70, hex(0x3B88C3));
+    glyph_text(&mut img, font, "OK", 256.0, 256.0, 330.0, hex(0xFFFFFF));
+    img
+}
+
+fn eyes() -> RgbaImage {
+    let mut img = canvas();
+    for cx in [150, 362] {
+        draw_filled_ellipse_mut(&mut img, (cx, 256), 100, 180, hex(0x292F33));
+        draw_filled_ellipse_mut(&mut img, (cx, 256), 88, 168, hex(0xF5F8FA));
+        draw_filled_ellipse_mut(&mut img, (cx + 30, 240), 40, 62, hex(0x292F33));
+    }
+    img
+}
+
+fn main() {
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let font = Font::try_from_bytes(include_bytes!("../DejaVu_Sans/DejaVuSans-Bold.ttf") as &[u8]).unwrap();
+    let out = root.join("stickers");
+    std::fs::create_dir_all(&out).unwrap();
+    let stickers = [
+        ("thumbsup", thumbsup()),
+        ("tada", tada()),
+        ("white_check_mark", check(&font)),
+        ("heart", glyph_sticker(&font, "♥", 0xDD2E44)),
+        ("star", glyph_sticker(&font, "★", 0xFFAC33)),
+        ("zap", glyph_sticker(&font, "⚡", 0xFFAC33)),
+        ("smile", smile()),
+        ("100", hundred(&font)),
+        ("ok", ok(&font)),
+        ("eyes", eyes()),
+    ];
+    for (name, img) in stickers {
+        let small = imageops::resize(&img, 128, 128, imageops::FilterType::Lanczos3);
+        small.save(out.join(format!("{}.png", name))).unwrap();
+    }
+}
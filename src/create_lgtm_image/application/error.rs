@@ -13,6 +13,9 @@ pub enum ApplicationError {
     #[error("Configuration error: {0}")]
     ConfigurationError(String),
 
+    #[error("Output exceeds byte budget: {0}")]
+    OutputBudgetExceeded(String),
+
     #[error("Domain error occurred: {0}")]
     DomainError(#[from] DomainError), // ドメインエラーをラップ
 
@@ -38,6 +41,7 @@ impl IntoResponse for ApplicationError {
             ApplicationError::LgtmGenerationFailed(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             ApplicationError::ExternalServiceError(msg) => (StatusCode::BAD_GATEWAY, msg),
             ApplicationError::ConfigurationError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            ApplicationError::OutputBudgetExceeded(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
             ApplicationError::DomainError(domain_err) => {
                 (StatusCode::BAD_REQUEST, domain_err.to_string())
             }
@@ -48,7 +52,10 @@ impl IntoResponse for ApplicationError {
                 match infra_err {
                     InfrastructureError::ExternalApiError(_) => (StatusCode::BAD_GATEWAY, infra_err.to_string()),
                     InfrastructureError::DecodingError(_) => (StatusCode::BAD_REQUEST, infra_err.to_string()),
+                    InfrastructureError::ImageLimitExceeded(_) => (StatusCode::PAYLOAD_TOO_LARGE, infra_err.to_string()),
+                    InfrastructureError::NotFound(_) => (StatusCode::NOT_FOUND, infra_err.to_string()),
                     InfrastructureError::ImageLibError(_) => (StatusCode::UNPROCESSABLE_ENTITY, infra_err.to_string()),
+                    InfrastructureError::DomainErrorWrapper(_) => (StatusCode::BAD_REQUEST, infra_err.to_string()),
                     _ => (StatusCode::INTERNAL_SERVER_ERROR, infra_err.to_string()),
                 }
             }
@@ -0,0 +1,382 @@
+use crate::domain::encode_settings::EncodeSettings;
+
+// LgtmService に渡すリクエスト内容 (ハンドラの DTO から組み立てる)
+#[derive(Debug, Clone)]
+pub struct LgtmRequest {
+    pub text: String,             // "random" なら辞書から選ぶ
+    pub locale: Option<String>,   // text=random / {random_phrase} で選ぶ言葉のロケール ("en" / "ja" など)
+    pub seed: Option<u64>,        // 指定すると毎回同じ言葉を選ぶ
+    pub subtitle: Option<String>, // テキストの下に小さく添える行。"auto" / "random" なら頭文字の合う言葉を辞書から選ぶ
+    pub text_color_hex: String,
+    pub text_position: String,
+    pub text_path: Option<TextPathRequest>, // 文字を円弧や曲線に沿わせる
+    pub writing_mode: String,     // "horizontal" / "vertical" (縦書き)
+    pub text_effect: Option<TextEffectRequest>, // ネオン・立体・虹色・グリッチ
+    pub tile: Option<TileRequest>, // 画像全体に繰り返す (text_position が "tiled" なら省略しても既定値で繰り返す)
+    pub extra_texts: Vec<ExtraTextRequest>, // メインのテキストとは別に置くテキスト
+    pub output_format: String,
+    pub max_bytes: Option<usize>, // 出力サイズの上限 (バイト)。超える場合は段階的に劣化させる
+    pub colors: Option<u16>,      // png8 / gif のパレット色数
+    pub dither: bool,             // 減色時にディザをかけるか
+    pub metadata: String,         // "strip" / "icc" / "all-except-gps"
+    // テキストを描画する前の変形
+    pub crop: Option<String>,     // "x,y,width,height"
+    pub aspect: Option<String>,   // "1:1" / "16:9" / "4:3" など
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub resize_mode: String,      // "fit" / "fill" / "contain"
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub padding_color_hex: String,
+    pub min_size: u32,            // 短辺がこれ未満なら拡大してから描画する (0 で無効)
+    pub upscale_filter: String,   // "auto" / "nearest" / "lanczos"
+    pub filters: Vec<FilterRequest>, // テキストを描く前に順にかけるフィルタ
+    pub backdrop: Option<BackdropRequest>, // テキストの下だけぼかして暗くする
+    // テキストを描いた後の飾り
+    pub frame: Option<String>,    // "polaroid"
+    pub frame_color_hex: String,  // ポラロイドの余白の色
+    pub border_width: u32,        // 0 で枠線なし
+    pub border_color_hex: String,
+    pub border_gradient_hex: Option<String>, // 指定すると border_color からこの色への縦グラデーション
+    pub corner_radius: u32,       // 0 で角丸なし
+    pub logo: Option<LogoRequest>, // テキストの上に重ねるロゴ
+    pub stickers: Vec<StickerRequest>, // 同梱ステッカーを画像として重ねる (テキスト中の ":thumbsup:" とは別)
+    pub stamp: Option<StampRequest>, // 判子風の承認印
+    pub bubbles: Vec<BubbleRequest>, // 文字を囲む吹き出し
+    pub qr: Option<QrRequest>,    // PR の URL などの QR コード
+    // テキストの {user} / {pr} / {repo} に埋め込む値
+    pub user: Option<String>,
+    pub pr: Option<String>,
+    pub repo: Option<String>,
+}
+
+// テキストを並べる線。kind は "straight" / "arc" / "bezier"
+// arc は radius (短辺に対する比率) と angle (度。0 が真上)、bezier は points (画像に対する比率の制御点 3 つか 4 つ) を使う
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextPathRequest {
+    pub kind: String,
+    pub radius: Option<f32>,
+    pub angle: Option<f32>,
+    pub points: Vec<(f32, f32)>,
+}
+
+// 追加のテキスト。position と path の意味はメインのテキストと同じ
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraTextRequest {
+    pub text: String,
+    pub color_hex: String,
+    pub position: String,
+    pub path: Option<TextPathRequest>,
+    pub writing_mode: String,
+    pub effect: Option<TextEffectRequest>,
+}
+
+// テキストの効果。name は "neon" / "3d" / "rainbow" / "glitch" で、使う項目は効果ごとに違う (省略時は既定値)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextEffectRequest {
+    pub name: String,
+    pub color_hex: Option<String>, // neon の光の色
+    pub width: Option<u32>,        // neon の縁の太さ / 3d の奥行き / glitch のずらし幅 (px)
+    pub radius: Option<f32>,       // neon の光の広がり
+    pub angle: Option<f32>,        // 3d の押し出す向き (度)
+    pub shade: Option<f32>,        // 3d の側面の暗さ (0.0-1.0)
+    pub step: Option<f32>,         // rainbow の 1 文字ごとの色相の変化 (度)
+    pub slices: Option<u32>,       // glitch の帯の数
+    pub seed: Option<u32>,         // glitch のずれ方
+    pub stroke: Option<f32>,       // outline のふちの太さ (文字の大きさに対する比率)
+}
+
+// ミーム (画像の上下にキャプション)。どちらか一方は空でもいい
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemeRequest {
+    pub top: String,
+    pub bottom: String,
+    pub output_format: String,
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for MemeRequest {
+    fn default() -> Self {
+        Self {
+            top: String::new(),
+            bottom: String::new(),
+            output_format: "png".to_string(),
+            max_bytes: None,
+        }
+    }
+}
+
+// 透かし風に繰り返すときの指定。省略した項目は既定値
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileRequest {
+    pub angle: Option<f32>,   // 格子を回す角度 (度。時計回り)
+    pub spacing: Option<f32>, // 文字の間隔 (文字の高さに対する比率)
+    pub opacity: Option<f32>, // 0.0-1.0
+}
+
+// フィルタ 1 つ分の指定。amount の意味はフィルタごとに違う (省略時は既定値)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterRequest {
+    pub name: String,
+    pub amount: Option<f32>,
+}
+
+// テキストの下のすりガラス風の背景。省略した項目は既定値
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackdropRequest {
+    pub blur: Option<f32>,
+    pub darken: Option<f32>,
+    pub padding: Option<u32>,
+    pub feather: Option<u32>,
+}
+
+// ロゴ画像の取得元
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogoSource {
+    Upload(Vec<u8>), // multipart でアップロードされた画像
+    Asset(String),   // サーバーの assets ディレクトリにある画像の名前
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogoRequest {
+    pub source: LogoSource,
+    pub position: String, // text_position と同じ値
+    pub scale: f32,       // 下の画像の幅に対する比率
+    pub opacity: f32,
+    pub margin: u32,
+}
+
+impl LogoRequest {
+    pub fn new(source: LogoSource) -> Self {
+        Self {
+            source,
+            position: "bottom-right".to_string(),
+            scale: 0.2,
+            opacity: 1.0,
+            margin: 16,
+        }
+    }
+}
+
+// 同梱ステッカー 1 つ分。position などの意味は LogoRequest と同じ
+#[derive(Debug, Clone, PartialEq)]
+pub struct StickerRequest {
+    pub shortcode: String, // "thumbsup" または ":thumbsup:"
+    pub position: String,
+    pub scale: f32,
+    pub opacity: f32,
+    pub margin: u32,
+}
+
+impl StickerRequest {
+    pub fn new(shortcode: String) -> Self {
+        Self {
+            shortcode,
+            position: "top-right".to_string(),
+            scale: 0.15,
+            opacity: 1.0,
+            margin: 16,
+        }
+    }
+}
+
+// 判子。date に "today" を指定するとサーバーの今日の日付 (YYYY.MM.DD) になる
+#[derive(Debug, Clone, PartialEq)]
+pub struct StampRequest {
+    pub text: String,
+    pub name: Option<String>,
+    pub date: Option<String>,
+    pub shape: String,  // "circle" / "square"
+    pub layout: String, // "arc" / "vertical"
+    pub color_hex: String,
+    pub position: String,
+    pub scale: f32,
+    pub margin: u32,
+    pub distress: f32,
+    pub angle: f32,
+}
+
+impl Default for StampRequest {
+    fn default() -> Self {
+        Self {
+            text: "LGTM".to_string(),
+            name: None,
+            date: None,
+            shape: "circle".to_string(),
+            layout: "arc".to_string(),
+            color_hex: "#D0312DE6".to_string(),
+            position: "bottom-right".to_string(),
+            scale: 0.3,
+            margin: 16,
+            distress: 0.3,
+            angle: -8.0,
+        }
+    }
+}
+
+// 吹き出し。kind は "speech" / "thought"、tail は "auto" / "top" / "bottom" / "left" / "right"
+// target はしっぽの先 (画像に対する比率)。省略するとしっぽなし
+#[derive(Debug, Clone, PartialEq)]
+pub struct BubbleRequest {
+    pub text: String,
+    pub kind: String,
+    pub position: String,
+    pub margin: u32,
+    pub target: Option<(f32, f32)>,
+    pub tail: String,
+    pub text_size: f32,
+    pub text_color_hex: String,
+    pub fill_hex: String,
+    pub border_hex: String,
+    pub border_width: u32,
+}
+
+impl BubbleRequest {
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            kind: "speech".to_string(),
+            position: "top-left".to_string(),
+            margin: 16,
+            target: None,
+            tail: "auto".to_string(),
+            text_size: 0.06,
+            text_color_hex: "#000000FF".to_string(),
+            fill_hex: "#FFFFFFFF".to_string(),
+            border_hex: "#000000FF".to_string(),
+            border_width: 3,
+        }
+    }
+}
+
+// QR コード。size は短辺に対する比率、quiet_zone は周りの余白 (モジュール数)
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrRequest {
+    pub content: String,
+    pub position: String,
+    pub size: f32,
+    pub margin: u32,
+    pub quiet_zone: u32,
+    pub dark_hex: String,
+    pub light_hex: String,
+}
+
+impl QrRequest {
+    pub fn new(content: String) -> Self {
+        Self {
+            content,
+            position: "bottom-left".to_string(),
+            size: 0.2,
+            margin: 16,
+            quiet_zone: 4,
+            dark_hex: "#000000FF".to_string(),
+            light_hex: "#FFFFFFFF".to_string(),
+        }
+    }
+}
+
+// 複数の画像を 1 枚に並べる指定。layout は "grid" / "strip"
+// columns はグリッドの列数 (省略すると画像の枚数の平方根を切り上げた数)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollageRequest {
+    pub layout: String,
+    pub columns: Option<u32>,
+    pub gutter: u32,
+    pub background_hex: String,
+}
+
+impl Default for CollageRequest {
+    fn default() -> Self {
+        Self {
+            layout: "grid".to_string(),
+            columns: None,
+            gutter: 8,
+            background_hex: "#FFFFFFFF".to_string(),
+        }
+    }
+}
+
+// ギャラリーの最近の画像を並べた一覧の指定。count 枚を新しい順に左上から並べる
+// columns を省略すると枚数の平方根を切り上げた列数になる
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactSheetRequest {
+    pub count: usize,
+    pub columns: Option<u32>,
+    pub thumbnail_size: u32,
+    pub gutter: u32,
+    pub background_hex: String,
+    pub label_color_hex: String,
+}
+
+impl Default for ContactSheetRequest {
+    fn default() -> Self {
+        Self {
+            count: 12,
+            columns: None,
+            thumbnail_size: 200,
+            gutter: 8,
+            background_hex: "#FFFFFFFF".to_string(),
+            label_color_hex: "#333333FF".to_string(),
+        }
+    }
+}
+
+impl Default for LgtmRequest {
+    fn default() -> Self {
+        Self {
+            text: "LGTM".to_string(),
+            locale: None,
+            seed: None,
+            subtitle: None,
+            text_color_hex: "#FFFFFFFF".to_string(),
+            text_position: "center".to_string(),
+            text_path: None,
+            writing_mode: "horizontal".to_string(),
+            text_effect: None,
+            tile: None,
+            extra_texts: Vec::new(),
+            output_format: "png".to_string(),
+            max_bytes: None,
+            colors: None,
+            dither: false,
+            metadata: "strip".to_string(),
+            crop: None,
+            aspect: None,
+            width: None,
+            height: None,
+            resize_mode: "fit".to_string(),
+            max_width: None,
+            max_height: None,
+            padding_color_hex: "#00000000".to_string(),
+            min_size: 0,
+            upscale_filter: "auto".to_string(),
+            filters: Vec::new(),
+            backdrop: None,
+            frame: None,
+            frame_color_hex: "#FAFAFAFF".to_string(),
+            border_width: 0,
+            border_color_hex: "#FFFFFFFF".to_string(),
+            border_gradient_hex: None,
+            corner_radius: 0,
+            logo: None,
+            stickers: Vec::new(),
+            stamp: None,
+            bubbles: Vec::new(),
+            qr: None,
+            user: None,
+            pr: None,
+            repo: None,
+        }
+    }
+}
+
+// 生成結果。最終的な寸法とエンコード設定はレスポンスヘッダで返す
+#[derive(Debug, Clone)]
+pub struct LgtmOutput {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub settings: EncodeSettings,
+    pub gallery_id: Option<String>, // ギャラリーに残したときの ID
+}
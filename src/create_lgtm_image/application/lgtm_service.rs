@@ -1,24 +1,77 @@
 use std::sync::Arc;
 // use anyhow::Result; // Remove if fully transitioned
 use super::error::ApplicationError; // Changed from anyhow::Result
+use super::text_template::{render_template, TemplateContext};
 use image::ImageFormat as InnerImageFormat;
 
+use super::lgtm_request::{BackdropRequest, BubbleRequest, CollageRequest, ContactSheetRequest, ExtraTextRequest, FilterRequest, LgtmOutput, LgtmRequest, LogoRequest, MemeRequest, LogoSource, QrRequest, StampRequest, StickerRequest, TextEffectRequest, TextPathRequest, TileRequest};
 use crate::domain::image_processor_trait::ImageProcessor;
-use crate::domain::text_overlay::TextOverlay;
-use crate::domain::color::Color as DomainColor;
+use crate::domain::image::Image as DomainImage;
+use crate::domain::text_overlay::{Subtitle, TextOverlay, TextPath, TextWrap, TileLayout, WritingMode};
 use crate::domain::position::Position as DomainPosition;
+use crate::domain::encode_settings::EncodeSettings;
+use crate::domain::metadata_policy::MetadataPolicy;
+use crate::domain::render_options::RenderOptions;
+use crate::domain::filter::{ImageFilter, TextBackdrop};
+use crate::domain::frame::{Border, BorderFill, Frame, Polaroid};
+use crate::domain::bubble::{BubbleKind, BubbleOverlay, TailSide};
+use crate::domain::qr::{QrOverlay, MAX_QR_BYTES};
+use crate::domain::collage::{Collage, CollageLayout, ContactSheet};
+use crate::domain::overlay::{ImageOverlay, Overlay};
+use crate::domain::stamp::{StampLayout, StampOverlay, StampShape};
+use crate::domain::text_effect::TextEffect;
+use crate::domain::transform::{AspectRatio, CropRect, MinSize, Resize, ResizeMode, Transform, UpscaleFilter};
+use crate::domain::error::DomainError;
 // external_image_fetcherもインフラ層なので、トレイト経由でDIするのが望ましいが、今回は直接使う
 use crate::infrastructure::external_image_fetcher::DefaultExternalImageFetcher;
+use crate::infrastructure::asset_store::LocalAssetStore;
+use crate::infrastructure::gallery_store::LocalGalleryStore;
+use crate::infrastructure::phrase_dictionary::PhraseDictionary;
+use crate::infrastructure::sticker_library::{sticker_names, sticker_png};
 
+// 1 回に指定できるフィルタの数
+const MAX_FILTERS: usize = 8;
+
+// 添える行の大きさ (メインの文字に対する比率)
+const SUBTITLE_SCALE: f32 = 0.3;
+// 1 枚に並べられる画像の数と、すき間の上限 (px)
+const MAX_COLLAGE_IMAGES: usize = 16;
+const MAX_COLLAGE_GUTTER: u32 = 256;
+// 一覧のサムネイルの一辺 (px)
+const MIN_THUMBNAIL_SIZE: u32 = 32;
+const MAX_THUMBNAIL_SIZE: u32 = 512;
 
 pub struct LgtmService {
     image_processor: Arc<dyn ImageProcessor + Send + Sync>, // トレイトオブジェクトとして保持
+    asset_store: LocalAssetStore, // ロゴなどサーバー側の画像
+    phrases: PhraseDictionary,    // text=random で選ぶ言葉
+    gallery: Option<LocalGalleryStore>, // 作った画像の保存先。設定しなければ残さない
     // external_image_fetcher: Arc<dyn ExternalImageFetcherTrait + Send + Sync>, // 本来はこうしたい
 }
 
 impl LgtmService {
     pub fn new(image_processor: Arc<dyn ImageProcessor + Send + Sync>) -> Self {
-        Self { image_processor }
+        Self {
+            image_processor,
+            asset_store: LocalAssetStore::default(),
+            phrases: PhraseDictionary::default(),
+            gallery: None,
+        }
+    }
+
+    pub fn with_asset_store(mut self, asset_store: LocalAssetStore) -> Self {
+        self.asset_store = asset_store;
+        self
+    }
+
+    pub fn with_phrases(mut self, phrases: PhraseDictionary) -> Self {
+        self.phrases = phrases;
+        self
+    }
+
+    pub fn with_gallery(mut self, gallery: LocalGalleryStore) -> Self {
+        self.gallery = Some(gallery);
+        self
     }
 
     fn map_position_str_to_domain(&self, position_str: &str) -> DomainPosition {
@@ -39,62 +92,794 @@ impl LgtmService {
     fn map_format_str_to_enum(&self, format_str: &str) -> (InnerImageFormat, &'static str) {
         match format_str.to_lowercase().as_str() {
             "jpeg" | "jpg" => (InnerImageFormat::Jpeg, "image/jpeg"),
-            "png" | _ => (InnerImageFormat::Png, "image/png"), // Default to PNG
+            "gif" => (InnerImageFormat::Gif, "image/gif"),
+            "webp" => (InnerImageFormat::WebP, "image/webp"), // ロスレス
+            _ => (InnerImageFormat::Png, "image/png"), // Default to PNG (png8 も PNG)
+        }
+    }
+
+    fn map_metadata_str_to_policy(&self, metadata_str: &str) -> MetadataPolicy {
+        match metadata_str.to_lowercase().as_str() {
+            "icc" => MetadataPolicy::PreserveIcc,
+            "all-except-gps" => MetadataPolicy::PreserveAllExceptGps,
+            _ => MetadataPolicy::StripAll, // Default
+        }
+    }
+
+    fn map_resize_mode_str_to_domain(&self, mode_str: &str) -> ResizeMode {
+        match mode_str.to_lowercase().as_str() {
+            "fill" => ResizeMode::Fill,
+            "contain" => ResizeMode::Contain,
+            _ => ResizeMode::Fit, // Default
+        }
+    }
+
+    fn map_upscale_filter_str_to_domain(&self, filter_str: &str) -> UpscaleFilter {
+        match filter_str.to_lowercase().as_str() {
+            "nearest" => UpscaleFilter::Nearest,
+            "lanczos" => UpscaleFilter::Lanczos,
+            _ => UpscaleFilter::Auto, // Default
+        }
+    }
+
+    // "16:9" のような縦横比。"square" などの別名も受け付ける
+    fn parse_aspect(&self, aspect_str: &str) -> Result<AspectRatio, DomainError> {
+        match aspect_str.to_lowercase().as_str() {
+            "square" => return Ok(AspectRatio::SQUARE),
+            "wide" => return Ok(AspectRatio::WIDE),
+            "standard" => return Ok(AspectRatio::STANDARD),
+            _ => {}
+        }
+        let parsed = aspect_str
+            .split_once(':')
+            .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)));
+        match parsed {
+            Some((width, height)) if width > 0 && height > 0 => Ok(AspectRatio { width, height }),
+            _ => Err(DomainError::InvalidInput(format!("Invalid aspect ratio: {}", aspect_str))),
+        }
+    }
+
+    // "x,y,width,height"
+    fn parse_crop(&self, crop_str: &str) -> Result<CropRect, DomainError> {
+        let values: Vec<u32> = crop_str
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| DomainError::InvalidInput(format!("Invalid crop: {}", crop_str)))?;
+        match values[..] {
+            [x, y, width, height] => Ok(CropRect { x, y, width, height }),
+            _ => Err(DomainError::InvalidInput(format!("Invalid crop (expected x,y,width,height): {}", crop_str))),
+        }
+    }
+
+    // フィルタ名と amount (省略時は既定値) からドメインのフィルタを作る
+    fn map_filter_to_domain(&self, filter: &FilterRequest) -> Result<ImageFilter, DomainError> {
+        let amount = |default: f32| filter.amount.unwrap_or(default);
+        match filter.name.to_lowercase().as_str() {
+            "grayscale" => Ok(ImageFilter::Grayscale { amount: amount(1.0) }),
+            "sepia" => Ok(ImageFilter::Sepia { amount: amount(1.0) }),
+            "blur" => Ok(ImageFilter::Blur { sigma: amount(3.0) }),
+            "brightness" => Ok(ImageFilter::Brightness { amount: amount(0.2) }),
+            "contrast" => Ok(ImageFilter::Contrast { amount: amount(0.2) }),
+            "saturation" => Ok(ImageFilter::Saturation { factor: amount(1.5) }),
+            "vignette" => Ok(ImageFilter::Vignette { strength: amount(0.5) }),
+            "darken" => Ok(ImageFilter::Darken { opacity: amount(0.3) }),
+            _ => Err(DomainError::InvalidInput(format!("Unknown filter: {}", filter.name))),
+        }
+    }
+
+    // テキストの下のすりガラス。省略した項目は既定値で埋める
+    fn map_backdrop_to_domain(&self, backdrop: &BackdropRequest) -> TextBackdrop {
+        TextBackdrop {
+            blur_sigma: backdrop.blur.unwrap_or(8.0),
+            darken: backdrop.darken.unwrap_or(0.35),
+            padding: backdrop.padding.unwrap_or(16),
+            feather: backdrop.feather.unwrap_or(12),
+        }
+    }
+
+    fn build_frame(&self, request: &LgtmRequest) -> Result<Frame, DomainError> {
+        let polaroid = match request.frame.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("none") => None,
+            Some("polaroid") => Some(Polaroid { color: self.image_processor.parse_hex_color(&request.frame_color_hex) }),
+            Some(_) => return Err(DomainError::InvalidInput(format!("Unknown frame: {}", request.frame.as_deref().unwrap_or_default()))),
+        };
+        let border_color = self.image_processor.parse_hex_color(&request.border_color_hex);
+        let fill = match &request.border_gradient_hex {
+            Some(bottom) => BorderFill::Gradient { top: border_color, bottom: self.image_processor.parse_hex_color(bottom) },
+            None => BorderFill::Solid(border_color),
+        };
+        Ok(Frame {
+            polaroid,
+            border: (request.border_width > 0).then_some(Border { width: request.border_width, fill }),
+            corner_radius: request.corner_radius,
+        })
+    }
+
+    async fn build_logo_overlay(&self, logo: &LogoRequest) -> Result<ImageOverlay, ApplicationError> {
+        if !(logo.scale > 0.0 && logo.scale <= 1.0) {
+            return Err(DomainError::InvalidInput(format!("Logo scale must be in (0, 1]: {}", logo.scale)).into());
+        }
+        let image_bytes = match &logo.source {
+            LogoSource::Upload(data) => data.clone(),
+            LogoSource::Asset(name) => self.asset_store.load_image(name).await?,
+        };
+        Ok(ImageOverlay {
+            image_bytes,
+            position: self.map_position_str_to_domain(&logo.position),
+            scale: logo.scale,
+            opacity: logo.opacity.clamp(0.0, 1.0),
+            margin: logo.margin,
+        })
+    }
+
+    fn build_sticker_overlay(&self, sticker: &StickerRequest) -> Result<ImageOverlay, DomainError> {
+        let png = sticker_png(&sticker.shortcode).ok_or_else(|| {
+            DomainError::InvalidInput(format!("Unknown sticker: {} (available: {})", sticker.shortcode, sticker_names().join(", ")))
+        })?;
+        if !(sticker.scale > 0.0 && sticker.scale <= 1.0) {
+            return Err(DomainError::InvalidInput(format!("Sticker scale must be in (0, 1]: {}", sticker.scale)));
+        }
+        Ok(ImageOverlay {
+            image_bytes: png.to_vec(),
+            position: self.map_position_str_to_domain(&sticker.position),
+            scale: sticker.scale,
+            opacity: sticker.opacity.clamp(0.0, 1.0),
+            margin: sticker.margin,
+        })
+    }
+
+    fn build_stamp_overlay(&self, stamp: &StampRequest) -> Result<StampOverlay, DomainError> {
+        let shape = match stamp.shape.to_lowercase().as_str() {
+            "circle" => StampShape::Circle,
+            "square" => StampShape::RoundedSquare,
+            _ => return Err(DomainError::InvalidInput(format!("Unknown stamp shape: {}", stamp.shape))),
+        };
+        let layout = match stamp.layout.to_lowercase().as_str() {
+            "arc" => StampLayout::Arc,
+            "vertical" => StampLayout::Vertical,
+            _ => return Err(DomainError::InvalidInput(format!("Unknown stamp layout: {}", stamp.layout))),
+        };
+        if !(stamp.scale > 0.0 && stamp.scale <= 1.0) {
+            return Err(DomainError::InvalidInput(format!("Stamp scale must be in (0, 1]: {}", stamp.scale)));
+        }
+        let date = stamp.date.as_ref().map(|date| match date.as_str() {
+            "today" => chrono::Local::now().format("%Y.%m.%d").to_string(),
+            _ => date.clone(),
+        });
+        Ok(StampOverlay {
+            text: stamp.text.clone(),
+            name: stamp.name.clone(),
+            date,
+            shape,
+            layout,
+            color: self.image_processor.parse_hex_color(&stamp.color_hex),
+            position: self.map_position_str_to_domain(&stamp.position),
+            scale: stamp.scale,
+            margin: stamp.margin,
+            distress: stamp.distress.clamp(0.0, 1.0),
+            angle: stamp.angle,
+        })
+    }
+
+    fn build_bubble_overlay(&self, bubble: &BubbleRequest) -> Result<BubbleOverlay, DomainError> {
+        if bubble.text.trim().is_empty() {
+            return Err(DomainError::InvalidInput("Bubble text must not be empty".to_string()));
+        }
+        let kind = match bubble.kind.to_lowercase().as_str() {
+            "speech" => BubbleKind::Speech,
+            "thought" => BubbleKind::Thought,
+            _ => return Err(DomainError::InvalidInput(format!("Unknown bubble kind: {}", bubble.kind))),
+        };
+        let tail = match bubble.tail.to_lowercase().as_str() {
+            "auto" => TailSide::Auto,
+            "top" => TailSide::Top,
+            "bottom" => TailSide::Bottom,
+            "left" => TailSide::Left,
+            "right" => TailSide::Right,
+            _ => return Err(DomainError::InvalidInput(format!("Unknown bubble tail: {}", bubble.tail))),
+        };
+        if let Some((x, y)) = bubble.target {
+            if !((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y)) {
+                return Err(DomainError::InvalidInput(format!("Bubble target must be within 0.0-1.0: ({}, {})", x, y)));
+            }
+        }
+        if !(bubble.text_size > 0.0 && bubble.text_size <= 0.5) {
+            return Err(DomainError::InvalidInput(format!("Bubble text size must be in (0, 0.5]: {}", bubble.text_size)));
         }
+        Ok(BubbleOverlay {
+            text: bubble.text.clone(),
+            kind,
+            position: self.map_position_str_to_domain(&bubble.position),
+            margin: bubble.margin,
+            target: bubble.target,
+            tail,
+            text_size: bubble.text_size,
+            text_color: self.image_processor.parse_hex_color(&bubble.text_color_hex),
+            fill: self.image_processor.parse_hex_color(&bubble.fill_hex),
+            border: self.image_processor.parse_hex_color(&bubble.border_hex),
+            border_width: bubble.border_width,
+        })
+    }
+
+    fn build_qr_overlay(&self, qr: &QrRequest) -> Result<QrOverlay, DomainError> {
+        if qr.content.is_empty() {
+            return Err(DomainError::InvalidInput("QR content must not be empty".to_string()));
+        }
+        if qr.content.len() > MAX_QR_BYTES {
+            return Err(DomainError::InvalidInput(format!("QR content must be at most {} bytes: {}", MAX_QR_BYTES, qr.content.len())));
+        }
+        if !(qr.size > 0.0 && qr.size <= 1.0) {
+            return Err(DomainError::InvalidInput(format!("QR size must be in (0, 1]: {}", qr.size)));
+        }
+        if qr.quiet_zone > 16 {
+            return Err(DomainError::InvalidInput(format!("QR quiet zone must be at most 16 modules: {}", qr.quiet_zone)));
+        }
+        Ok(QrOverlay {
+            content: qr.content.clone(),
+            position: self.map_position_str_to_domain(&qr.position),
+            size: qr.size,
+            margin: qr.margin,
+            quiet_zone: qr.quiet_zone,
+            dark: self.image_processor.parse_hex_color(&qr.dark_hex),
+            light: self.image_processor.parse_hex_color(&qr.light_hex),
+        })
+    }
+
+    fn build_text_path(&self, path: &TextPathRequest) -> Result<TextPath, DomainError> {
+        match path.kind.to_lowercase().as_str() {
+            "straight" => Ok(TextPath::Straight),
+            "arc" => {
+                let radius = path.radius.unwrap_or(0.35);
+                // 円と文字が画像に収まるのは短辺の半分まで
+                if !(radius > 0.0 && radius <= 0.5) {
+                    return Err(DomainError::InvalidInput(format!("Arc radius must be in (0, 0.5]: {}", radius)));
+                }
+                Ok(TextPath::Arc { radius, angle: path.angle.unwrap_or(0.0) })
+            }
+            "bezier" => {
+                if !matches!(path.points.len(), 3 | 4) {
+                    return Err(DomainError::InvalidInput(format!(
+                        "Bezier path needs 3 or 4 control points, got {}",
+                        path.points.len()
+                    )));
+                }
+                if path.points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+                    return Err(DomainError::InvalidInput("Bezier control points must be finite".to_string()));
+                }
+                Ok(TextPath::Bezier(path.points.clone()))
+            }
+            _ => Err(DomainError::InvalidInput(format!("Unknown text path: {}", path.kind))),
+        }
+    }
+
+    // 縦書きは 1 文字ずつ縦に積むので、曲線に沿わせる指定とは一緒に使えない
+    fn map_writing_mode(&self, mode_str: &str, path: &TextPath) -> Result<WritingMode, DomainError> {
+        match mode_str.to_lowercase().as_str() {
+            "horizontal" => Ok(WritingMode::Horizontal),
+            "vertical" if *path == TextPath::Straight => Ok(WritingMode::Vertical),
+            "vertical" => Err(DomainError::InvalidInput("Vertical writing mode cannot be combined with a text path".to_string())),
+            _ => Err(DomainError::InvalidInput(format!("Unknown writing mode: {}", mode_str))),
+        }
+    }
+
+    fn build_text_effect(&self, effect: &TextEffectRequest) -> Result<TextEffect, DomainError> {
+        match effect.name.to_lowercase().as_str() {
+            "neon" => Ok(TextEffect::Neon {
+                color: self.image_processor.parse_hex_color(effect.color_hex.as_deref().unwrap_or("#FF3CACFF")),
+                stroke: effect.width.unwrap_or(2),
+                glow: effect.radius.unwrap_or(10.0),
+            }),
+            "3d" | "extrude" => Ok(TextEffect::Extrude {
+                depth: effect.width.unwrap_or(8),
+                angle: effect.angle.unwrap_or(45.0),
+                shade: effect.shade.unwrap_or(0.5),
+            }),
+            "rainbow" => Ok(TextEffect::Rainbow { hue_step: effect.step.unwrap_or(30.0) }),
+            "glitch" => Ok(TextEffect::Glitch {
+                offset: effect.width.unwrap_or(4),
+                slices: effect.slices.unwrap_or(6),
+                seed: effect.seed.unwrap_or(1),
+            }),
+            "outline" => Ok(TextEffect::Outline {
+                color: self.image_processor.parse_hex_color(effect.color_hex.as_deref().unwrap_or("#000000FF")),
+                width: effect.stroke.unwrap_or(0.06),
+            }),
+            _ => Err(DomainError::InvalidInput(format!("Unknown text effect: {}", effect.name))),
+        }
+    }
+
+    // 繰り返しは 1 行の横書きを格子に並べるので、曲線や縦書きとは一緒に使えない
+    fn build_tile_layout(&self, tile: &TileRequest, text_overlay: &TextOverlay) -> Result<TileLayout, DomainError> {
+        if text_overlay.path != TextPath::Straight || text_overlay.writing_mode != WritingMode::Horizontal {
+            return Err(DomainError::InvalidInput("Tiled text cannot be combined with a text path or vertical writing mode".to_string()));
+        }
+        let layout = TileLayout {
+            angle: tile.angle.unwrap_or(-30.0),
+            spacing: tile.spacing.unwrap_or(1.0),
+            opacity: tile.opacity.unwrap_or(0.3),
+        };
+        if !layout.angle.is_finite() {
+            return Err(DomainError::InvalidInput(format!("Invalid tile angle: {}", layout.angle)));
+        }
+        if !(0.0..=10.0).contains(&layout.spacing) {
+            return Err(DomainError::InvalidInput(format!("Tile spacing must be between 0 and 10: {}", layout.spacing)));
+        }
+        if !(0.0..=1.0).contains(&layout.opacity) {
+            return Err(DomainError::InvalidInput(format!("Tile opacity must be between 0 and 1: {}", layout.opacity)));
+        }
+        Ok(layout)
+    }
+
+    fn build_text_overlay(
+        &self,
+        text: &str,
+        color_hex: &str,
+        position: &str,
+        path: Option<&TextPathRequest>,
+        writing_mode: &str,
+        effect: Option<&TextEffectRequest>,
+    ) -> Result<TextOverlay, DomainError> {
+        let path = path.map(|p| self.build_text_path(p)).transpose()?.unwrap_or_default();
+        Ok(TextOverlay {
+            text: text.to_string(),
+            color: self.image_processor.parse_hex_color(color_hex),
+            position: self.map_position_str_to_domain(position),
+            writing_mode: self.map_writing_mode(writing_mode, &path)?,
+            path,
+            effect: effect.map(|e| self.build_text_effect(e)).transpose()?,
+            tile: None,
+            wrap: None,
+            subtitle: None,
+        })
+    }
+
+    fn build_extra_text(&self, extra: &ExtraTextRequest) -> Result<TextOverlay, DomainError> {
+        self.build_text_overlay(
+            &extra.text,
+            &extra.color_hex,
+            &extra.position,
+            extra.path.as_ref(),
+            &extra.writing_mode,
+            extra.effect.as_ref(),
+        )
+    }
+
+    fn build_render_options(&self, request: &LgtmRequest) -> Result<RenderOptions, ApplicationError> {
+        let resize = (request.width.is_some() || request.height.is_some()).then(|| Resize {
+            width: request.width,
+            height: request.height,
+            mode: self.map_resize_mode_str_to_domain(&request.resize_mode),
+        });
+        let transform = Transform {
+            crop: request.crop.as_deref().map(|c| self.parse_crop(c)).transpose()?,
+            aspect: request.aspect.as_deref().map(|a| self.parse_aspect(a)).transpose()?,
+            resize,
+            max_width: request.max_width,
+            max_height: request.max_height,
+            min_size: (request.min_size > 0).then(|| MinSize {
+                min_side: request.min_size,
+                filter: self.map_upscale_filter_str_to_domain(&request.upscale_filter),
+            }),
+            padding_color: self.image_processor.parse_hex_color(&request.padding_color_hex),
+        };
+        let mut overlays = request
+            .extra_texts
+            .iter()
+            .map(|t| self.build_extra_text(t).map(Overlay::Text))
+            .collect::<Result<Vec<_>, _>>()?;
+        for sticker in &request.stickers {
+            overlays.push(Overlay::Image(self.build_sticker_overlay(sticker)?));
+        }
+        if let Some(stamp) = &request.stamp {
+            overlays.push(Overlay::Stamp(self.build_stamp_overlay(stamp)?));
+        }
+        for bubble in &request.bubbles {
+            overlays.push(Overlay::Bubble(self.build_bubble_overlay(bubble)?));
+        }
+        if let Some(qr) = &request.qr {
+            overlays.push(Overlay::Qr(self.build_qr_overlay(qr)?));
+        }
+        if request.filters.len() > MAX_FILTERS {
+            return Err(DomainError::InvalidInput(format!("At most {} filters are allowed: {}", MAX_FILTERS, request.filters.len())).into());
+        }
+        let filters = request
+            .filters
+            .iter()
+            .map(|f| self.map_filter_to_domain(f))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RenderOptions {
+            transform,
+            filters,
+            text_backdrop: request.backdrop.as_ref().map(|b| self.map_backdrop_to_domain(b)),
+            overlays,
+            frame: self.build_frame(request)?,
+        })
+    }
+
+    // png8 / gif はパレット出力 (colors 未指定なら 256 色)。それ以外では colors は使わない
+    fn map_encode_settings(&self, request: &LgtmRequest, format: InnerImageFormat) -> EncodeSettings {
+        let palette_output = matches!(request.output_format.to_lowercase().as_str(), "png8" | "gif");
+        EncodeSettings {
+            max_colors: palette_output.then(|| request.colors.unwrap_or(256)),
+            dither: request.dither,
+            metadata_policy: self.map_metadata_str_to_policy(&request.metadata),
+            ..EncodeSettings::new(format)
+        }
+    }
+
+    // maxBytes を超えたときに試す設定の候補 (劣化の少ない順)
+    // JPEG は品質を下げ、PNG は減色し、それでも収まらなければ段階的に縮小する
+    fn budget_candidates(&self, base: &EncodeSettings) -> Vec<EncodeSettings> {
+        let mut candidates = Vec::new();
+        match base.format {
+            // ロスレスの WebP は品質も色数も選べないので縮小だけ
+            InnerImageFormat::WebP => {
+                for scale in [0.75, 0.5, 0.35, 0.25] {
+                    candidates.push(EncodeSettings { scale, ..base.clone() });
+                }
+            }
+            InnerImageFormat::Jpeg => {
+                for quality in [65, 50, 35, 20] {
+                    candidates.push(EncodeSettings { quality, ..base.clone() });
+                }
+                for scale in [0.75, 0.5, 0.35, 0.25] {
+                    candidates.push(EncodeSettings { quality: 50, scale, ..base.clone() });
+                }
+            }
+            _ => {
+                // 指定済みの色数 (png8 / gif) より増やすことはしない
+                let requested = base.max_colors.unwrap_or(u16::MAX);
+                for colors in [256, 128, 64, 32, 16].into_iter().filter(|&c| c < requested) {
+                    candidates.push(EncodeSettings { max_colors: Some(colors), ..base.clone() });
+                }
+                for scale in [0.75, 0.5, 0.35, 0.25] {
+                    candidates.push(EncodeSettings { max_colors: Some(requested.min(64)), scale, ..base.clone() });
+                }
+            }
+        }
+        candidates
+    }
+
+    // 劣化なしの PNG を元に、予算に収まる最初の設定で再エンコードする
+    fn fit_to_budget(
+        &self,
+        master: DomainImage,
+        settings: EncodeSettings,
+        max_bytes: usize,
+    ) -> Result<(DomainImage, EncodeSettings), ApplicationError> {
+        let mut smallest = usize::MAX;
+        let mut candidates = vec![settings.clone()];
+        candidates.extend(self.budget_candidates(&settings));
+
+        for candidate in candidates {
+            let image = self.image_processor.reencode_image(master.data.clone(), &candidate)?;
+            if image.data.len() <= max_bytes {
+                return Ok((image, candidate));
+            }
+            smallest = smallest.min(image.data.len());
+        }
+
+        Err(ApplicationError::OutputBudgetExceeded(format!(
+            "smallest output was {} bytes, budget is {} bytes",
+            smallest, max_bytes
+        )))
+    }
+
+    // 言葉を選ぶ seed。指定がなければ呼ぶたびに変わる
+    fn request_seed(&self, request: &LgtmRequest) -> u64 {
+        request.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+        })
+    }
+
+    // テキストに埋め込む値。{random_phrase} は seed を指定しなければ呼ぶたびに変わる
+    fn template_context(&self, request: &LgtmRequest, random_phrase: Option<String>) -> TemplateContext {
+        TemplateContext {
+            now: chrono::Local::now().naive_local(),
+            user: request.user.clone(),
+            pr: request.pr.clone(),
+            repo: request.repo.clone(),
+            random_phrase,
+        }
+    }
+
+    // "auto" は頭文字がテキストに合う辞書の最初の言葉、"random" は seed で選んだ言葉。それ以外はそのまま添える
+    fn build_subtitle(&self, subtitle: &str, request: &LgtmRequest, text_overlay: &TextOverlay) -> Result<Subtitle, DomainError> {
+        let plain = text_overlay.path == TextPath::Straight && text_overlay.writing_mode == WritingMode::Horizontal && text_overlay.tile.is_none();
+        if !plain {
+            return Err(DomainError::InvalidInput("Subtitle cannot be combined with a text path, vertical writing mode or tiling".to_string()));
+        }
+        let locale = request.locale.as_deref();
+        let text = match subtitle {
+            "auto" => self.phrases.pick_expansion(&request.text, locale, None)?.to_string(),
+            "random" => self.phrases.pick_expansion(&request.text, locale, Some(self.request_seed(request)))?.to_string(),
+            _ if subtitle.trim().is_empty() => return Err(DomainError::InvalidInput("Subtitle must not be empty".to_string())),
+            _ => subtitle.to_string(),
+        };
+        Ok(Subtitle { text, scale: SUBTITLE_SCALE })
+    }
+
+    // 画像に描く文字 (メインと追加のテキスト、吹き出し、判子、QR コード) のプレースホルダを埋めたリクエストを返す
+    // text が "random" なら辞書から選んだ言葉 ({random_phrase} と同じもの) にする
+    // subtitle が "auto" / "random" なら、選んだ言葉を下に添えてその頭文字を大きく描く (先に言葉を決めてから subtitle を作る)
+    fn expand_templates(&self, request: &LgtmRequest) -> Result<LgtmRequest, DomainError> {
+        let locale = request.locale.as_deref();
+        let seed = self.request_seed(request);
+        let acronym = request.text == "random" && matches!(request.subtitle.as_deref(), Some("auto" | "random"));
+        let (main_text, random_phrase) = if acronym {
+            let (initials, phrase) = self.phrases.pick_acronym(locale, seed)?;
+            (Some(initials), Some(phrase.to_string()))
+        } else {
+            (None, self.phrases.pick(locale, seed).ok().map(str::to_string))
+        };
+        let context = self.template_context(request, random_phrase);
+        let render = |text: &mut String| -> Result<(), DomainError> {
+            *text = render_template(text, &context).map_err(|e| DomainError::InvalidInput(e.to_string()))?;
+            Ok(())
+        };
+        let mut request = request.clone();
+        if let Some(main_text) = main_text {
+            request.text = main_text;
+            request.subtitle = context.random_phrase.clone();
+        } else if request.text == "random" {
+            request.text = context.random_phrase.clone().ok_or_else(|| {
+                DomainError::InvalidInput(format!("No phrases for locale: {}", request.locale.as_deref().unwrap_or_default()))
+            })?;
+        } else {
+            render(&mut request.text)?;
+        }
+        if let Some(subtitle) = request.subtitle.as_mut().filter(|s| !acronym && !matches!(s.as_str(), "auto" | "random")) {
+            render(subtitle)?;
+        }
+        for extra in &mut request.extra_texts {
+            render(&mut extra.text)?;
+        }
+        for bubble in &mut request.bubbles {
+            render(&mut bubble.text)?;
+        }
+        if let Some(stamp) = &mut request.stamp {
+            render(&mut stamp.text)?;
+            if let Some(name) = &mut stamp.name {
+                render(name)?;
+            }
+        }
+        if let Some(qr) = &mut request.qr {
+            render(&mut qr.content)?;
+        }
+        Ok(request)
     }
 
     pub async fn generate_lgtm_image(
         &self,
         image_data: Vec<u8>,
-        text: String,
-        text_color_hex: String,
-        text_position_str: String,
-        output_format_str: String, // 出力フォーマット指定を追加
-    ) -> Result<(Vec<u8>, &'static str), ApplicationError> { // Changed to ApplicationError
-        println!("LgtmService: generate_lgtm_image called with format: {}", output_format_str);
+        request: &LgtmRequest,
+    ) -> Result<LgtmOutput, ApplicationError> { // Changed to ApplicationError
+        println!("LgtmService: generate_lgtm_image called with format: {}", request.output_format);
+        let request = &self.expand_templates(request)?;
+
+        let mut text_overlay = self.build_text_overlay(
+            &request.text,
+            &request.text_color_hex,
+            &request.text_position,
+            request.text_path.as_ref(),
+            &request.writing_mode,
+            request.text_effect.as_ref(),
+        )?;
+        let tile = match (&request.tile, request.text_position.eq_ignore_ascii_case("tiled")) {
+            (Some(tile), _) => Some(tile.clone()),
+            (None, true) => Some(TileRequest::default()),
+            (None, false) => None,
+        };
+        if let Some(tile) = tile {
+            text_overlay.tile = Some(self.build_tile_layout(&tile, &text_overlay)?);
+        }
+        if let Some(subtitle) = &request.subtitle {
+            text_overlay.subtitle = Some(self.build_subtitle(subtitle, request, &text_overlay)?);
+        }
+
+        let render_options = self.build_render_options(request)?;
+        self.render(image_data, request, &text_overlay, render_options).await
+    }
 
-        let color = self.image_processor.parse_hex_color(&text_color_hex);
-        let position = self.map_position_str_to_domain(&text_position_str);
+    // ミームのキャプション 1 つ分。大文字にして、白い文字を黒いふちで囲み、高さの 3 割に収まるよう折り返して縮める
+    fn build_meme_caption(&self, text: &str, position: DomainPosition) -> TextOverlay {
+        TextOverlay {
+            effect: Some(TextEffect::Outline { color: self.image_processor.parse_hex_color("#000000FF"), width: 0.06 }),
+            wrap: Some(TextWrap { max_height: 0.3 }),
+            ..TextOverlay::new(text.trim().to_uppercase(), self.image_processor.parse_hex_color("#FFFFFFFF"), position)
+        }
+    }
 
-        let text_overlay = TextOverlay {
-            text,
-            color,
-            position,
+    // 上のキャプションをメインのテキスト、下のキャプションを追加のテキストにして 1 回で描く
+    pub async fn generate_meme(&self, image_data: Vec<u8>, request: &MemeRequest) -> Result<LgtmOutput, ApplicationError> {
+        if request.top.trim().is_empty() && request.bottom.trim().is_empty() {
+            return Err(DomainError::InvalidInput("Meme needs a top or bottom caption".to_string()).into());
+        }
+        let base = LgtmRequest {
+            output_format: request.output_format.clone(),
+            max_bytes: request.max_bytes,
+            ..LgtmRequest::default()
         };
+        let top = self.build_meme_caption(&request.top, DomainPosition::TopCenter);
+        let mut render_options = self.build_render_options(&base)?;
+        render_options.overlays.push(Overlay::Text(self.build_meme_caption(&request.bottom, DomainPosition::BottomCenter)));
+        self.render(image_data, &base, &top, render_options).await
+    }
 
-        let (output_format_enum, content_type) = self.map_format_str_to_enum(&output_format_str);
+    pub async fn generate_meme_from_url(&self, image_url: String, request: &MemeRequest) -> Result<LgtmOutput, ApplicationError> {
+        let image_fetcher = DefaultExternalImageFetcher::new();
+        let image_data = image_fetcher.fetch_image_from_url_impl(&image_url).await?;
+        self.generate_meme(image_data, request).await
+    }
 
-        let processed_image_bytes = self.image_processor.add_text_to_image(
-            image_data,
-            None, // image_data からフォーマットを推測させる
-            &text_overlay,
-            output_format_enum,
-        )?;
+    // 組み立てたテキストと描画オプションで描き、出力形式とサイズの上限に合わせてエンコードする
+    async fn render(
+        &self,
+        image_data: Vec<u8>,
+        request: &LgtmRequest,
+        text_overlay: &TextOverlay,
+        mut render_options: RenderOptions,
+    ) -> Result<LgtmOutput, ApplicationError> {
+        if let Some(logo) = &request.logo {
+            render_options.overlays.push(Overlay::Image(self.build_logo_overlay(logo).await?));
+        }
+        let (output_format_enum, content_type) = match self.map_format_str_to_enum(&request.output_format) {
+            // 角丸の透明な切り抜きは JPEG では表せないので PNG にする (PNG / WebP / GIF はそのまま)
+            (InnerImageFormat::Jpeg, _) if render_options.frame.needs_alpha() => (InnerImageFormat::Png, "image/png"),
+            format => format,
+        };
+        let settings = self.map_encode_settings(request, output_format_enum);
+
+        let (image, settings) = match request.max_bytes {
+            None => {
+                let image = self.image_processor.add_text_to_image(
+                    image_data,
+                    None, // image_data からフォーマットを推測させる
+                    text_overlay,
+                    &render_options,
+                    &settings,
+                )?;
+                (image, settings)
+            }
+            Some(max_bytes) => {
+                // 再エンコードで劣化が重ならないよう、まず PNG で描画しておく
+                let master = self.image_processor.add_text_to_image(
+                    image_data,
+                    None,
+                    text_overlay,
+                    &render_options,
+                    &EncodeSettings {
+                        metadata_policy: settings.metadata_policy,
+                        ..EncodeSettings::new(InnerImageFormat::Png)
+                    },
+                )?;
+                self.fit_to_budget(master, settings, max_bytes)?
+            }
+        };
+
+        // /upload / /fetch / /meme のどれで作ってもギャラリーに残す
+        let gallery_id = match &self.gallery {
+            Some(gallery) => Some(gallery.save(&image.data, content_type).await?),
+            None => None,
+        };
+        Ok(LgtmOutput {
+            data: image.data,
+            content_type,
+            width: image.width,
+            height: image.height,
+            settings,
+            gallery_id,
+        })
+    }
 
-        Ok((processed_image_bytes, content_type))
+    fn build_collage(&self, collage: &CollageRequest, count: usize) -> Result<Collage, DomainError> {
+        if count == 0 || count > MAX_COLLAGE_IMAGES {
+            return Err(DomainError::InvalidInput(format!("Collage needs 1 to {} images: {}", MAX_COLLAGE_IMAGES, count)));
+        }
+        let layout = match collage.layout.to_lowercase().as_str() {
+            "grid" => {
+                let columns = collage.columns.unwrap_or_else(|| (count as f32).sqrt().ceil() as u32);
+                if columns == 0 || columns as usize > MAX_COLLAGE_IMAGES {
+                    return Err(DomainError::InvalidInput(format!("Collage columns must be between 1 and {}: {}", MAX_COLLAGE_IMAGES, columns)));
+                }
+                CollageLayout::Grid { columns }
+            }
+            "strip" => CollageLayout::Strip,
+            _ => return Err(DomainError::InvalidInput(format!("Unknown collage layout: {}", collage.layout))),
+        };
+        if collage.gutter > MAX_COLLAGE_GUTTER {
+            return Err(DomainError::InvalidInput(format!("Collage gutter must be at most {}: {}", MAX_COLLAGE_GUTTER, collage.gutter)));
+        }
+        Ok(Collage {
+            layout,
+            gutter: collage.gutter,
+            background: self.image_processor.parse_hex_color(&collage.background_hex),
+        })
+    }
+
+    // 複数の画像を 1 枚に並べてから、1 枚の画像と同じようにテキストなどを描く
+    pub async fn generate_collage(
+        &self,
+        images: Vec<Vec<u8>>,
+        collage: &CollageRequest,
+        request: &LgtmRequest,
+    ) -> Result<LgtmOutput, ApplicationError> {
+        let collage = self.build_collage(collage, images.len())?;
+        let composed = self.image_processor.compose_collage(images, &collage)?;
+        self.generate_lgtm_image(composed.data, request).await
+    }
+
+    fn gallery(&self) -> Result<&LocalGalleryStore, DomainError> {
+        self.gallery.as_ref().ok_or_else(|| DomainError::InvalidInput("Gallery is not enabled".to_string()))
+    }
+
+    pub async fn load_from_gallery(&self, id: &str) -> Result<(Vec<u8>, &'static str), ApplicationError> {
+        Ok(self.gallery()?.load(id).await?)
+    }
+
+    // ギャラリーの最近の画像を新しい順に、ID を添えたサムネイルで並べた 1 枚 (PNG)
+    pub async fn generate_contact_sheet(&self, request: &ContactSheetRequest) -> Result<LgtmOutput, ApplicationError> {
+        if request.count == 0 || request.count > MAX_COLLAGE_IMAGES {
+            return Err(DomainError::InvalidInput(format!("Contact sheet count must be between 1 and {}: {}", MAX_COLLAGE_IMAGES, request.count)).into());
+        }
+        if !(MIN_THUMBNAIL_SIZE..=MAX_THUMBNAIL_SIZE).contains(&request.thumbnail_size) {
+            return Err(DomainError::InvalidInput(format!(
+                "Thumbnail size must be between {} and {}: {}",
+                MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE, request.thumbnail_size
+            ))
+            .into());
+        }
+        let gallery = self.gallery()?;
+        let ids = gallery.list_recent(request.count).await?;
+        let mut images = Vec::with_capacity(ids.len());
+        for id in ids {
+            // 一覧を取ったあとに消された画像など、読めないものは飛ばす
+            if let Ok((data, _)) = gallery.load(&id).await {
+                images.push((id, data));
+            }
+        }
+        if images.is_empty() {
+            return Err(DomainError::InvalidInput("Gallery has no images yet".to_string()).into());
+        }
+        // 並べ方の検証はコラージュと同じ (グリッドのみ)
+        let collage_request = CollageRequest {
+            layout: "grid".to_string(),
+            columns: request.columns,
+            gutter: request.gutter,
+            background_hex: request.background_hex.clone(),
+        };
+        let sheet = ContactSheet {
+            collage: self.build_collage(&collage_request, images.len())?,
+            thumbnail_size: request.thumbnail_size,
+            label_color: self.image_processor.parse_hex_color(&request.label_color_hex),
+        };
+        let image = self.image_processor.render_contact_sheet(images, &sheet)?;
+        Ok(LgtmOutput {
+            data: image.data,
+            content_type: "image/png",
+            width: image.width,
+            height: image.height,
+            settings: EncodeSettings::new(InnerImageFormat::Png),
+            gallery_id: None,
+        })
     }
 
     pub async fn generate_lgtm_image_from_url(
         &self,
         image_url: String,
-        text: String,
-        text_color_hex: String,
-        text_position_str: String,
-        output_format_str: String,
-    ) -> Result<(Vec<u8>, &'static str), ApplicationError> { // Changed to ApplicationError
+        request: &LgtmRequest,
+    ) -> Result<LgtmOutput, ApplicationError> { // Changed to ApplicationError
         println!("LgtmService: generate_lgtm_image_from_url called for URL: {}", image_url);
 
         // インフラ層の具体的な fetcher を直接利用 (DIするのが望ましい)
         let image_fetcher = DefaultExternalImageFetcher::new();
         let image_data = image_fetcher.fetch_image_from_url_impl(&image_url).await?;
 
-        self.generate_lgtm_image(
-            image_data,
-            text,
-            text_color_hex,
-            text_position_str,
-            output_format_str
-        ).await
+        self.generate_lgtm_image(image_data, request).await
     }
 }
 
@@ -104,36 +889,102 @@ mod tests {
     use crate::domain::image_processor_trait::ImageProcessor;
     use crate::infrastructure::error::InfrastructureError; // ImageProcessorモックが返すエラー用
     use crate::domain::text_overlay::TextOverlay as DomainTextOverlayFull; // Renamed to avoid conflict
+    use crate::domain::color::Color as DomainColor;
     use image::ImageFormat as InnerImageFormat; // モック内で使うため
     use std::sync::{Arc, Mutex};
 
+    // render_contact_sheet に渡された ID の並びと指定
+    type ContactSheetCall = (Vec<String>, ContactSheet);
+
     // 手動モック: ImageProcessor トレイトのテスト用実装
     #[derive(Clone)]
     struct MockImageProcessor {
         add_text_result: Arc<Mutex<Result<Vec<u8>, String>>>, // Error type is String for easier mocking
         parse_color_result: Arc<Mutex<DomainColor>>,
         add_text_called: Arc<Mutex<bool>>,
-        last_text_overlay: Arc<Mutex<Option<DomainTextOverlayFull>>>
+        last_text_overlay: Arc<Mutex<Option<DomainTextOverlayFull>>>,
+        reencode_calls: Arc<Mutex<Vec<EncodeSettings>>>,
+        last_render_options: Arc<Mutex<Option<RenderOptions>>>,
+        last_image_bytes: Arc<Mutex<Option<Vec<u8>>>>,
+        last_collage: Arc<Mutex<Option<(usize, Collage)>>>,
+        last_contact_sheet: Arc<Mutex<Option<ContactSheetCall>>>,
+    }
+
+    // 変換は [1, 2, 3] を返して成功し、色は黒になる。テストごとに必要なフィールドだけ上書きする
+    impl Default for MockImageProcessor {
+        fn default() -> Self {
+            Self {
+                add_text_result: Arc::new(Mutex::new(Ok(vec![1, 2, 3]))),
+                parse_color_result: Arc::new(Mutex::new(DomainColor::new(0,0,0,255))),
+                add_text_called: Arc::new(Mutex::new(false)),
+                last_text_overlay: Arc::new(Mutex::new(None)),
+                reencode_calls: Arc::new(Mutex::new(Vec::new())),
+                last_render_options: Arc::new(Mutex::new(None)),
+                last_image_bytes: Arc::new(Mutex::new(None)),
+                last_collage: Arc::new(Mutex::new(None)),
+                last_contact_sheet: Arc::new(Mutex::new(None)),
+            }
+        }
     }
 
     impl ImageProcessor for MockImageProcessor {
         fn add_text_to_image(
             &self,
-            _image_bytes: Vec<u8>,
+            image_bytes: Vec<u8>,
             _input_format_opt: Option<InnerImageFormat>,
             text_overlay: &DomainTextOverlayFull,
-            _output_format: InnerImageFormat,
-        ) -> Result<Vec<u8>, InfrastructureError> {
+            render_options: &RenderOptions,
+            encode_settings: &EncodeSettings,
+        ) -> Result<DomainImage, InfrastructureError> {
+            *self.last_image_bytes.lock().unwrap() = Some(image_bytes);
+            *self.last_render_options.lock().unwrap() = Some(render_options.clone());
             let mut called_flag = self.add_text_called.lock().unwrap();
             *called_flag = true;
             let mut last_overlay_lock = self.last_text_overlay.lock().unwrap();
             *last_overlay_lock = Some(text_overlay.clone());
 
             self.add_text_result.lock().unwrap().as_ref()
-                .map(|v| v.clone())
+                .map(|v| DomainImage::new(v.clone(), 100, 100, encode_settings.format))
                 .map_err(|s| InfrastructureError::ImageProcessingError(s.clone()))
         }
 
+        // 設定に応じて出力サイズが小さくなったふりをする
+        fn reencode_image(
+            &self,
+            image_bytes: Vec<u8>,
+            encode_settings: &EncodeSettings,
+        ) -> Result<DomainImage, InfrastructureError> {
+            self.reencode_calls.lock().unwrap().push(encode_settings.clone());
+            let quality_factor = match encode_settings.format {
+                InnerImageFormat::Jpeg => encode_settings.quality as f32 / 100.0,
+                _ => encode_settings.max_colors.map_or(1.0, |c| c as f32 / 256.0),
+            };
+            let size_factor = quality_factor * encode_settings.scale * encode_settings.scale;
+            let len = (image_bytes.len() as f32 * size_factor) as usize;
+            let side = (100.0 * encode_settings.scale) as u32;
+            Ok(DomainImage::new(vec![0; len], side, side, encode_settings.format))
+        }
+
+        // 並べたふりをして、画像をつなげたバイト列を返す
+        fn compose_collage(
+            &self,
+            images: Vec<Vec<u8>>,
+            collage: &Collage,
+        ) -> Result<DomainImage, InfrastructureError> {
+            *self.last_collage.lock().unwrap() = Some((images.len(), collage.clone()));
+            Ok(DomainImage::new(images.concat(), 100, 100, InnerImageFormat::Png))
+        }
+
+        fn render_contact_sheet(
+            &self,
+            images: Vec<(String, Vec<u8>)>,
+            sheet: &ContactSheet,
+        ) -> Result<DomainImage, InfrastructureError> {
+            let (ids, data): (Vec<String>, Vec<Vec<u8>>) = images.into_iter().unzip();
+            *self.last_contact_sheet.lock().unwrap() = Some((ids, sheet.clone()));
+            Ok(DomainImage::new(data.concat(), 100, 100, InnerImageFormat::Png))
+        }
+
         fn parse_hex_color(&self, _hex_str: &str) -> DomainColor {
             self.parse_color_result.lock().unwrap().clone()
         }
@@ -141,28 +992,22 @@ mod tests {
 
     #[tokio::test]
     async fn test_generate_lgtm_image_success() {
-        let mock_image_processor = Arc::new(MockImageProcessor {
-            add_text_result: Arc::new(Mutex::new(Ok(vec![1, 2, 3]))),
-            parse_color_result: Arc::new(Mutex::new(DomainColor::new(0,0,0,255))),
-            add_text_called: Arc::new(Mutex::new(false)),
-            last_text_overlay: Arc::new(Mutex::new(None)),
-        });
+        let mock_image_processor = Arc::new(MockImageProcessor::default());
 
         let service = LgtmService::new(mock_image_processor.clone());
 
         let image_data = vec![4, 5, 6];
-        let result = service.generate_lgtm_image(
-            image_data,
-            "Test".to_string(),
-            "#000000".to_string(),
-            "center".to_string(),
-            "png".to_string()
-        ).await;
+        let request = LgtmRequest {
+            text: "Test".to_string(),
+            text_color_hex: "#000000".to_string(),
+            ..LgtmRequest::default()
+        };
+        let result = service.generate_lgtm_image(image_data, &request).await;
 
         assert!(result.is_ok());
-        let (data, content_type) = result.unwrap();
-        assert_eq!(data, vec![1, 2, 3]);
-        assert_eq!(content_type, "image/png");
+        let output = result.unwrap();
+        assert_eq!(output.data, vec![1, 2, 3]);
+        assert_eq!(output.content_type, "image/png");
         assert!(*mock_image_processor.add_text_called.lock().unwrap());
 
         let overlay_used = mock_image_processor.last_text_overlay.lock().unwrap();
@@ -173,21 +1018,18 @@ mod tests {
     #[tokio::test]
     async fn test_generate_lgtm_image_processor_fails() {
         let mock_image_processor = Arc::new(MockImageProcessor {
-             add_text_result: Arc::new(Mutex::new(Err("mock processing error".to_string()))),
-             parse_color_result: Arc::new(Mutex::new(DomainColor::new(0,0,0,255))),
-             add_text_called: Arc::new(Mutex::new(false)),
-             last_text_overlay: Arc::new(Mutex::new(None)),
+            add_text_result: Arc::new(Mutex::new(Err("mock processing error".to_string()))),
+            ..MockImageProcessor::default()
         });
         let service = LgtmService::new(mock_image_processor);
 
         let image_data = vec![4, 5, 6];
-        let result = service.generate_lgtm_image(
-            image_data,
-            "Test".to_string(),
-            "#000000".to_string(),
-            "center".to_string(),
-            "png".to_string()
-        ).await;
+        let request = LgtmRequest {
+            text: "Test".to_string(),
+            text_color_hex: "#000000".to_string(),
+            ..LgtmRequest::default()
+        };
+        let result = service.generate_lgtm_image(image_data, &request).await;
 
         assert!(result.is_err());
         match result.err().unwrap() {
@@ -202,4 +1044,830 @@ mod tests {
             e => panic!("Expected ApplicationError::InfrastructureError, got {:?}", e),
         }
     }
+
+    fn budget_mock(master_len: usize) -> Arc<MockImageProcessor> {
+        Arc::new(MockImageProcessor {
+            add_text_result: Arc::new(Mutex::new(Ok(vec![0; master_len]))),
+            ..MockImageProcessor::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_within_budget_skips_reencode() {
+        let mock_image_processor = budget_mock(1000);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest { max_bytes: None, ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+
+        assert_eq!(output.data.len(), 1000);
+        assert!(mock_image_processor.reencode_calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_reduces_jpeg_quality_to_fit_budget() {
+        let mock_image_processor = budget_mock(1000);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        // 品質 75 で 750 バイト、65 で 650 バイト → 700 に収まるのは 65
+        let request = LgtmRequest {
+            output_format: "jpeg".to_string(),
+            max_bytes: Some(700),
+            ..LgtmRequest::default()
+        };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+
+        assert!(output.data.len() <= 700);
+        assert_eq!(output.content_type, "image/jpeg");
+        assert_eq!(output.settings.quality, 65);
+        assert_eq!(output.settings.scale, 1.0);
+        assert_eq!((output.width, output.height), (100, 100));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_downscales_png_when_palette_is_not_enough() {
+        let mock_image_processor = budget_mock(1000);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        // 16 色まで減らしても 62 バイト → 縮小 (64 色 × 0.25 倍 = 15 バイト) まで進む
+        let request = LgtmRequest { max_bytes: Some(20), ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+
+        assert!(output.data.len() <= 20);
+        assert_eq!(output.settings.max_colors, Some(64));
+        assert_eq!(output.settings.scale, 0.25);
+        assert_eq!((output.width, output.height), (25, 25));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_fails_when_budget_unreachable() {
+        let mock_image_processor = budget_mock(1000);
+        let service = LgtmService::new(mock_image_processor);
+
+        let request = LgtmRequest { max_bytes: Some(1), ..LgtmRequest::default() };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+
+        match result {
+            Err(ApplicationError::OutputBudgetExceeded(_)) => {}
+            other => panic!("Expected OutputBudgetExceeded, got {:?}", other.map(|o| o.data.len())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_png8_and_gif_use_palette() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor);
+
+        let request = LgtmRequest { output_format: "png8".to_string(), colors: Some(32), dither: true, ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.content_type, "image/png");
+        assert_eq!(output.settings.max_colors, Some(32));
+        assert!(output.settings.dither);
+
+        let request = LgtmRequest { output_format: "gif".to_string(), ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.content_type, "image/gif");
+        assert_eq!(output.settings.format, InnerImageFormat::Gif);
+        assert_eq!(output.settings.max_colors, Some(256));
+
+        // 通常の png では colors を指定しても減色しない
+        let request = LgtmRequest { colors: Some(8), ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.settings.max_colors, None);
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_metadata_policy() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor);
+
+        let output = service.generate_lgtm_image(vec![1], &LgtmRequest::default()).await.unwrap();
+        assert_eq!(output.settings.metadata_policy, MetadataPolicy::StripAll);
+
+        let request = LgtmRequest { metadata: "icc".to_string(), ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.settings.metadata_policy, MetadataPolicy::PreserveIcc);
+
+        let request = LgtmRequest { metadata: "all-except-gps".to_string(), max_bytes: Some(100), ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.settings.metadata_policy, MetadataPolicy::PreserveAllExceptGps);
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_builds_transform() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            crop: Some("10, 20, 300, 200".to_string()),
+            aspect: Some("16:9".to_string()),
+            width: Some(400),
+            resize_mode: "contain".to_string(),
+            max_height: Some(300),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+
+        let options = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap();
+        let transform = options.transform;
+        assert_eq!(transform.crop, Some(CropRect { x: 10, y: 20, width: 300, height: 200 }));
+        assert_eq!(transform.aspect, Some(AspectRatio::WIDE));
+        assert_eq!(transform.resize, Some(Resize { width: Some(400), height: None, mode: ResizeMode::Contain }));
+        assert_eq!(transform.max_height, Some(300));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_rejects_malformed_transform() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        for request in [
+            LgtmRequest { aspect: Some("wide-ish".to_string()), ..LgtmRequest::default() },
+            LgtmRequest { aspect: Some("0:1".to_string()), ..LgtmRequest::default() },
+            LgtmRequest { crop: Some("1,2,3".to_string()), ..LgtmRequest::default() },
+        ] {
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+        assert!(!*mock_image_processor.add_text_called.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_min_size_policy() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        // 指定しなければ拡大しない (これまでの出力を変えない)
+        service.generate_lgtm_image(vec![1], &LgtmRequest::default()).await.unwrap();
+        let transform = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().transform;
+        assert_eq!(transform.min_size, None);
+
+        let request = LgtmRequest { min_size: 256, ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let transform = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().transform;
+        assert_eq!(transform.min_size, Some(MinSize { min_side: 256, filter: UpscaleFilter::Auto }));
+
+        let request = LgtmRequest { min_size: 64, upscale_filter: "nearest".to_string(), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let transform = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().transform;
+        assert_eq!(transform.min_size, Some(MinSize { min_side: 64, filter: UpscaleFilter::Nearest }));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_filters_in_order() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            filters: vec![
+                FilterRequest { name: "Grayscale".to_string(), amount: None },
+                FilterRequest { name: "blur".to_string(), amount: Some(5.0) },
+                FilterRequest { name: "darken".to_string(), amount: None },
+            ],
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let options = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap();
+        assert_eq!(options.filters, vec![
+            ImageFilter::Grayscale { amount: 1.0 },
+            ImageFilter::Blur { sigma: 5.0 },
+            ImageFilter::Darken { opacity: 0.3 },
+        ]);
+
+        let request = LgtmRequest {
+            filters: vec![FilterRequest { name: "posterize".to_string(), amount: None }],
+            ..LgtmRequest::default()
+        };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+
+        let request = LgtmRequest {
+            filters: vec![FilterRequest { name: "blur".to_string(), amount: Some(1.0) }; MAX_FILTERS + 1],
+            ..LgtmRequest::default()
+        };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_backdrop_defaults() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        service.generate_lgtm_image(vec![1], &LgtmRequest::default()).await.unwrap();
+        let options = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap();
+        assert_eq!(options.text_backdrop, None);
+
+        let request = LgtmRequest {
+            backdrop: Some(BackdropRequest { darken: Some(0.6), feather: Some(0), ..BackdropRequest::default() }),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let options = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap();
+        assert_eq!(options.text_backdrop, Some(TextBackdrop { blur_sigma: 8.0, darken: 0.6, padding: 16, feather: 0 }));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_frame() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            frame: Some("Polaroid".to_string()),
+            border_width: 8,
+            border_gradient_hex: Some("#000000".to_string()),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let frame = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().frame;
+        let color = mock_image_processor.parse_color_result.lock().unwrap().clone();
+        assert_eq!(frame.polaroid, Some(Polaroid { color: color.clone() }));
+        assert_eq!(frame.border, Some(Border { width: 8, fill: BorderFill::Gradient { top: color.clone(), bottom: color } }));
+        assert_eq!(frame.corner_radius, 0);
+
+        let request = LgtmRequest { frame: Some("scrapbook".to_string()), ..LgtmRequest::default() };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_rounded_corners_force_png() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest { output_format: "jpeg".to_string(), corner_radius: 16, ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.content_type, "image/png");
+        assert_eq!(output.settings.format, InnerImageFormat::Png);
+
+        // gif は透明色を持てるのでそのまま
+        let request = LgtmRequest { output_format: "gif".to_string(), corner_radius: 16, ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.content_type, "image/gif");
+
+        // WebP (ロスレス) もアルファを持てるのでそのまま
+        let request = LgtmRequest { output_format: "webp".to_string(), corner_radius: 16, ..LgtmRequest::default() };
+        let output = service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(output.content_type, "image/webp");
+        assert_eq!(output.settings.format, InnerImageFormat::WebP);
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_adds_logo_overlay() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            logo: Some(LogoRequest { position: "top-left".to_string(), opacity: 2.0, ..LogoRequest::new(LogoSource::Upload(vec![7, 7])) }),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        assert_eq!(overlays, vec![Overlay::Image(ImageOverlay {
+            image_bytes: vec![7, 7],
+            position: DomainPosition::TopLeft,
+            scale: 0.2,
+            opacity: 1.0,
+            margin: 16,
+        })]);
+
+        let request = LgtmRequest {
+            logo: Some(LogoRequest { scale: 0.0, ..LogoRequest::new(LogoSource::Upload(vec![7])) }),
+            ..LgtmRequest::default()
+        };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_loads_logo_from_assets() {
+        let dir = std::env::temp_dir().join(format!("lgtm-service-assets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("team.png"), b"team-logo").unwrap();
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone()).with_asset_store(LocalAssetStore::new(&dir));
+
+        let request = LgtmRequest { logo: Some(LogoRequest::new(LogoSource::Asset("team".to_string()))), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        assert!(matches!(&overlays[..], [Overlay::Image(o)] if o.image_bytes == b"team-logo"));
+
+        let request = LgtmRequest { logo: Some(LogoRequest::new(LogoSource::Asset("nope".to_string()))), ..LgtmRequest::default() };
+        assert!(service.generate_lgtm_image(vec![1], &request).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_places_stickers() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            stickers: vec![
+                StickerRequest::new(":+1:".to_string()),
+                StickerRequest { position: "bottom-left".to_string(), ..StickerRequest::new("tada".to_string()) },
+            ],
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        let placed: Vec<_> = overlays
+            .iter()
+            .filter_map(|overlay| match overlay {
+                Overlay::Image(o) => Some((o.image_bytes.as_slice(), o.position.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(placed, vec![
+            (sticker_png("thumbsup").unwrap(), DomainPosition::TopRight),
+            (sticker_png("tada").unwrap(), DomainPosition::BottomLeft),
+        ]);
+
+        let request = LgtmRequest { stickers: vec![StickerRequest::new("party_parrot".to_string())], ..LgtmRequest::default() };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_stamp() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            stamp: Some(StampRequest {
+                text: "承認".to_string(),
+                name: Some("佐藤".to_string()),
+                date: Some("today".to_string()),
+                layout: "vertical".to_string(),
+                shape: "Square".to_string(),
+                ..StampRequest::default()
+            }),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        let [Overlay::Stamp(stamp)] = &overlays[..] else { panic!("{:?}", overlays) };
+        assert_eq!((stamp.shape, stamp.layout), (StampShape::RoundedSquare, StampLayout::Vertical));
+        assert_eq!(stamp.position, DomainPosition::BottomRight);
+        assert_eq!(stamp.date, Some(chrono::Local::now().format("%Y.%m.%d").to_string()));
+
+        for stamp in [
+            StampRequest { shape: "star".to_string(), ..StampRequest::default() },
+            StampRequest { layout: "spiral".to_string(), ..StampRequest::default() },
+            StampRequest { scale: 1.5, ..StampRequest::default() },
+        ] {
+            let request = LgtmRequest { stamp: Some(stamp), ..LgtmRequest::default() };
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_text_paths() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            text_path: Some(TextPathRequest {
+                kind: "bezier".to_string(),
+                points: vec![(0.1, 0.8), (0.5, 0.2), (0.9, 0.8)],
+                ..TextPathRequest::default()
+            }),
+            extra_texts: vec![ExtraTextRequest {
+                text: "LOOKS GOOD TO ME".to_string(),
+                color_hex: "#FFFFFF".to_string(),
+                position: "center".to_string(),
+                path: Some(TextPathRequest { kind: "Arc".to_string(), ..TextPathRequest::default() }),
+                writing_mode: "horizontal".to_string(),
+                effect: None,
+            }],
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        assert_eq!(text_overlay.path, TextPath::Bezier(vec![(0.1, 0.8), (0.5, 0.2), (0.9, 0.8)]));
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        let [Overlay::Text(extra)] = &overlays[..] else { panic!("{:?}", overlays) };
+        assert_eq!(extra.path, TextPath::Arc { radius: 0.35, angle: 0.0 });
+
+        for path in [
+            TextPathRequest { kind: "spiral".to_string(), ..TextPathRequest::default() },
+            TextPathRequest { kind: "arc".to_string(), radius: Some(0.8), ..TextPathRequest::default() },
+            TextPathRequest { kind: "bezier".to_string(), points: vec![(0.0, 0.0), (1.0, 1.0)], ..TextPathRequest::default() },
+        ] {
+            let request = LgtmRequest { text_path: Some(path), ..LgtmRequest::default() };
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_writing_mode() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest { writing_mode: "Vertical".to_string(), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        assert_eq!(text_overlay.writing_mode, WritingMode::Vertical);
+
+        let arc = TextPathRequest { kind: "arc".to_string(), ..TextPathRequest::default() };
+        for request in [
+            LgtmRequest { writing_mode: "diagonal".to_string(), ..LgtmRequest::default() },
+            LgtmRequest { writing_mode: "vertical".to_string(), text_path: Some(arc), ..LgtmRequest::default() },
+        ] {
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_text_effects() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let cases = [
+            (TextEffectRequest { name: "3D".to_string(), width: Some(12), ..TextEffectRequest::default() }, TextEffect::Extrude { depth: 12, angle: 45.0, shade: 0.5 }),
+            (TextEffectRequest { name: "rainbow".to_string(), ..TextEffectRequest::default() }, TextEffect::Rainbow { hue_step: 30.0 }),
+            (TextEffectRequest { name: "glitch".to_string(), seed: Some(42), ..TextEffectRequest::default() }, TextEffect::Glitch { offset: 4, slices: 6, seed: 42 }),
+            (
+                TextEffectRequest { name: "neon".to_string(), color_hex: Some("#00FFFF".to_string()), ..TextEffectRequest::default() },
+                TextEffect::Neon { color: DomainColor::new(0, 0, 0, 255), stroke: 2, glow: 10.0 }, // モックの parse_hex_color は固定の色を返す
+            ),
+        ];
+        for (effect, expected) in cases {
+            let request = LgtmRequest { text_effect: Some(effect), ..LgtmRequest::default() };
+            service.generate_lgtm_image(vec![1], &request).await.unwrap();
+            let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+            assert_eq!(text_overlay.effect, Some(expected));
+        }
+
+        let request = LgtmRequest {
+            text_effect: Some(TextEffectRequest { name: "sparkle".to_string(), ..TextEffectRequest::default() }),
+            ..LgtmRequest::default()
+        };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_tiled_position() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        // "tiled" だけなら既定値で繰り返す
+        let request = LgtmRequest { text_position: "Tiled".to_string(), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        assert_eq!(text_overlay.tile, Some(TileLayout { angle: -30.0, spacing: 1.0, opacity: 0.3 }));
+
+        let request = LgtmRequest {
+            tile: Some(TileRequest { angle: Some(45.0), opacity: Some(0.5), ..TileRequest::default() }),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        assert_eq!(text_overlay.tile, Some(TileLayout { angle: 45.0, spacing: 1.0, opacity: 0.5 }));
+
+        for request in [
+            LgtmRequest { tile: Some(TileRequest { opacity: Some(1.5), ..TileRequest::default() }), ..LgtmRequest::default() },
+            LgtmRequest { tile: Some(TileRequest { spacing: Some(-1.0), ..TileRequest::default() }), ..LgtmRequest::default() },
+            LgtmRequest { text_position: "tiled".to_string(), writing_mode: "vertical".to_string(), ..LgtmRequest::default() },
+        ] {
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_meme_builds_top_and_bottom_captions() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = MemeRequest { top: " one does not simply ".to_string(), bottom: "merge on friday".to_string(), ..MemeRequest::default() };
+        let output = service.generate_meme(vec![1], &request).await.unwrap();
+        assert_eq!(output.content_type, "image/png");
+
+        let top = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        assert_eq!(top.text, "ONE DOES NOT SIMPLY");
+        assert_eq!(top.position, DomainPosition::TopCenter);
+        assert_eq!(top.wrap, Some(TextWrap { max_height: 0.3 }));
+        assert!(matches!(top.effect, Some(TextEffect::Outline { .. })));
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        match &overlays[..] {
+            [Overlay::Text(bottom)] => {
+                assert_eq!(bottom.text, "MERGE ON FRIDAY");
+                assert_eq!(bottom.position, DomainPosition::BottomCenter);
+                assert_eq!(bottom.effect, top.effect);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let result = service.generate_meme(vec![1], &MemeRequest { top: "  ".to_string(), ..MemeRequest::default() }).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_bubbles() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            bubbles: vec![BubbleRequest {
+                kind: "Thought".to_string(),
+                target: Some((0.8, 0.9)),
+                tail: "left".to_string(),
+                ..BubbleRequest::new("LGTM?".to_string())
+            }],
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        match &overlays[..] {
+            [Overlay::Bubble(bubble)] => {
+                assert_eq!(bubble.text, "LGTM?");
+                assert_eq!(bubble.kind, BubbleKind::Thought);
+                assert_eq!(bubble.tail, TailSide::Left);
+                assert_eq!(bubble.target, Some((0.8, 0.9)));
+                assert_eq!(bubble.position, DomainPosition::TopLeft);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        for bubble in [
+            BubbleRequest { kind: "shout".to_string(), ..BubbleRequest::new("LGTM".to_string()) },
+            BubbleRequest { target: Some((1.5, 0.5)), ..BubbleRequest::new("LGTM".to_string()) },
+            BubbleRequest::new(" ".to_string()),
+        ] {
+            let request = LgtmRequest { bubbles: vec![bubble], ..LgtmRequest::default() };
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_maps_qr() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let url = "https://github.com/koshiro1234/Create_LGTM_Image/pull/42";
+        let request = LgtmRequest {
+            qr: Some(QrRequest { position: "top-right".to_string(), quiet_zone: 2, ..QrRequest::new(url.to_string()) }),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        match &overlays[..] {
+            [Overlay::Qr(qr)] => {
+                assert_eq!(qr.content, url);
+                assert_eq!(qr.position, DomainPosition::TopRight);
+                assert_eq!(qr.size, 0.2);
+                assert_eq!(qr.quiet_zone, 2);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        for qr in [
+            QrRequest::new(String::new()),
+            QrRequest::new("a".repeat(MAX_QR_BYTES + 1)),
+            QrRequest { size: 0.0, ..QrRequest::new(url.to_string()) },
+            QrRequest { quiet_zone: 40, ..QrRequest::new(url.to_string()) },
+        ] {
+            let request = LgtmRequest { qr: Some(qr), ..LgtmRequest::default() };
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_expands_text_templates() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let request = LgtmRequest {
+            text: "LGTM @{user} {{done}}".to_string(),
+            extra_texts: vec![ExtraTextRequest {
+                text: "{repo}#{pr} {date}".to_string(),
+                color_hex: "#FFFFFF".to_string(),
+                position: "bottom-center".to_string(),
+                path: None,
+                writing_mode: "horizontal".to_string(),
+                effect: None,
+            }],
+            qr: Some(QrRequest::new("https://github.com/{repo}/pull/{pr}".to_string())),
+            user: Some("octocat".to_string()),
+            pr: Some("42".to_string()),
+            repo: Some("koshiro1234/Create_LGTM_Image".to_string()),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        assert_eq!(text_overlay.text, "LGTM @octocat {done}");
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        match &overlays[..] {
+            [Overlay::Text(extra), Overlay::Qr(qr)] => {
+                let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+                assert_eq!(extra.text, format!("koshiro1234/Create_LGTM_Image#42 {}", today));
+                assert_eq!(qr.content, "https://github.com/koshiro1234/Create_LGTM_Image/pull/42");
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let request = LgtmRequest { text: "{random_phrase}".to_string(), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap().text;
+        assert!(PhraseDictionary::default().phrases().iter().any(|p| p.text == text), "{}", text);
+
+        for text in ["LGTM {branch}", "LGTM {user}", "LGTM {"] {
+            let request = LgtmRequest { text: text.to_string(), ..LgtmRequest::default() };
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))), "{}", text);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_picks_random_text_from_dictionary() {
+        let mock_image_processor = budget_mock(10);
+        let phrases = PhraseDictionary::parse("en Looks Good To Me\nen Let's Go To Mars\nja よさそう\nja {かっこ}\n").unwrap();
+        let service = LgtmService::new(mock_image_processor.clone()).with_phrases(phrases.clone());
+        let last_text = || mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap().text;
+
+        // 同じ seed なら同じ言葉。{random_phrase} も同じ言葉になる
+        let request = LgtmRequest {
+            text: "random".to_string(),
+            extra_texts: vec![ExtraTextRequest {
+                text: "({random_phrase})".to_string(),
+                color_hex: "#FFFFFF".to_string(),
+                position: "bottom-center".to_string(),
+                path: None,
+                writing_mode: "horizontal".to_string(),
+                effect: None,
+            }],
+            locale: Some("en".to_string()),
+            seed: Some(7),
+            ..LgtmRequest::default()
+        };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let picked = last_text();
+        assert_eq!(picked, phrases.pick(Some("en"), 7).unwrap());
+        let overlays = mock_image_processor.last_render_options.lock().unwrap().clone().unwrap().overlays;
+        assert!(matches!(&overlays[..], [Overlay::Text(extra)] if extra.text == format!("({})", picked)));
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(last_text(), picked);
+
+        // 辞書の言葉はテンプレートとして読まない
+        for seed in 0..10 {
+            let request = LgtmRequest { text: "random".to_string(), locale: Some("ja".to_string()), seed: Some(seed), ..LgtmRequest::default() };
+            service.generate_lgtm_image(vec![1], &request).await.unwrap();
+            assert!(["よさそう", "{かっこ}"].contains(&last_text().as_str()));
+        }
+
+        let request = LgtmRequest { text: "random".to_string(), locale: Some("fr".to_string()), ..LgtmRequest::default() };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+        // random を使わなければ locale に合う言葉がなくてもいい
+        let request = LgtmRequest { locale: Some("fr".to_string()), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(last_text(), "LGTM");
+    }
+
+    #[tokio::test]
+    async fn test_generate_lgtm_image_adds_subtitle() {
+        let mock_image_processor = budget_mock(10);
+        let phrases = PhraseDictionary::parse("en Looks Good To Me\nen Let's Go To Mars\nen Ship It\n").unwrap();
+        let service = LgtmService::new(mock_image_processor.clone()).with_phrases(phrases.clone());
+        let last_subtitle = || mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap().subtitle;
+
+        let request = LgtmRequest { subtitle: Some("auto".to_string()), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(last_subtitle(), Some(Subtitle { text: "Looks Good To Me".to_string(), scale: SUBTITLE_SCALE }));
+
+        let request = LgtmRequest { text: "SI".to_string(), subtitle: Some("random".to_string()), seed: Some(3), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(last_subtitle().unwrap().text, "Ship It");
+        let request = LgtmRequest { subtitle: Some("random".to_string()), seed: Some(3), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(last_subtitle().unwrap().text, phrases.pick_expansion("LGTM", None, Some(3)).unwrap());
+
+        // auto / random 以外はテンプレートを埋めてそのまま添える
+        let request = LgtmRequest { subtitle: Some("by {user}".to_string()), user: Some("octocat".to_string()), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        assert_eq!(last_subtitle().unwrap().text, "by octocat");
+
+        // text=random なら先に言葉を選び、その頭文字を大きく、言葉そのものを下に添える
+        let request = LgtmRequest { text: "random".to_string(), subtitle: Some("auto".to_string()), seed: Some(3), ..LgtmRequest::default() };
+        service.generate_lgtm_image(vec![1], &request).await.unwrap();
+        let text_overlay = mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap();
+        let (acronym, phrase) = phrases.pick_acronym(None, 3).unwrap();
+        assert_eq!(text_overlay.text, acronym);
+        assert_eq!(text_overlay.subtitle.unwrap().text, phrase);
+        let request = LgtmRequest { text: "random".to_string(), subtitle: Some("random".to_string()), locale: Some("ja".to_string()), ..LgtmRequest::default() };
+        let result = service.generate_lgtm_image(vec![1], &request).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+
+        for request in [
+            LgtmRequest { text: "SGTM".to_string(), subtitle: Some("auto".to_string()), ..LgtmRequest::default() },
+            LgtmRequest { subtitle: Some(" ".to_string()), ..LgtmRequest::default() },
+            LgtmRequest { subtitle: Some("auto".to_string()), writing_mode: "vertical".to_string(), ..LgtmRequest::default() },
+            LgtmRequest { subtitle: Some("auto".to_string()), text_position: "tiled".to_string(), ..LgtmRequest::default() },
+        ] {
+            let result = service.generate_lgtm_image(vec![1], &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))), "{:?}", request.subtitle);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_collage_composes_before_drawing() {
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone());
+
+        let images = vec![vec![1], vec![2], vec![3], vec![4], vec![5]];
+        let request = LgtmRequest { text: "LGTM x5".to_string(), ..LgtmRequest::default() };
+        service.generate_collage(images, &CollageRequest::default(), &request).await.unwrap();
+        // 5 枚なら 3 列のグリッド。並べた 1 枚に 1 回だけ描く
+        let (count, collage) = mock_image_processor.last_collage.lock().unwrap().clone().unwrap();
+        assert_eq!(count, 5);
+        assert_eq!(collage.layout, CollageLayout::Grid { columns: 3 });
+        assert_eq!(collage.gutter, 8);
+        assert_eq!(mock_image_processor.last_image_bytes.lock().unwrap().clone().unwrap(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(mock_image_processor.last_text_overlay.lock().unwrap().clone().unwrap().text, "LGTM x5");
+
+        let strip = CollageRequest { layout: "Strip".to_string(), gutter: 0, ..CollageRequest::default() };
+        service.generate_collage(vec![vec![1], vec![2]], &strip, &request).await.unwrap();
+        assert_eq!(mock_image_processor.last_collage.lock().unwrap().clone().unwrap().1.layout, CollageLayout::Strip);
+
+        for (images, collage) in [
+            (Vec::new(), CollageRequest::default()),
+            (vec![vec![1]; MAX_COLLAGE_IMAGES + 1], CollageRequest::default()),
+            (vec![vec![1]; 2], CollageRequest { layout: "mosaic".to_string(), ..CollageRequest::default() }),
+            (vec![vec![1]; 2], CollageRequest { columns: Some(0), ..CollageRequest::default() }),
+            (vec![vec![1]; 2], CollageRequest { gutter: 1000, ..CollageRequest::default() }),
+        ] {
+            let result = service.generate_collage(images, &collage, &request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))), "{:?}", collage);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_generated_output_is_saved_to_gallery() {
+        let dir = std::env::temp_dir().join(format!("lgtm-service-gallery-{}", std::process::id()));
+        let mock_image_processor = budget_mock(10);
+        // ギャラリーを設定しなければ残さない
+        let output = LgtmService::new(mock_image_processor.clone()).generate_lgtm_image(vec![1], &LgtmRequest::default()).await.unwrap();
+        assert_eq!(output.gallery_id, None);
+
+        let service = LgtmService::new(mock_image_processor.clone()).with_gallery(LocalGalleryStore::new(&dir));
+        let lgtm = service.generate_lgtm_image(vec![1], &LgtmRequest::default()).await.unwrap();
+        let meme = service.generate_meme(vec![1], &MemeRequest { top: "ship it".to_string(), ..MemeRequest::default() }).await.unwrap();
+        let collage = service.generate_collage(vec![vec![1], vec![2]], &CollageRequest::default(), &LgtmRequest::default()).await.unwrap();
+        let ids: Vec<String> = [lgtm, meme, collage].into_iter().map(|output| output.gallery_id.unwrap()).collect();
+        // コラージュは並べた後に 1 回だけ残す
+        assert_eq!(LocalGalleryStore::new(&dir).list_recent(10).await.unwrap(), ids.into_iter().rev().collect::<Vec<_>>());
+        assert_eq!(service.load_from_gallery(&LocalGalleryStore::new(&dir).list_recent(1).await.unwrap()[0]).await.unwrap(), (vec![0; 10], "image/png"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_generate_contact_sheet_lists_recent_gallery_images() {
+        let dir = std::env::temp_dir().join(format!("lgtm-contact-sheet-{}", std::process::id()));
+        let mock_image_processor = budget_mock(10);
+        let service = LgtmService::new(mock_image_processor.clone()).with_gallery(LocalGalleryStore::new(&dir));
+
+        // まだ何も保存していなければエラー
+        let result = service.generate_contact_sheet(&ContactSheetRequest::default()).await;
+        assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))));
+
+        let mut ids = Vec::new();
+        for data in [vec![1], vec![2], vec![3]] {
+            ids.push(LocalGalleryStore::new(&dir).save(&data, "image/png").await.unwrap());
+        }
+        assert_eq!(service.load_from_gallery(&ids[1]).await.unwrap(), (vec![2], "image/png"));
+
+        let output = service.generate_contact_sheet(&ContactSheetRequest { count: 2, ..ContactSheetRequest::default() }).await.unwrap();
+        assert_eq!(output.content_type, "image/png");
+        // 新しい順に 2 枚。2 枚なら 2 列
+        assert_eq!(output.data, vec![3, 2]);
+        let (listed, sheet) = mock_image_processor.last_contact_sheet.lock().unwrap().clone().unwrap();
+        assert_eq!(listed, vec![ids[2].clone(), ids[1].clone()]);
+        assert_eq!(sheet.collage.layout, CollageLayout::Grid { columns: 2 });
+        assert_eq!(sheet.thumbnail_size, 200);
+
+        for request in [
+            ContactSheetRequest { count: 0, ..ContactSheetRequest::default() },
+            ContactSheetRequest { count: MAX_COLLAGE_IMAGES + 1, ..ContactSheetRequest::default() },
+            ContactSheetRequest { thumbnail_size: 8, ..ContactSheetRequest::default() },
+            ContactSheetRequest { columns: Some(0), ..ContactSheetRequest::default() },
+        ] {
+            let result = service.generate_contact_sheet(&request).await;
+            assert!(matches!(result, Err(ApplicationError::DomainError(DomainError::InvalidInput(_)))), "{:?}", request);
+        }
+
+        // 読めない画像は飛ばして残りだけ並べる
+        std::fs::create_dir(dir.join("99991231-235959999.png")).unwrap();
+        let output = service.generate_contact_sheet(&ContactSheetRequest { count: 2, ..ContactSheetRequest::default() }).await.unwrap();
+        assert_eq!(output.data, vec![3]);
+        let result = service.load_from_gallery("19700101-000000000").await;
+        assert!(matches!(result, Err(ApplicationError::InfrastructureError(InfrastructureError::NotFound(_)))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
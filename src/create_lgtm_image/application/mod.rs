@@ -1,2 +1,4 @@
 pub mod lgtm_service;
+pub mod lgtm_request;
+pub mod text_template;
 pub mod error;
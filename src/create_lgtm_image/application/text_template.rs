@@ -0,0 +1,121 @@
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+// 使えるプレースホルダの一覧 (エラーメッセージ用)
+const PLACEHOLDERS: &str = "{date}, {time}, {user}, {pr}, {repo}, {random_phrase}";
+
+// テンプレートに埋め込む値。user / pr / repo はリクエストから、それ以外はサーバー側で決める
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub now: NaiveDateTime, // {date} (YYYY-MM-DD) と {time} (HH:MM)
+    pub user: Option<String>,
+    pub pr: Option<String>,
+    pub repo: Option<String>,
+    pub random_phrase: Option<String>, // 辞書から選んだ言葉。locale に合う言葉がなければ None
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    #[error("Unknown placeholder {{{0}}} (available: {PLACEHOLDERS}; write {{{{ or }}}} for a literal brace)")]
+    UnknownPlaceholder(String),
+
+    #[error("Placeholder {{{0}}} needs the \"{0}\" parameter")]
+    MissingValue(String),
+
+    #[error("Placeholder {{random_phrase}} has no phrase for the requested locale")]
+    NoRandomPhrase,
+
+    #[error("Unclosed '{{' at character {0} (write {{{{ for a literal brace)")]
+    Unclosed(usize),
+
+    #[error("Unmatched '}}' at character {0} (write }}}} for a literal brace)")]
+    UnmatchedBrace(usize),
+}
+
+// "{name}" を context の値に置き換える。"{{" と "}}" はそれぞれ 1 文字の波かっこになる
+// 位置はエラーメッセージ用に 0 から数えた文字数
+pub fn render_template(template: &str, context: &TemplateContext) -> Result<String, TemplateError> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut output = String::with_capacity(template.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                output.push(chars[i]);
+                i += 2;
+            }
+            ('{', _) => {
+                let close = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '}' || c == '{')
+                    .filter(|&n| chars[i + 1 + n] == '}')
+                    .ok_or(TemplateError::Unclosed(i))?;
+                let name: String = chars[i + 1..i + 1 + close].iter().collect();
+                output.push_str(&lookup(name.trim(), context)?);
+                i += close + 2;
+            }
+            ('}', _) => return Err(TemplateError::UnmatchedBrace(i)),
+            (c, _) => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn lookup(name: &str, context: &TemplateContext) -> Result<String, TemplateError> {
+    let required = |value: &Option<String>| value.clone().ok_or_else(|| TemplateError::MissingValue(name.to_string()));
+    match name {
+        "date" => Ok(context.now.format("%Y-%m-%d").to_string()),
+        "time" => Ok(context.now.format("%H:%M").to_string()),
+        "user" => required(&context.user),
+        "pr" => required(&context.pr),
+        "repo" => required(&context.repo),
+        "random_phrase" => context.random_phrase.clone().ok_or(TemplateError::NoRandomPhrase),
+        _ => Err(TemplateError::UnknownPlaceholder(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            now: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_opt(9, 5, 0).unwrap(),
+            user: Some("octocat".to_string()),
+            pr: Some("42".to_string()),
+            repo: None,
+            random_phrase: Some("Let's Go To Mars".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_render_template_fills_placeholders() {
+        let rendered = render_template("LGTM @{user} #{ pr } ({date} {time}) {random_phrase}", &context()).unwrap();
+        assert_eq!(rendered, "LGTM @octocat #42 (2026-10-18 09:05) Let's Go To Mars");
+        // プレースホルダがなければそのまま
+        assert_eq!(render_template("よさそう :thumbsup:", &context()).unwrap(), "よさそう :thumbsup:");
+    }
+
+    #[test]
+    fn test_render_template_escapes_braces() {
+        assert_eq!(render_template("{{user}} = {user}, }}", &context()).unwrap(), "{user} = octocat, }");
+        assert_eq!(render_template("{{{user}}}", &context()).unwrap(), "{octocat}");
+    }
+
+    #[test]
+    fn test_render_template_reports_errors() {
+        assert_eq!(render_template("LGTM {usr}", &context()), Err(TemplateError::UnknownPlaceholder("usr".to_string())));
+        assert_eq!(render_template("{repo}", &context()), Err(TemplateError::MissingValue("repo".to_string())));
+        assert_eq!(render_template("LGTM {user", &context()), Err(TemplateError::Unclosed(5)));
+        assert_eq!(render_template("{ {user}", &context()), Err(TemplateError::Unclosed(0)));
+        assert_eq!(render_template("LGTM }", &context()), Err(TemplateError::UnmatchedBrace(5)));
+        let no_phrase = TemplateContext { random_phrase: None, ..context() };
+        assert_eq!(render_template("{random_phrase}", &no_phrase), Err(TemplateError::NoRandomPhrase));
+        let message = TemplateError::UnknownPlaceholder("usr".to_string()).to_string();
+        assert!(message.starts_with("Unknown placeholder {usr} (available: {date},"), "{}", message);
+    }
+}
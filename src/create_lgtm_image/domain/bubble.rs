@@ -0,0 +1,35 @@
+use crate::domain::color::Color;
+use crate::domain::position::Position;
+
+// 吹き出しの形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BubbleKind {
+    Speech,  // 角の丸い四角と、三角のしっぽ
+    Thought, // もくもくした雲と、だんだん小さくなる丸のしっぽ
+}
+
+// しっぽを出す辺。Auto は target のある向きの辺
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailSide {
+    Auto,
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+// 文字を囲む吹き出し。大きさは文字を測って決め、本体は Position で置く
+#[derive(Debug, Clone, PartialEq)]
+pub struct BubbleOverlay {
+    pub text: String,
+    pub kind: BubbleKind,
+    pub position: Position,
+    pub margin: u32,                // 画像のふちからの距離 (px)
+    pub target: Option<(f32, f32)>, // しっぽの先 (画像に対する比率 0.0-1.0)。None ならしっぽなし
+    pub tail: TailSide,
+    pub text_size: f32, // 画像の高さに対する文字の大きさ
+    pub text_color: Color,
+    pub fill: Color,
+    pub border: Color,
+    pub border_width: u32, // 0 で枠線なし
+}
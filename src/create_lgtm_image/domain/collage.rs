@@ -0,0 +1,24 @@
+use crate::domain::color::Color;
+
+// 複数の画像の並べ方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollageLayout {
+    Grid { columns: u32 }, // 同じ大きさのマスに左上から行ごとに並べ、マスの中では縦横比を保って真ん中に置く
+    Strip,                 // 高さをそろえて横一列に並べる
+}
+
+// 複数の画像を 1 枚に並べてから文字を描く
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collage {
+    pub layout: CollageLayout,
+    pub gutter: u32,       // 画像どうしと外周のすき間 (px)
+    pub background: Color, // すき間と、マスで余った部分の色
+}
+
+// ギャラリーの最近の画像を ID 付きのサムネイルで一覧にした 1 枚
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactSheet {
+    pub collage: Collage,   // サムネイルの並べ方 (グリッド) とすき間・背景
+    pub thumbnail_size: u32, // サムネイルを収める正方形の一辺 (px)
+    pub label_color: Color, // サムネイルの下に書く ID の色
+}
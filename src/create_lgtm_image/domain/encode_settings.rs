@@ -0,0 +1,29 @@
+use image::ImageFormat;
+use crate::domain::metadata_policy::MetadataPolicy;
+
+// 出力画像をエンコードするときの設定
+// maxBytes の予算に収めるため、LgtmService がこの値を段階的に変えて再エンコードする
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodeSettings {
+    pub format: ImageFormat,
+    pub quality: u8,             // JPEG の品質 (1-100)。PNG では使わない
+    pub max_colors: Option<u16>, // パレットの色数上限。PNG では Some なら PNG8 で書き出す (GIF は常にパレット)
+    pub dither: bool,            // 減色時に Floyd–Steinberg ディザをかけるか
+    pub scale: f32,              // 縮小率 (1.0 なら原寸)
+    pub metadata_policy: MetadataPolicy,
+}
+
+impl EncodeSettings {
+    pub const DEFAULT_JPEG_QUALITY: u8 = 75; // image クレートの JpegEncoder と同じ既定値
+
+    pub fn new(format: ImageFormat) -> Self {
+        Self {
+            format,
+            quality: Self::DEFAULT_JPEG_QUALITY,
+            max_colors: None,
+            dither: false,
+            scale: 1.0,
+            metadata_policy: MetadataPolicy::StripAll,
+        }
+    }
+}
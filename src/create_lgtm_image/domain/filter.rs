@@ -0,0 +1,23 @@
+// テキストを描画する前に画像全体にかけるフィルタ (指定した順に適用する)
+// 背景を落ち着かせて LGTM の文字を目立たせるためのもの
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageFilter {
+    Grayscale { amount: f32 },    // 0.0-1.0 (1.0 で完全にモノクロ)
+    Sepia { amount: f32 },        // 0.0-1.0
+    Blur { sigma: f32 },          // ガウスぼかしの標準偏差 (px)
+    Brightness { amount: f32 },   // -1.0-1.0 (各チャンネルに amount * 255 を足す)
+    Contrast { amount: f32 },     // -1.0-1.0 (0.0 で変化なし)
+    Saturation { factor: f32 },   // 0.0 でモノクロ、1.0 で変化なし
+    Vignette { strength: f32 },   // 0.0-1.0 (四隅を暗くする強さ)
+    Darken { opacity: f32 },      // 0.0-1.0 (黒を重ねる不透明度)
+}
+
+// テキストの下だけにかけるすりガラス風の背景 (測ったテキストの範囲 + padding をぼかして暗くする)
+// 画像全体にフィルタをかける代わりに使えば、文字の周り以外はくっきりしたまま残る
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBackdrop {
+    pub blur_sigma: f32, // ガウスぼかしの標準偏差 (px)
+    pub darken: f32,     // 0.0-1.0 (黒を重ねる不透明度)
+    pub padding: u32,    // テキストの範囲から広げる幅 (px)
+    pub feather: u32,    // 外周をなめらかに元の画像へ戻す幅 (px)
+}
@@ -0,0 +1,36 @@
+use crate::domain::color::Color;
+
+// 枠線の塗り方
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorderFill {
+    Solid(Color),
+    Gradient { top: Color, bottom: Color }, // 上から下へのグラデーション
+}
+
+// 画像の外側に足す枠線 (画像はその分だけ大きくなる)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Border {
+    pub width: u32,
+    pub fill: BorderFill,
+}
+
+// ポラロイド風の枠。下の余白を広く取り、テキストはその余白に描く
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polaroid {
+    pub color: Color,
+}
+
+// テキストを描いた画像の周りの飾り。polaroid → テキスト → border → 角丸 の順に適用する
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    pub polaroid: Option<Polaroid>,
+    pub border: Option<Border>,
+    pub corner_radius: u32, // 0 で角丸なし。角は透明に切り抜くのでアルファのある形式で出力する
+}
+
+impl Frame {
+    // 透明な部分ができるか (JPEG では出力できない)
+    pub fn needs_alpha(&self) -> bool {
+        self.corner_radius > 0
+    }
+}
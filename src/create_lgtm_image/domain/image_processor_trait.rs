@@ -1,7 +1,9 @@
 use crate::domain::image::Image as DomainImage;
 use crate::domain::text_overlay::TextOverlay as DomainTextOverlay;
 use crate::domain::color::Color as DomainColor; // 追加
-use crate::domain::position::Position as DomainPosition; // 追加
+use crate::domain::encode_settings::EncodeSettings;
+use crate::domain::render_options::RenderOptions;
+use crate::domain::collage::{Collage, ContactSheet};
 use crate::infrastructure::error::InfrastructureError; // Changed from DomainError
 // use anyhow::Result; // Removed as no longer directly used by trait methods
 use image::ImageFormat as InnerImageFormat; // imageクレートのImageFormatをインポート
@@ -13,8 +15,30 @@ pub trait ImageProcessor {
         image_bytes: Vec<u8>,
         input_format_opt: Option<InnerImageFormat>,
         text_overlay: &DomainTextOverlay,
-        output_format: InnerImageFormat,
-    ) -> Result<Vec<u8>, InfrastructureError>; // Changed to InfrastructureError
+        render_options: &RenderOptions,
+        encode_settings: &EncodeSettings,
+    ) -> Result<DomainImage, InfrastructureError>; // Changed to InfrastructureError
+
+    // 処理済みの画像を別の設定でエンコードし直す (品質・減色・縮小)
+    fn reencode_image(
+        &self,
+        image_bytes: Vec<u8>,
+        encode_settings: &EncodeSettings,
+    ) -> Result<DomainImage, InfrastructureError>;
+
+    // 複数の画像を並べて 1 枚にし、劣化なしの PNG で返す (この後 add_text_to_image に渡す)
+    fn compose_collage(
+        &self,
+        images: Vec<Vec<u8>>,
+        collage: &Collage,
+    ) -> Result<DomainImage, InfrastructureError>;
+
+    // ギャラリーの画像を (ID, バイト列) で受け取り、ID を添えたサムネイルを並べた 1 枚を PNG で返す
+    fn render_contact_sheet(
+        &self,
+        images: Vec<(String, Vec<u8>)>,
+        sheet: &ContactSheet,
+    ) -> Result<DomainImage, InfrastructureError>;
 
     fn parse_hex_color(&self, hex_str: &str) -> DomainColor;
 }
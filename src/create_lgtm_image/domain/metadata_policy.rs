@@ -0,0 +1,8 @@
+// 再エンコード時に元画像のメタデータ (EXIF / ICC / XMP) をどこまで残すか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataPolicy {
+    #[default]
+    StripAll,             // すべて削除 (既定)
+    PreserveIcc,          // ICC プロファイルだけ残す (広色域の画像の色ずれ防止)
+    PreserveAllExceptGps, // EXIF / ICC / XMP を残すが、位置情報は必ず消す
+}
@@ -4,3 +4,15 @@ pub mod color;
 pub mod position;
 pub mod image_processor_trait;
 pub mod error;
+pub mod encode_settings;
+pub mod metadata_policy;
+pub mod transform;
+pub mod render_options;
+pub mod filter;
+pub mod frame;
+pub mod overlay;
+pub mod stamp;
+pub mod bubble;
+pub mod qr;
+pub mod collage;
+pub mod text_effect;
@@ -0,0 +1,25 @@
+use crate::domain::bubble::BubbleOverlay;
+use crate::domain::position::Position;
+use crate::domain::qr::QrOverlay;
+use crate::domain::stamp::StampOverlay;
+use crate::domain::text_overlay::TextOverlay;
+
+// テキストの後に重ねる画像 (チームのロゴなど)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageOverlay {
+    pub image_bytes: Vec<u8>, // エンコードされたままの画像
+    pub position: Position,
+    pub scale: f32,   // 下の画像の幅に対する比率 (0.0-1.0)。縦横比は保つ
+    pub opacity: f32, // 0.0-1.0
+    pub margin: u32,  // 画像のふちからの距離 (px)
+}
+
+// テキストの上に重ねるレイヤー。指定した順に重ねる
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    Image(ImageOverlay),
+    Stamp(StampOverlay),
+    Text(TextOverlay), // メインのテキストとは別に置くテキスト (円弧で囲む文字など)
+    Bubble(BubbleOverlay),
+    Qr(QrOverlay),
+}
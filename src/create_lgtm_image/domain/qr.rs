@@ -0,0 +1,17 @@
+use crate::domain::color::Color;
+use crate::domain::position::Position;
+
+// 誤り訂正レベル M で符号化できるバイト数の上限 (バージョン 40)
+pub const MAX_QR_BYTES: usize = 2331;
+
+// QR コード (PR の URL など)。content をそのまま符号化し、Position で置く
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrOverlay {
+    pub content: String,
+    pub position: Position,
+    pub size: f32,       // 画像の短辺に対する一辺の長さ (クワイエットゾーン込み, 0.0-1.0)
+    pub margin: u32,     // 画像のふちからの距離 (px)
+    pub quiet_zone: u32, // 周りの余白の幅 (モジュール数)
+    pub dark: Color,
+    pub light: Color, // 余白も含めた明るいモジュールの色
+}
@@ -0,0 +1,14 @@
+use crate::domain::filter::{ImageFilter, TextBackdrop};
+use crate::domain::frame::Frame;
+use crate::domain::overlay::Overlay;
+use crate::domain::transform::Transform;
+
+// テキストを重ねる前後の処理 (変形・フィルタなど) の指定
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderOptions {
+    pub transform: Transform,
+    pub filters: Vec<ImageFilter>,
+    pub text_backdrop: Option<TextBackdrop>, // テキストの下だけぼかして暗くする
+    pub overlays: Vec<Overlay>, // テキストの後、枠線の前に重ねる
+    pub frame: Frame,
+}
@@ -0,0 +1,32 @@
+use crate::domain::color::Color;
+use crate::domain::position::Position;
+
+// 判子の外枠
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StampShape {
+    Circle,
+    RoundedSquare,
+}
+
+// 判子の中の文字の並べ方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StampLayout {
+    Arc,      // text を上の弧に、date を中央の帯に、name を下の弧に沿わせる (データ印風)
+    Vertical, // text を中央に縦に積み、name はその左の列、date は下の弧に沿わせる
+}
+
+// 朱肉で押したような承認印。TextOverlay と同じく Position で置く
+#[derive(Debug, Clone, PartialEq)]
+pub struct StampOverlay {
+    pub text: String, // "承認" / "LGTM" など
+    pub name: Option<String>,
+    pub date: Option<String>, // 表示する文字列そのまま
+    pub shape: StampShape,
+    pub layout: StampLayout,
+    pub color: Color,
+    pub position: Position,
+    pub scale: f32,    // 画像の短辺に対する判子の大きさ (0.0-1.0)
+    pub margin: u32,   // 画像のふちからの距離 (px)
+    pub distress: f32, // 0.0-1.0 (かすれ具合)
+    pub angle: f32,    // 傾き (度、時計回り)
+}
@@ -0,0 +1,16 @@
+use crate::domain::color::Color;
+
+// テキストの見た目の効果。色と位置は TextOverlay の指定をそのまま使う
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextEffect {
+    // 文字の内側の縁を color で光らせ、外側に広く光をにじませる
+    Neon { color: Color, stroke: u32, glow: f32 },
+    // 文字を angle の向き (度。0 が右で時計回り) に depth px 押し出し、側面を shade だけ暗くする
+    Extrude { depth: u32, angle: f32, shade: f32 },
+    // 1 文字ごとに色相を hue_step 度ずつずらす
+    Rainbow { hue_step: f32 },
+    // 赤と青緑のずれた影を左右に置き、横の帯ごとに文字をずらす。seed が同じなら同じずれ方になる
+    Glitch { offset: u32, slices: u32, seed: u32 },
+    // 文字を color のふちで囲む。width は文字の大きさに対するふちの太さの比率
+    Outline { color: Color, width: f32 },
+}
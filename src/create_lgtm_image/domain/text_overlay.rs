@@ -1,11 +1,62 @@
 use crate::domain::color::Color;
 use crate::domain::position::Position;
+use crate::domain::text_effect::TextEffect;
 
-#[derive(Clone, Debug)]
+// テキストを並べる線。Straight 以外は 1 文字ずつ線の向きに合わせて回す
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TextPath {
+    #[default]
+    Straight,
+    // 円弧。円は Position で置き、文字は円の外側に沿わせる
+    // radius は画像の短辺に対する比率、angle は文字列の中央が来る角度 (度。0 が真上で時計回り)
+    Arc { radius: f32, angle: f32 },
+    // ベジェ曲線。制御点は画像に対する比率 (0.0-1.0) で、3 点なら 2 次、4 点なら 3 次
+    Bezier(Vec<(f32, f32)>),
+}
+
+// 縦書きは上から下へ、列は右から左へ並べる
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WritingMode {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+// 画像全体に文字を斜めの格子で繰り返して置く (透かし)。position の代わりに使う
+// angle は格子を回す角度 (度。時計回り)、spacing は文字の間隔 (文字の高さに対する比率)、opacity は 0.0-1.0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileLayout {
+    pub angle: f32,
+    pub spacing: f32,
+    pub opacity: f32,
+}
+
+// 幅に合わせて単語の区切りで折り返し、max_height に収まるまで縮める (ミームのキャプションなど)
+// max_height は使ってよい高さ (画像の高さに対する比率)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextWrap {
+    pub max_height: f32,
+}
+
+// 大きな文字の下に小さく添える 1 行 (略語の展開 "Looks Good To Me" など)
+// scale は上の文字の大きさに対する比率
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtitle {
+    pub text: String,
+    pub scale: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct TextOverlay {
     pub text: String,
     pub color: Color,
     pub position: Position,
+    pub path: TextPath,
+    pub writing_mode: WritingMode, // Vertical は path が Straight のときだけ使える
+    pub effect: Option<TextEffect>,
+    pub tile: Option<TileLayout>, // 指定すると position を使わず画像全体に繰り返す
+    pub wrap: Option<TextWrap>,   // 横書きで path が Straight のときだけ使う
+    pub subtitle: Option<Subtitle>, // 横書きで path が Straight のときだけ使う (wrap より優先)
 }
 
 impl TextOverlay {
@@ -14,6 +65,12 @@ impl TextOverlay {
             text,
             color,
             position,
+            path: TextPath::Straight,
+            writing_mode: WritingMode::Horizontal,
+            effect: None,
+            tile: None,
+            wrap: None,
+            subtitle: None,
         }
     }
 }
@@ -0,0 +1,78 @@
+use crate::domain::color::Color;
+
+// 指定サイズへのリサイズ方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeMode {
+    Fit,     // 縦横比を保ったまま枠に収める (枠より小さくなる辺がある)
+    Fill,    // 縦横比を保ったまま枠を覆い、はみ出した分を中央基準で切り取る
+    Contain, // Fit した上で、余白をパディング色で埋めてぴったり枠の大きさにする
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resize {
+    pub width: Option<u32>, // 片方だけ指定した場合は縦横比を保ってもう片方を決める
+    pub height: Option<u32>,
+    pub mode: ResizeMode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// 縦横比 (1:1, 16:9, 4:3 など)。足りない方向をパディング色で埋めて合わせる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AspectRatio {
+    pub const SQUARE: AspectRatio = AspectRatio { width: 1, height: 1 };
+    pub const WIDE: AspectRatio = AspectRatio { width: 16, height: 9 };
+    pub const STANDARD: AspectRatio = AspectRatio { width: 4, height: 3 };
+}
+
+// 小さすぎる画像を拡大するときの補間方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    Auto,    // 色数の少ない画像 (ドット絵やアイコン) は Nearest、それ以外は Lanczos
+    Nearest, // ドット絵向け。整数倍で拡大してくっきり保つ
+    Lanczos,
+}
+
+// 短辺が min_side 未満の画像は、文字が読めるようにテキストを置く前に拡大する
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinSize {
+    pub min_side: u32,
+    pub filter: UpscaleFilter,
+}
+
+// テキストを描画する前に行う変形。crop → aspect → resize → max_width/max_height → min_size の順に適用する
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub crop: Option<CropRect>,
+    pub aspect: Option<AspectRatio>,
+    pub resize: Option<Resize>,
+    pub max_width: Option<u32>, // これを超える場合だけ縮小する (拡大はしない)
+    pub max_height: Option<u32>,
+    pub min_size: Option<MinSize>, // resize を明示した場合は使わない
+    pub padding_color: Color,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            crop: None,
+            aspect: None,
+            resize: None,
+            max_width: None,
+            max_height: None,
+            min_size: None,
+            padding_color: Color::new(0, 0, 0, 0), // 透明 (JPEG では黒になる)
+        }
+    }
+}
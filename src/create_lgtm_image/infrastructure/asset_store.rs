@@ -0,0 +1,69 @@
+use super::error::InfrastructureError;
+use super::file_storage::{is_safe_name, read_first_existing};
+use crate::domain::error::DomainError;
+use std::path::PathBuf;
+
+const ASSET_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "gif"];
+
+// サーバー側に置いたロゴなどの画像を名前で読み込む
+// 名前は英数字・"-"・"_" だけを受け付け、ディレクトリの外は読めないようにする
+// 名前が不正・見つからない場合はリクエストの誤りとして扱う (400)
+// 読めなかったときのそれ以外の I/O エラーはそのまま返す (500)
+#[derive(Debug, Clone)]
+pub struct LocalAssetStore {
+    dir: PathBuf,
+}
+
+impl Default for LocalAssetStore {
+    fn default() -> Self {
+        Self::new("assets")
+    }
+}
+
+impl LocalAssetStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // LGTM_ASSETS_DIR で置き場所を変えられる (デフォルトは ./assets)
+    pub fn from_env() -> Self {
+        match std::env::var("LGTM_ASSETS_DIR") {
+            Ok(dir) => Self::new(dir),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // "team-logo" なら team-logo.png / .jpg / .jpeg / .gif の順に探す
+    pub async fn load_image(&self, name: &str) -> Result<Vec<u8>, InfrastructureError> {
+        if !is_safe_name(name, |c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(DomainError::InvalidInput(format!("Invalid asset name: {}", name)).into());
+        }
+        if let Some((data, _)) = read_first_existing(&self.dir, name, &ASSET_EXTENSIONS).await? {
+            return Ok(data);
+        }
+        Err(DomainError::InvalidInput(format!("Unknown asset: {}", name)).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_image_finds_asset_by_name() {
+        let dir = std::env::temp_dir().join(format!("lgtm-assets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("team-logo.jpg"), b"logo").unwrap();
+        let store = LocalAssetStore::new(&dir);
+
+        assert_eq!(store.load_image("team-logo").await.unwrap(), b"logo");
+        assert!(matches!(store.load_image("missing").await, Err(InfrastructureError::DomainErrorWrapper(_))));
+        // ディレクトリの外は読めない
+        assert!(matches!(store.load_image("../team-logo").await, Err(InfrastructureError::DomainErrorWrapper(_))));
+        assert!(matches!(store.load_image("").await, Err(InfrastructureError::DomainErrorWrapper(_))));
+        // ファイルではなくディレクトリになっているなど、見つからない以外の読み込みエラーは I/O エラー
+        std::fs::create_dir(dir.join("broken.png")).unwrap();
+        assert!(matches!(store.load_image("broken").await, Err(InfrastructureError::IoError(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,8 @@
 use crate::application::error::ApplicationError; // Added for handler return types
 use axum::{
     body::Body,
-    extract::{Multipart, Query, Json, State},
-    http::{self, StatusCode, header::HeaderName},
+    extract::{Multipart, Path, Query, Json, State},
+    http::response::Builder,
     response::{IntoResponse, Response},
 };
 use serde::Deserialize;
@@ -13,6 +13,8 @@ use tokio::io::AsyncWriteExt;
 // use std::io::Cursor; // LgtmService がバイト列を直接扱うので、ハンドラでは不要になることが多い
 
 use crate::application::lgtm_service::LgtmService;
+use crate::application::lgtm_request::{BackdropRequest, BubbleRequest, CollageRequest, ContactSheetRequest, FilterRequest, LgtmOutput, LgtmRequest, ExtraTextRequest, LogoRequest, MemeRequest, LogoSource, QrRequest, StampRequest, StickerRequest, TextEffectRequest, TextPathRequest, TileRequest};
+use crate::domain::error::DomainError;
 // LocalFileStorage の use は preview/download が直接ファイルを読むなら必要
 
 #[derive(Clone)]
@@ -25,44 +27,545 @@ pub struct AppState {
 #[derive(Deserialize, Debug)]
 pub struct FetchImageParams {
     pub url: String,
+    #[serde(flatten)]
+    pub options: LgtmParams,
+}
+
+// 画像の描き方の指定。/fetch の本文と /upload の "options" フィールドで共通
+#[derive(Deserialize, Debug, Default)]
+pub struct LgtmParams {
     pub text: Option<String>,
+    pub locale: Option<String>,
+    pub seed: Option<u64>,
+    pub subtitle: Option<String>,
     #[serde(rename = "textColor")]
     pub text_color: Option<String>,
     #[serde(rename = "textPosition")]
     pub text_position: Option<String>,
+    #[serde(rename = "textPath")]
+    pub text_path: Option<TextPathParams>,
+    #[serde(rename = "writingMode")]
+    pub writing_mode: Option<String>,
+    #[serde(rename = "textEffect")]
+    pub text_effect: Option<TextEffectParams>,
+    pub tile: Option<TileParams>,
+    pub texts: Option<Vec<ExtraTextParams>>,
     #[serde(rename = "outputFormat")]
     pub output_format: Option<String>,
+    #[serde(rename = "maxBytes")]
+    pub max_bytes: Option<usize>,
+    pub colors: Option<u16>,
+    pub dither: Option<bool>,
+    pub metadata: Option<String>,
+    pub crop: Option<String>,
+    pub aspect: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(rename = "resizeMode")]
+    pub resize_mode: Option<String>,
+    #[serde(rename = "maxWidth")]
+    pub max_width: Option<u32>,
+    #[serde(rename = "maxHeight")]
+    pub max_height: Option<u32>,
+    #[serde(rename = "paddingColor")]
+    pub padding_color: Option<String>,
+    #[serde(rename = "minSize")]
+    pub min_size: Option<u32>,
+    #[serde(rename = "upscaleFilter")]
+    pub upscale_filter: Option<String>,
+    pub filters: Option<Vec<FilterParams>>,
+    pub backdrop: Option<BackdropParams>,
+    pub frame: Option<String>,
+    #[serde(rename = "frameColor")]
+    pub frame_color: Option<String>,
+    #[serde(rename = "borderWidth")]
+    pub border_width: Option<u32>,
+    #[serde(rename = "borderColor")]
+    pub border_color: Option<String>,
+    #[serde(rename = "borderGradient")]
+    pub border_gradient: Option<String>,
+    #[serde(rename = "cornerRadius")]
+    pub corner_radius: Option<u32>,
+    pub logo: Option<LogoParams>,
+    pub stickers: Option<Vec<StickerParams>>,
+    pub stamp: Option<StampParams>,
+    pub bubbles: Option<Vec<BubbleParams>>,
+    pub qr: Option<QrParams>,
+    // テキストの {user} / {pr} / {repo} に埋め込む値
+    pub user: Option<String>,
+    pub pr: Option<String>,
+    pub repo: Option<String>,
+}
+
+// 文字を並べる線 (例: { "type": "arc", "radius": 0.35, "angle": 0 } / { "type": "bezier", "points": [[0.1, 0.8], [0.5, 0.2], [0.9, 0.8]] })
+#[derive(Deserialize, Debug)]
+pub struct TextPathParams {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub radius: Option<f32>,
+    pub angle: Option<f32>,
+    pub points: Option<Vec<(f32, f32)>>,
+}
+
+impl TextPathParams {
+    fn into_request(self) -> TextPathRequest {
+        TextPathRequest {
+            kind: self.kind,
+            radius: self.radius,
+            angle: self.angle,
+            points: self.points.unwrap_or_default(),
+        }
+    }
+}
+
+// texts 配列の要素 (例: { "text": "LOOKS GOOD TO ME", "path": { "type": "arc" } })
+#[derive(Deserialize, Debug)]
+pub struct ExtraTextParams {
+    pub text: String,
+    pub color: Option<String>,
+    pub position: Option<String>,
+    pub path: Option<TextPathParams>,
+    #[serde(rename = "writingMode")]
+    pub writing_mode: Option<String>,
+    pub effect: Option<TextEffectParams>,
+}
+
+impl ExtraTextParams {
+    fn into_request(self) -> ExtraTextRequest {
+        let defaults = LgtmRequest::default();
+        ExtraTextRequest {
+            text: self.text,
+            color_hex: self.color.unwrap_or(defaults.text_color_hex),
+            position: self.position.unwrap_or(defaults.text_position),
+            path: self.path.map(TextPathParams::into_request),
+            writing_mode: self.writing_mode.unwrap_or(defaults.writing_mode),
+            effect: self.effect.map(TextEffectParams::into_request),
+        }
+    }
+}
+
+// テキストの効果 (例: { "type": "neon", "color": "#FF3CAC" } / { "type": "3d", "width": 8 })
+#[derive(Deserialize, Debug)]
+pub struct TextEffectParams {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub color: Option<String>,
+    pub width: Option<u32>,
+    pub radius: Option<f32>,
+    pub angle: Option<f32>,
+    pub shade: Option<f32>,
+    pub step: Option<f32>,
+    pub slices: Option<u32>,
+    pub seed: Option<u32>,
+    pub stroke: Option<f32>,
+}
+
+impl TextEffectParams {
+    fn into_request(self) -> TextEffectRequest {
+        TextEffectRequest {
+            name: self.kind,
+            color_hex: self.color,
+            width: self.width,
+            radius: self.radius,
+            angle: self.angle,
+            shade: self.shade,
+            step: self.step,
+            slices: self.slices,
+            seed: self.seed,
+            stroke: self.stroke,
+        }
+    }
+}
+
+// 透かし風の繰り返し (例: { "angle": -30, "spacing": 1.0, "opacity": 0.3 })
+#[derive(Deserialize, Debug)]
+pub struct TileParams {
+    pub angle: Option<f32>,
+    pub spacing: Option<f32>,
+    pub opacity: Option<f32>,
+}
+
+// filters 配列の要素 (例: { "type": "blur", "amount": 4 })
+#[derive(Deserialize, Debug)]
+pub struct FilterParams {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub amount: Option<f32>,
+}
+
+// テキストの下のすりガラス (例: { "blur": 8, "darken": 0.35, "padding": 16, "feather": 12 })
+#[derive(Deserialize, Debug)]
+pub struct BackdropParams {
+    pub blur: Option<f32>,
+    pub darken: Option<f32>,
+    pub padding: Option<u32>,
+    pub feather: Option<u32>,
+}
+
+// ロゴの指定 (例: { "asset": "team-logo", "position": "bottom-right", "scale": 0.2 })
+#[derive(Deserialize, Debug)]
+pub struct LogoParams {
+    pub asset: String, // サーバーの assets ディレクトリにある画像の名前
+    pub position: Option<String>,
+    pub scale: Option<f32>,
+    pub opacity: Option<f32>,
+    pub margin: Option<u32>,
+}
+
+impl LogoParams {
+    fn into_request(self) -> LogoRequest {
+        logo_request(LogoSource::Asset(self.asset), self.position, self.scale, self.opacity, self.margin)
+    }
+}
+
+fn logo_request(source: LogoSource, position: Option<String>, scale: Option<f32>, opacity: Option<f32>, margin: Option<u32>) -> LogoRequest {
+    let defaults = LogoRequest::new(source);
+    LogoRequest {
+        position: position.unwrap_or(defaults.position),
+        scale: scale.unwrap_or(defaults.scale),
+        opacity: opacity.unwrap_or(defaults.opacity),
+        margin: margin.unwrap_or(defaults.margin),
+        source: defaults.source,
+    }
+}
+
+// 同梱ステッカーの指定 (例: { "name": "thumbsup", "position": "top-right" })
+#[derive(Deserialize, Debug)]
+pub struct StickerParams {
+    pub name: String,
+    pub position: Option<String>,
+    pub scale: Option<f32>,
+    pub opacity: Option<f32>,
+    pub margin: Option<u32>,
+}
+
+impl StickerParams {
+    fn into_request(self) -> StickerRequest {
+        let defaults = StickerRequest::new(self.name);
+        StickerRequest {
+            position: self.position.unwrap_or(defaults.position),
+            scale: self.scale.unwrap_or(defaults.scale),
+            opacity: self.opacity.unwrap_or(defaults.opacity),
+            margin: self.margin.unwrap_or(defaults.margin),
+            shortcode: defaults.shortcode,
+        }
+    }
+}
+
+// 判子の指定 (例: { "text": "承認", "name": "佐藤", "date": "today" })
+#[derive(Deserialize, Debug)]
+pub struct StampParams {
+    pub text: Option<String>,
+    pub name: Option<String>,
+    pub date: Option<String>,
+    pub shape: Option<String>,
+    pub layout: Option<String>,
+    pub color: Option<String>,
+    pub position: Option<String>,
+    pub scale: Option<f32>,
+    pub margin: Option<u32>,
+    pub distress: Option<f32>,
+    pub angle: Option<f32>,
+}
+
+impl StampParams {
+    fn into_request(self) -> StampRequest {
+        let defaults = StampRequest::default();
+        StampRequest {
+            text: self.text.unwrap_or(defaults.text),
+            name: self.name,
+            date: self.date,
+            shape: self.shape.unwrap_or(defaults.shape),
+            layout: self.layout.unwrap_or(defaults.layout),
+            color_hex: self.color.unwrap_or(defaults.color_hex),
+            position: self.position.unwrap_or(defaults.position),
+            scale: self.scale.unwrap_or(defaults.scale),
+            margin: self.margin.unwrap_or(defaults.margin),
+            distress: self.distress.unwrap_or(defaults.distress),
+            angle: self.angle.unwrap_or(defaults.angle),
+        }
+    }
+}
+
+// 吹き出しの指定 (例: { "text": "LGTM!", "target": [0.7, 0.6] })
+#[derive(Deserialize, Debug)]
+pub struct BubbleParams {
+    pub text: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub position: Option<String>,
+    pub margin: Option<u32>,
+    pub target: Option<(f32, f32)>,
+    pub tail: Option<String>,
+    #[serde(rename = "textSize")]
+    pub text_size: Option<f32>,
+    #[serde(rename = "textColor")]
+    pub text_color: Option<String>,
+    pub fill: Option<String>,
+    #[serde(rename = "borderColor")]
+    pub border_color: Option<String>,
+    #[serde(rename = "borderWidth")]
+    pub border_width: Option<u32>,
+}
+
+impl BubbleParams {
+    fn into_request(self) -> BubbleRequest {
+        let defaults = BubbleRequest::new(self.text);
+        BubbleRequest {
+            kind: self.kind.unwrap_or(defaults.kind),
+            position: self.position.unwrap_or(defaults.position),
+            margin: self.margin.unwrap_or(defaults.margin),
+            target: self.target,
+            tail: self.tail.unwrap_or(defaults.tail),
+            text_size: self.text_size.unwrap_or(defaults.text_size),
+            text_color_hex: self.text_color.unwrap_or(defaults.text_color_hex),
+            fill_hex: self.fill.unwrap_or(defaults.fill_hex),
+            border_hex: self.border_color.unwrap_or(defaults.border_hex),
+            border_width: self.border_width.unwrap_or(defaults.border_width),
+            text: defaults.text,
+        }
+    }
+}
+
+// QR コードの指定 (例: { "content": "https://github.com/owner/repo/pull/42", "position": "bottom-right" })
+#[derive(Deserialize, Debug)]
+pub struct QrParams {
+    pub content: String,
+    pub position: Option<String>,
+    pub size: Option<f32>,
+    pub margin: Option<u32>,
+    #[serde(rename = "quietZone")]
+    pub quiet_zone: Option<u32>,
+    #[serde(rename = "darkColor")]
+    pub dark_color: Option<String>,
+    #[serde(rename = "lightColor")]
+    pub light_color: Option<String>,
+}
+
+impl QrParams {
+    fn into_request(self) -> QrRequest {
+        let defaults = QrRequest::new(self.content);
+        QrRequest {
+            position: self.position.unwrap_or(defaults.position),
+            size: self.size.unwrap_or(defaults.size),
+            margin: self.margin.unwrap_or(defaults.margin),
+            quiet_zone: self.quiet_zone.unwrap_or(defaults.quiet_zone),
+            dark_hex: self.dark_color.unwrap_or(defaults.dark_hex),
+            light_hex: self.light_color.unwrap_or(defaults.light_hex),
+            content: defaults.content,
+        }
+    }
+}
+
+impl LgtmParams {
+    // 省略した項目は LgtmRequest の既定値になる
+    pub fn into_request(self) -> LgtmRequest {
+        let defaults = LgtmRequest::default();
+        LgtmRequest {
+            text: self.text.unwrap_or(defaults.text),
+            locale: self.locale,
+            seed: self.seed,
+            subtitle: self.subtitle,
+            text_color_hex: self.text_color.unwrap_or(defaults.text_color_hex),
+            text_position: self.text_position.unwrap_or(defaults.text_position),
+            text_path: self.text_path.map(TextPathParams::into_request),
+            writing_mode: self.writing_mode.unwrap_or(defaults.writing_mode),
+            text_effect: self.text_effect.map(TextEffectParams::into_request),
+            tile: self.tile.map(|t| TileRequest { angle: t.angle, spacing: t.spacing, opacity: t.opacity }),
+            extra_texts: self.texts.unwrap_or_default().into_iter().map(ExtraTextParams::into_request).collect(),
+            output_format: self.output_format.unwrap_or(defaults.output_format),
+            max_bytes: self.max_bytes,
+            colors: self.colors,
+            dither: self.dither.unwrap_or(defaults.dither),
+            metadata: self.metadata.unwrap_or(defaults.metadata),
+            crop: self.crop,
+            aspect: self.aspect,
+            width: self.width,
+            height: self.height,
+            resize_mode: self.resize_mode.unwrap_or(defaults.resize_mode),
+            max_width: self.max_width,
+            max_height: self.max_height,
+            padding_color_hex: self.padding_color.unwrap_or(defaults.padding_color_hex),
+            min_size: self.min_size.unwrap_or(defaults.min_size),
+            upscale_filter: self.upscale_filter.unwrap_or(defaults.upscale_filter),
+            filters: self.filters.unwrap_or_default().into_iter()
+                .map(|f| FilterRequest { name: f.kind, amount: f.amount })
+                .collect(),
+            backdrop: self.backdrop.map(|b| BackdropRequest {
+                blur: b.blur,
+                darken: b.darken,
+                padding: b.padding,
+                feather: b.feather,
+            }),
+            frame: self.frame,
+            frame_color_hex: self.frame_color.unwrap_or(defaults.frame_color_hex),
+            border_width: self.border_width.unwrap_or(defaults.border_width),
+            border_color_hex: self.border_color.unwrap_or(defaults.border_color_hex),
+            border_gradient_hex: self.border_gradient,
+            corner_radius: self.corner_radius.unwrap_or(defaults.corner_radius),
+            logo: self.logo.map(LogoParams::into_request),
+            stickers: self.stickers.unwrap_or_default().into_iter().map(StickerParams::into_request).collect(),
+            stamp: self.stamp.map(StampParams::into_request),
+            bubbles: self.bubbles.unwrap_or_default().into_iter().map(BubbleParams::into_request).collect(),
+            qr: self.qr.map(QrParams::into_request),
+            user: self.user,
+            pr: self.pr,
+            repo: self.repo,
+        }
+    }
+}
+
+// /upload のクエリパラメータ
+// 描き方は multipart の "options" フィールドに /fetch の本文 (url 以外) と同じ JSON で書く
+// ロゴは multipart の "logo" フィールドでアップロードするか、logo でサーバー側の画像を指定する
+#[derive(Deserialize, Debug, Default)]
+pub struct UploadImageParams {
+    pub text: Option<String>, // "random" なら辞書から選ぶ
+    pub locale: Option<String>,
+    pub seed: Option<u64>,
+    pub subtitle: Option<String>,
+    #[serde(rename = "maxBytes")]
+    pub max_bytes: Option<usize>,
+    pub logo: Option<String>,
+    #[serde(rename = "logoPosition")]
+    pub logo_position: Option<String>,
+    #[serde(rename = "logoScale")]
+    pub logo_scale: Option<f32>,
+    #[serde(rename = "logoOpacity")]
+    pub logo_opacity: Option<f32>,
+    #[serde(rename = "logoMargin")]
+    pub logo_margin: Option<u32>,
+    // 指定すると、送った画像を全部 1 枚に並べてから描く ("grid" / "strip")
+    pub collage: Option<String>,
+    #[serde(rename = "collageColumns")]
+    pub collage_columns: Option<u32>,
+    #[serde(rename = "collageGutter")]
+    pub collage_gutter: Option<u32>,
+    #[serde(rename = "collageBackground")]
+    pub collage_background: Option<String>,
+}
+
+// multipart で描き方を送るときのフィールド名
+const OPTIONS_FIELD_NAME: &str = "options";
+
+// multipart でロゴを送るときのフィールド名
+const LOGO_FIELD_NAME: &str = "logo";
+
+// 最終的な寸法とエンコード設定をレスポンスヘッダに載せる
+fn with_output_headers(builder: Builder, output: &LgtmOutput) -> Builder {
+    let builder = builder
+        .header("X-Lgtm-Width", output.width)
+        .header("X-Lgtm-Height", output.height)
+        .header("X-Lgtm-Scale", output.settings.scale.to_string());
+    let builder = match output.settings.format {
+        image::ImageFormat::Jpeg => builder.header("X-Lgtm-Quality", output.settings.quality as u32),
+        _ => builder,
+    };
+    let builder = match output.settings.max_colors {
+        Some(colors) => builder.header("X-Lgtm-Colors", colors),
+        None => builder,
+    };
+    // ギャラリーに残した画像の ID
+    match &output.gallery_id {
+        Some(id) => builder.header("X-Lgtm-Id", id),
+        None => builder,
+    }
+}
+
+// TODO: ファイル保存は FileStorage サービス経由にしたい
+// /preview と /download で返す output.png を書く (ギャラリーにはサービスが残す)
+async fn save_output(output: &LgtmOutput) -> Result<(), ApplicationError> {
+    // For now, map IO errors to ApplicationError::InfrastructureError manually or via a helper
+    let mut file = TokioFile::create("output.png").await.map_err(|e| ApplicationError::InfrastructureError(super::error::InfrastructureError::IoError(e)))?;
+    file.write_all(&output.data).await.map_err(|e| ApplicationError::InfrastructureError(super::error::InfrastructureError::IoError(e)))?;
+    Ok(())
 }
 
 pub async fn upload_image_handler(
     State(state): State<Arc<AppState>>,
+    Query(params): Query<UploadImageParams>,
     mut multipart: Multipart,
 ) -> Result<impl IntoResponse, ApplicationError> { // Changed to ApplicationError
+    // options やロゴのフィールドが画像より後に来てもいいように、先に全部読んでおく
+    let mut images = Vec::new();
+    let mut options = None;
+    let mut uploaded_logo = None;
     // Simplified error handling for multipart processing for this step
     // Proper error mapping from multipart errors to ApplicationError would be more robust
     while let Some(field) = multipart.next_field().await.map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Multipart error: {}", e)))? {
+        let is_options = field.name() == Some(OPTIONS_FIELD_NAME);
+        let is_logo = field.name() == Some(LOGO_FIELD_NAME);
         let data = field.bytes().await.map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to read bytes from multipart field: {}", e)))?;
+        if is_options {
+            let parsed: LgtmParams = serde_json::from_slice(&data)
+                .map_err(|e| DomainError::InvalidInput(format!("Invalid options: {}", e)))?;
+            options = Some(parsed);
+        } else if is_logo {
+            uploaded_logo = Some(data.to_vec());
+        } else {
+            images.push(data.to_vec());
+        }
+    }
+
+    // 描き方は /fetch と同じ。クエリの text / locale / seed / subtitle / maxBytes は options で指定しなかったときに使う
+    let options = options.unwrap_or_default();
+    let request = LgtmParams {
+        text: options.text.or(params.text),
+        locale: options.locale.or(params.locale),
+        seed: options.seed.or(params.seed),
+        subtitle: options.subtitle.or(params.subtitle),
+        max_bytes: options.max_bytes.or(params.max_bytes),
+        ..options
+    }
+    .into_request();
+    // ロゴはアップロードしたもの、クエリの logo、options の logo の順に使う
+    let logo_source = match (uploaded_logo, params.logo) {
+        (Some(data), _) => Some(LogoSource::Upload(data)),
+        (None, Some(name)) => Some(LogoSource::Asset(name)),
+        (None, None) => None,
+    };
+    let request = LgtmRequest {
+        logo: logo_source
+            .map(|source| logo_request(source, params.logo_position, params.logo_scale, params.logo_opacity, params.logo_margin))
+            .or(request.logo),
+        ..request
+    };
 
-        let text = "LGTM".to_string(); // Default text
-        let text_color_hex = "#FFFFFFFF".to_string(); // Default color
-        let text_position_str = "center".to_string(); // Default position
-        let output_format_str = "png".to_string(); // Default output format
-
-        let (processed_image_data, _content_type) = state.lgtm_service.generate_lgtm_image(
-            data.to_vec(),
-            text,
-            text_color_hex,
-            text_position_str,
-            output_format_str, // "png"
-        ).await?; // Use `?` due to `From<ApplicationError>` for `InfrastructureError`
-
-        // TODO: ファイル保存は FileStorage サービス経由にしたい
-        // For now, map IO errors to ApplicationError::InfrastructureError manually or via a helper
-        let mut file = TokioFile::create("output.png").await.map_err(|e| ApplicationError::InfrastructureError(super::error::InfrastructureError::IoError(e)))?;
-        file.write_all(&processed_image_data).await.map_err(|e| ApplicationError::InfrastructureError(super::error::InfrastructureError::IoError(e)))?;
+    // 画像が 2 枚以上あれば collage を省略してもグリッドに並べる (1 枚ずつ描くと最後の 1 枚しか返せないので)
+    let collage = match params.collage {
+        Some(layout) => Some(layout),
+        None if images.len() > 1 => Some(CollageRequest::default().layout),
+        None => None,
     }
+    .map(|layout| {
+        let defaults = CollageRequest::default();
+        CollageRequest {
+            layout,
+            columns: params.collage_columns,
+            gutter: params.collage_gutter.unwrap_or(defaults.gutter),
+            background_hex: params.collage_background.unwrap_or(defaults.background_hex),
+        }
+    });
+
+    let output = match collage {
+        // 全部の画像を 1 枚に並べて 1 回だけ描く
+        Some(collage) => Some(state.lgtm_service.generate_collage(images, &collage, &request).await?),
+        None => match images.pop() {
+            Some(data) => Some(state.lgtm_service.generate_lgtm_image(data, &request).await?), // Use `?` due to `From<ApplicationError>` for `InfrastructureError`
+            None => None,
+        },
+    };
 
-    Ok("画像アップロード完了".to_string())
+    let builder = match &output {
+        Some(output) => {
+            save_output(output).await?;
+            with_output_headers(Response::builder(), output)
+        }
+        None => Response::builder(),
+    };
+    builder
+        .body(Body::from("画像アップロード完了"))
+        .map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to build upload response: {}", e)))
 }
 
 pub async fn preview_image_handler(
@@ -94,21 +597,96 @@ pub async fn fetch_image_handler(
     State(state): State<Arc<AppState>>,
     Json(params): Json<FetchImageParams>,
 ) -> Result<impl IntoResponse, ApplicationError> { // Changed to ApplicationError
-    let actual_text = params.text.unwrap_or_else(|| "LGTM".to_string());
-    let actual_color_hex = params.text_color.unwrap_or_else(|| "#FFFFFFFF".to_string());
-    let actual_position_str = params.text_position.unwrap_or_else(|| "center".to_string());
-    let desired_format_str = params.output_format.unwrap_or_else(|| "png".to_string());
+    let request = params.options.into_request();
 
-    let (processed_image_data, content_type) = state.lgtm_service.generate_lgtm_image_from_url(
+    let output = state.lgtm_service.generate_lgtm_image_from_url(
         params.url,
-        actual_text,
-        actual_color_hex,
-        actual_position_str,
-        desired_format_str,
+        &request,
     ).await?; // Use `?`
 
+    with_output_headers(Response::builder(), &output)
+        .header("Content-Type", output.content_type)
+        .body(Body::from(output.data))
+        .map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to build fetch response: {}", e)))
+}
+
+// /meme のリクエスト (例: { "url": "...", "top": "one does not simply", "bottom": "merge without review" })
+#[derive(Deserialize, Debug)]
+pub struct MemeParams {
+    pub url: String,
+    pub top: Option<String>,
+    pub bottom: Option<String>,
+    #[serde(rename = "outputFormat")]
+    pub output_format: Option<String>,
+    #[serde(rename = "maxBytes")]
+    pub max_bytes: Option<usize>,
+}
+
+pub async fn meme_image_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<MemeParams>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let defaults = MemeRequest::default();
+    let request = MemeRequest {
+        top: params.top.unwrap_or(defaults.top),
+        bottom: params.bottom.unwrap_or(defaults.bottom),
+        output_format: params.output_format.unwrap_or(defaults.output_format),
+        max_bytes: params.max_bytes,
+    };
+
+    let output = state.lgtm_service.generate_meme_from_url(params.url, &request).await?;
+
+    with_output_headers(Response::builder(), &output)
+        .header("Content-Type", output.content_type)
+        .body(Body::from(output.data))
+        .map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to build meme response: {}", e)))
+}
+
+// /contact-sheet のクエリパラメータ
+#[derive(Deserialize, Debug, Default)]
+pub struct ContactSheetParams {
+    pub count: Option<usize>,
+    pub columns: Option<u32>,
+    #[serde(rename = "thumbnailSize")]
+    pub thumbnail_size: Option<u32>,
+    pub gutter: Option<u32>,
+    pub background: Option<String>,
+    #[serde(rename = "labelColor")]
+    pub label_color: Option<String>,
+}
+
+// ギャラリーの最近の画像を ID 付きのサムネイルで並べた 1 枚を返す
+pub async fn contact_sheet_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ContactSheetParams>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let defaults = ContactSheetRequest::default();
+    let request = ContactSheetRequest {
+        count: params.count.unwrap_or(defaults.count),
+        columns: params.columns,
+        thumbnail_size: params.thumbnail_size.unwrap_or(defaults.thumbnail_size),
+        gutter: params.gutter.unwrap_or(defaults.gutter),
+        background_hex: params.background.unwrap_or(defaults.background_hex),
+        label_color_hex: params.label_color.unwrap_or(defaults.label_color_hex),
+    };
+
+    let output = state.lgtm_service.generate_contact_sheet(&request).await?;
+
+    with_output_headers(Response::builder(), &output)
+        .header("Content-Type", output.content_type)
+        .body(Body::from(output.data))
+        .map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to build contact sheet response: {}", e)))
+}
+
+// 一覧に書かれた ID の画像をそのまま返す
+pub async fn gallery_image_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApplicationError> {
+    let (data, content_type) = state.lgtm_service.load_from_gallery(&id).await?;
+
     Response::builder()
         .header("Content-Type", content_type)
-        .body(Body::from(processed_image_data))
-        .map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to build fetch response: {}", e)))
+        .body(Body::from(data))
+        .map_err(|e| ApplicationError::LgtmGenerationFailed(format!("Failed to build gallery response: {}", e)))
 }
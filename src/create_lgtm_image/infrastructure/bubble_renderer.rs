@@ -0,0 +1,310 @@
+use super::fonts::FontSet;
+use super::text_layout::{layout_wrapped_text, place, TextLayout};
+use super::text_renderer::{draw_text_runs, TextPaint};
+use crate::domain::bubble::{BubbleKind, BubbleOverlay, TailSide};
+use crate::domain::color::Color;
+use crate::domain::position::Position as DomainPosition;
+use image::{Pixel, Rgba, RgbaImage};
+
+const MAX_TEXT_WIDTH_RATIO: f32 = 0.6; // 文字を折り返す幅 (画像の幅に対する比率)
+const MAX_TEXT_HEIGHT_RATIO: f32 = 0.5; // 文字を積んでよい高さ (画像の高さに対する比率)
+const PADDING_RATIO: f32 = 0.6; // 文字と枠の間 (文字の大きさに対する比率)
+const BUMP_RATIO: f32 = 0.45; // 雲のでこぼこの半径 (同上)
+
+type Point = (f32, f32);
+
+// 吹き出しを組み立てる図形。距離 (内側で負) の小さい方を取って 1 つの形にする
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    RoundedRect { center: Point, half: Point, radius: f32 },
+    Circle { center: Point, radius: f32 },
+    Triangle([Point; 3]),
+}
+
+impl Shape {
+    fn distance(&self, (px, py): Point) -> f32 {
+        match *self {
+            Shape::RoundedRect { center, half, radius } => {
+                let qx = (px - center.0).abs() - half.0 + radius;
+                let qy = (py - center.1).abs() - half.1 + radius;
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
+            }
+            Shape::Circle { center, radius } => (px - center.0).hypot(py - center.1) - radius,
+            Shape::Triangle(points) => triangle_distance((px, py), points),
+        }
+    }
+
+    // (左, 上, 右, 下)
+    fn bounds(&self) -> (f32, f32, f32, f32) {
+        match *self {
+            Shape::RoundedRect { center, half, .. } => (center.0 - half.0, center.1 - half.1, center.0 + half.0, center.1 + half.1),
+            Shape::Circle { center, radius } => (center.0 - radius, center.1 - radius, center.0 + radius, center.1 + radius),
+            Shape::Triangle(points) => points.iter().fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |(l, t, r, b), p| {
+                (l.min(p.0), t.min(p.1), r.max(p.0), b.max(p.1))
+            }),
+        }
+    }
+}
+
+// 三角形までの距離 (内側で負)
+fn triangle_distance(p: Point, [a, b, c]: [Point; 3]) -> f32 {
+    let sub = |u: Point, v: Point| (u.0 - v.0, u.1 - v.1);
+    let dot = |u: Point, v: Point| u.0 * v.0 + u.1 * v.1;
+    let cross = |u: Point, v: Point| u.0 * v.1 - u.1 * v.0;
+    let edges = [(a, sub(b, a)), (b, sub(c, b)), (c, sub(a, c))];
+    // 向き (時計回りか) で内外の符号をそろえる
+    let orientation = cross(sub(b, a), sub(a, c)).signum();
+    let mut nearest = f32::MAX;
+    let mut inside = true;
+    for (start, edge) in edges {
+        let v = sub(p, start);
+        let t = (dot(v, edge) / dot(edge, edge).max(f32::EPSILON)).clamp(0.0, 1.0);
+        let offset = (v.0 - edge.0 * t, v.1 - edge.1 * t);
+        nearest = nearest.min(dot(offset, offset));
+        if orientation * cross(v, edge) < 0.0 {
+            inside = false;
+        }
+    }
+    if inside { -nearest.sqrt() } else { nearest.sqrt() }
+}
+
+// 本体。雲は角の丸い四角のふちに丸を並べてでこぼこにする
+fn body_shapes(kind: BubbleKind, center: Point, half: Point, size: f32) -> Vec<Shape> {
+    match kind {
+        BubbleKind::Speech => vec![Shape::RoundedRect { center, half, radius: size.min(half.0).min(half.1) }],
+        BubbleKind::Thought => {
+            let bump = size * BUMP_RATIO;
+            let mut shapes = vec![Shape::RoundedRect { center, half, radius: half.0.min(half.1) * 0.5 }];
+            // 四角のふちを一周しながら、だいたい同じ間隔で丸を置く
+            let (inner_x, inner_y) = (half.0 - bump * 0.2, half.1 - bump * 0.2);
+            let corners = [(-inner_x, -inner_y), (inner_x, -inner_y), (inner_x, inner_y), (-inner_x, inner_y)];
+            for i in 0..4 {
+                let (from, to) = (corners[i], corners[(i + 1) % 4]);
+                let length = (to.0 - from.0).hypot(to.1 - from.1);
+                let count = ((length / (bump * 1.5)).round() as usize).max(1);
+                for j in 0..count {
+                    let t = j as f32 / count as f32;
+                    let point = (center.0 + from.0 + (to.0 - from.0) * t, center.1 + from.1 + (to.1 - from.1) * t);
+                    shapes.push(Shape::Circle { center: point, radius: bump });
+                }
+            }
+            shapes
+        }
+    }
+}
+
+// しっぽを出す辺の外向きの向き。Auto のときは本体から target を見た向きで、target が本体の中なら None
+fn tail_normal(tail: TailSide, center: Point, half: Point, target: Point) -> Option<Point> {
+    match tail {
+        TailSide::Top => Some((0.0, -1.0)),
+        TailSide::Bottom => Some((0.0, 1.0)),
+        TailSide::Left => Some((-1.0, 0.0)),
+        TailSide::Right => Some((1.0, 0.0)),
+        TailSide::Auto => {
+            // 本体の縦横比をならしてから、より離れている向きを選ぶ
+            let (rx, ry) = ((target.0 - center.0) / half.0.max(1.0), (target.1 - center.1) / half.1.max(1.0));
+            if rx.abs() <= 1.0 && ry.abs() <= 1.0 {
+                None
+            } else if rx.abs() > ry.abs() {
+                Some((rx.signum(), 0.0))
+            } else {
+                Some((0.0, ry.signum()))
+            }
+        }
+    }
+}
+
+// しっぽ。吹き出しは辺から target まで伸びる三角、雲は target に向かって小さくなる 3 つの丸
+fn tail_shapes(kind: BubbleKind, tail: TailSide, center: Point, half: Point, target: Point, size: f32) -> Vec<Shape> {
+    let Some(normal) = tail_normal(tail, center, half, target) else { return Vec::new() };
+    let tangent = (-normal.1, normal.0);
+    let (tangent_half, normal_half) = if normal.0 == 0.0 { (half.0, half.1) } else { (half.1, half.0) };
+    let base_half = (size * 0.5).min(tangent_half * 0.4);
+    // 辺の上で target に一番近いところ (角の丸みにはかからないようにする)
+    let reach = (tangent_half - size.min(half.0).min(half.1) - base_half).max(0.0);
+    let along = ((target.0 - center.0) * tangent.0 + (target.1 - center.1) * tangent.1).clamp(-reach, reach);
+    let edge = (
+        center.0 + normal.0 * normal_half + tangent.0 * along,
+        center.1 + normal.1 * normal_half + tangent.1 * along,
+    );
+    match kind {
+        BubbleKind::Speech => {
+            // 根元を本体の内側に入れて、本体の枠線がしっぽの付け根で途切れるようにする
+            let inset = (size * 0.5).min(normal_half * 0.9);
+            let base = (edge.0 - normal.0 * inset, edge.1 - normal.1 * inset);
+            vec![Shape::Triangle([
+                (base.0 - tangent.0 * base_half, base.1 - tangent.1 * base_half),
+                (base.0 + tangent.0 * base_half, base.1 + tangent.1 * base_half),
+                target,
+            ])]
+        }
+        BubbleKind::Thought => {
+            let start_gap = size * (BUMP_RATIO + 0.5);
+            let start = (edge.0 + normal.0 * start_gap, edge.1 + normal.1 * start_gap);
+            [(0.0, 0.35), (0.5, 0.25), (1.0, 0.15)]
+                .iter()
+                .map(|&(t, radius)| Shape::Circle {
+                    center: (start.0 + (target.0 - start.0) * t, start.1 + (target.1 - start.1) * t),
+                    radius: size * radius,
+                })
+                .collect()
+        }
+    }
+}
+
+fn to_rgba(color: &Color) -> [f32; 4] {
+    [color.r as f32, color.g as f32, color.b as f32, color.a as f32]
+}
+
+// 図形を塗る。形のふちから border_width px 内側までを枠線の色、その内側を fill の色にする
+fn fill_shapes(img: &mut RgbaImage, shapes: &[Shape], fill: &Color, border: &Color, border_width: f32) {
+    let (left, top, right, bottom) = shapes.iter().map(Shape::bounds).fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |acc, b| {
+        (acc.0.min(b.0), acc.1.min(b.1), acc.2.max(b.2), acc.3.max(b.3))
+    });
+    let (fill, border) = (to_rgba(fill), to_rgba(border));
+    let x_range = (left.floor().max(0.0) as u32)..((right.ceil() + 1.0).max(0.0) as u32).min(img.width());
+    let y_range = (top.floor().max(0.0) as u32)..((bottom.ceil() + 1.0).max(0.0) as u32).min(img.height());
+    for y in y_range {
+        for x in x_range.clone() {
+            let point = (x as f32 + 0.5, y as f32 + 0.5);
+            let distance = shapes.iter().map(|shape| shape.distance(point)).fold(f32::MAX, f32::min);
+            let coverage = (0.5 - distance).clamp(0.0, 1.0);
+            if coverage == 0.0 {
+                continue;
+            }
+            // 覆われた部分のうち fill になる割合
+            let inner = (0.5 - (distance + border_width)).clamp(0.0, 1.0) / coverage;
+            let mix = |i: usize| border[i] + (fill[i] - border[i]) * inner;
+            let color = Rgba([mix(0).round() as u8, mix(1).round() as u8, mix(2).round() as u8, (mix(3) * coverage).round() as u8]);
+            img.get_pixel_mut(x, y).blend(&color);
+        }
+    }
+}
+
+// 文字を測って大きさを決めた吹き出しを描く。本体は position に従ってふちから margin 内側に置く
+pub fn draw_bubble(img: &mut RgbaImage, fonts: &FontSet, bubble: &BubbleOverlay) {
+    let (width, height) = img.dimensions();
+    let base_scale = (height as f32 * bubble.text_size).max(1.0);
+    let wrap_area = (0, 0, (width as f32 * MAX_TEXT_WIDTH_RATIO) as u32, height);
+    let lines = layout_wrapped_text(fonts.primary, &bubble.text, &DomainPosition::TopCenter, wrap_area, base_scale, height as f32 * MAX_TEXT_HEIGHT_RATIO);
+    let Some((first, _)) = lines.first() else { return };
+    let size = first.scale.y;
+    let text_left = lines.iter().map(|(l, _)| l.x).min().unwrap_or(0);
+    let text_right = lines.iter().map(|(l, _)| l.x + l.width as i32).max().unwrap_or(0);
+    let text_bottom = lines.iter().map(|(l, _)| l.y + l.height as i32).max().unwrap_or(0);
+    let (text_width, text_height) = ((text_right - text_left) as f32, (text_bottom - first.y) as f32);
+
+    let padding = size * PADDING_RATIO;
+    let half = (text_width / 2.0 + padding, text_height / 2.0 + padding);
+    // 雲のでこぼこと枠線のぶんも含めて画像に収める
+    let outer = match bubble.kind {
+        BubbleKind::Speech => 0.0,
+        BubbleKind::Thought => size * BUMP_RATIO,
+    } + bubble.border_width as f32;
+    let margin = bubble.margin.min(width / 2).min(height / 2);
+    let area = (margin as i32, margin as i32, width - margin * 2, height - margin * 2);
+    let (left, top) = place(&bubble.position, (half.0 * 2.0 + outer * 2.0, half.1 * 2.0 + outer * 2.0), area);
+    let center = (left as f32 + outer + half.0, top as f32 + outer + half.1);
+
+    let mut shapes = body_shapes(bubble.kind, center, half, size);
+    if let Some((tx, ty)) = bubble.target {
+        let target = (tx * width as f32, ty * height as f32);
+        shapes.extend(tail_shapes(bubble.kind, bubble.tail, center, half, target, size));
+    }
+    fill_shapes(img, &shapes, &bubble.fill, &bubble.border, bubble.border_width as f32);
+
+    let color = Rgba([bubble.text_color.r, bubble.text_color.g, bubble.text_color.b, bubble.text_color.a]);
+    let dx = (center.0 - text_width / 2.0).round() as i32 - text_left;
+    let dy = (center.1 - text_height / 2.0).round() as i32 - first.y;
+    for (layout, line) in &lines {
+        let layout = TextLayout { x: layout.x + dx, y: layout.y + dy, ..*layout };
+        draw_text_runs(img, TextPaint::Solid(color), &layout, fonts.primary, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusttype::Font;
+
+    fn bubble(kind: BubbleKind, target: Option<(f32, f32)>) -> BubbleOverlay {
+        BubbleOverlay {
+            text: "LGTM!".to_string(),
+            kind,
+            position: DomainPosition::TopLeft,
+            margin: 10,
+            target,
+            tail: TailSide::Auto,
+            text_size: 0.08,
+            text_color: Color::new(0, 0, 0, 255),
+            fill: Color::new(255, 255, 255, 255),
+            border: Color::new(255, 0, 0, 255),
+            border_width: 3,
+        }
+    }
+
+    fn render(bubble: &BubbleOverlay) -> RgbaImage {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        let mut img = RgbaImage::from_pixel(400, 300, Rgba([0, 0, 255, 255]));
+        draw_bubble(&mut img, &FontSet { primary: &font, fallback: None }, bubble);
+        img
+    }
+
+    #[test]
+    fn test_triangle_distance_sign() {
+        let triangle = [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)];
+        assert!(triangle_distance((2.0, 2.0), triangle) < 0.0);
+        assert!((triangle_distance((5.0, -3.0), triangle) - 3.0).abs() < 1e-4);
+        // 頂点の順番が逆でも同じ
+        let reversed = [(0.0, 10.0), (10.0, 0.0), (0.0, 0.0)];
+        assert_eq!(triangle_distance((2.0, 2.0), triangle), triangle_distance((2.0, 2.0), reversed));
+    }
+
+    #[test]
+    fn test_auto_tail_faces_target() {
+        let (center, half) = ((100.0, 100.0), (50.0, 20.0));
+        assert_eq!(tail_normal(TailSide::Auto, center, half, (120.0, 200.0)), Some((0.0, 1.0)));
+        assert_eq!(tail_normal(TailSide::Auto, center, half, (300.0, 120.0)), Some((1.0, 0.0)));
+        assert_eq!(tail_normal(TailSide::Auto, center, half, (110.0, 105.0)), None);
+        assert_eq!(tail_normal(TailSide::Top, center, half, (120.0, 200.0)), Some((0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_speech_bubble_fits_text_and_points_tail_at_target() {
+        let img = render(&bubble(BubbleKind::Speech, Some((0.9, 0.9))));
+        let red = |x: u32, y: u32| img.get_pixel(x, y).0 == [255, 0, 0, 255];
+        // 左上に置いた本体は白く塗られ、文字 (黒) が入っている
+        let white = img.pixels().filter(|p| p.0 == [255, 255, 255, 255]).count();
+        assert!(white > 1000);
+        assert!(img.pixels().any(|p| p.0 == [0, 0, 0, 255]));
+        assert!(!red(5, 5) && img.get_pixel(5, 5).0 == [0, 0, 255, 255]);
+        // しっぽは target (360, 270) に向かって伸びる
+        assert!((296..304).any(|x| (218..230).any(|y| red(x, y))));
+        assert_eq!(img.get_pixel(390, 20).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_thought_bubble_tail_is_separate_circles() {
+        let target = (300.0, 250.0);
+        let tail = tail_shapes(BubbleKind::Thought, TailSide::Auto, (100.0, 60.0), (60.0, 30.0), target, 20.0);
+        let circles: Vec<(Point, f32)> = tail
+            .iter()
+            .map(|shape| match *shape {
+                Shape::Circle { center, radius } => (center, radius),
+                other => panic!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(circles.len(), 3);
+        // target に近づくほど小さく、最後の丸が target に乗る。丸どうしは離れている
+        assert!(circles.windows(2).all(|w| w[0].1 > w[1].1));
+        assert_eq!(circles[2].0, target);
+        for w in circles.windows(2) {
+            let gap = (w[1].0 .0 - w[0].0 .0).hypot(w[1].0 .1 - w[0].0 .1);
+            assert!(gap > w[0].1 + w[1].1);
+        }
+
+        let img = render(&bubble(BubbleKind::Thought, Some((0.9, 0.9))));
+        assert_ne!(img.get_pixel(360, 270).0, [0, 0, 255, 255]);
+        assert_eq!(img.get_pixel(330, 250).0, [0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::domain::collage::ContactSheet;
+use crate::domain::position::Position as DomainPosition;
+use super::text_layout::layout_text_in;
+use super::text_renderer::{draw_text_runs, TextPaint};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use rusttype::Font;
+
+// サムネイルの一辺に対する ID 欄の高さ
+const LABEL_HEIGHT_RATIO: f32 = 0.16;
+
+// 読めなかった画像の代わりに置く灰色の正方形
+pub fn placeholder_thumbnail(thumbnail_size: u32) -> RgbaImage {
+    RgbaImage::from_pixel(thumbnail_size.max(1), thumbnail_size.max(1), Rgba([204, 204, 204, 255]))
+}
+
+// サムネイル 1 枚分のマス。一辺 thumbnail_size の正方形に縦横比を保って縮めた画像を真ん中に置き、下に ID を書く
+// 小さい画像は引き伸ばさない。どのマスも同じ大きさなので compose_collage でそのまま並べられる
+pub fn render_contact_tile(font: &Font, img: &RgbaImage, id: &str, sheet: &ContactSheet) -> RgbaImage {
+    let size = sheet.thumbnail_size.max(1);
+    let label_height = ((size as f32 * LABEL_HEIGHT_RATIO).round() as u32).max(8);
+    let background = &sheet.collage.background;
+    let mut tile = RgbaImage::from_pixel(size, size + label_height, Rgba([background.r, background.g, background.b, background.a]));
+
+    let (w, h) = (img.width().max(1) as f32, img.height().max(1) as f32);
+    let fit = (size as f32 / w).min(size as f32 / h).min(1.0);
+    let (width, height) = (((w * fit).round() as u32).max(1), ((h * fit).round() as u32).max(1));
+    let x = (size - width.min(size)) / 2;
+    let y = (size - height.min(size)) / 2;
+    imageops::overlay(&mut tile, &imageops::resize(img, width, height, FilterType::Triangle), x as i64, y as i64);
+
+    let area = (0, size as i32, size, label_height);
+    let layout = layout_text_in(font, id, &DomainPosition::Center, area, label_height as f32 * 0.7);
+    let color = &sheet.label_color;
+    draw_text_runs(&mut tile, TextPaint::Solid(Rgba([color.r, color.g, color.b, color.a])), &layout, font, id);
+    tile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::collage::{Collage, CollageLayout};
+    use crate::domain::color::Color;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    #[test]
+    fn test_render_contact_tile_fits_thumbnail_and_draws_label() {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf") as &[u8]).unwrap();
+        let sheet = ContactSheet {
+            collage: Collage { layout: CollageLayout::Grid { columns: 4 }, gutter: 8, background: Color::new(255, 255, 255, 255) },
+            thumbnail_size: 100,
+            label_color: Color::new(0, 0, 0, 255),
+        };
+        // 横長の画像は 100x50 に縮んで上下に背景が残る
+        let tile = render_contact_tile(&font, &RgbaImage::from_pixel(400, 200, RED), "20261018-090512123", &sheet);
+        assert_eq!(tile.dimensions(), (100, 116));
+        assert_eq!(*tile.get_pixel(50, 10), WHITE);
+        assert_eq!(*tile.get_pixel(50, 50), RED);
+        assert_eq!(*tile.get_pixel(50, 90), WHITE);
+        // ID 欄に文字が書かれている
+        assert!((100..116).any(|y| (0..100).any(|x| tile.get_pixel(x, y)[0] < 128)));
+
+        // 小さい画像は引き伸ばさない
+        let small = render_contact_tile(&font, &RgbaImage::from_pixel(20, 20, RED), "1", &sheet);
+        assert_eq!(*small.get_pixel(50, 50), RED);
+        assert_eq!(*small.get_pixel(35, 50), WHITE);
+    }
+}
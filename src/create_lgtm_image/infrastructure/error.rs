@@ -15,6 +15,12 @@ pub enum InfrastructureError {
     #[error("Data decoding failed: {0}")]
     DecodingError(String),
 
+    #[error("Image exceeds decode limits: {0}")]
+    ImageLimitExceeded(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("Underlying image library error")]
     ImageLibError(#[from] image::ImageError), // image::ImageError をラップ
 
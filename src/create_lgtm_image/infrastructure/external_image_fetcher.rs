@@ -10,6 +10,7 @@ pub trait ExternalImageFetcher {
 }
 */
 
+#[derive(Default)]
 pub struct DefaultExternalImageFetcher;
 
 impl DefaultExternalImageFetcher {
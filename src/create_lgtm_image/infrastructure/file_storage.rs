@@ -3,6 +3,7 @@ use super::error::InfrastructureError; // Changed from anyhow::Result
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::fs; // fs::read を使うために追加
+use std::path::Path;
 
 // ドメイン層で定義する FileStorage トレイトの具体的な実装
 /*
@@ -12,6 +13,7 @@ pub trait FileStorage {
 }
 */
 
+#[derive(Default)]
 pub struct LocalFileStorage;
 
 impl LocalFileStorage {
@@ -30,3 +32,21 @@ impl LocalFileStorage {
         Ok(data)
     }
 }
+
+// 名前が空でなく、allowed な文字だけでできているか。"../" などでディレクトリの外を読まれないようにする
+pub(crate) fn is_safe_name(name: &str, allowed: impl Fn(char) -> bool) -> bool {
+    !name.is_empty() && name.chars().all(allowed)
+}
+
+// dir にある "name.拡張子" を extensions の順に探し、最初に見つかったファイルの中身と何番目の拡張子だったかを返す
+// どれも無ければ None。見つからない以外の I/O エラーはそのまま返す
+pub(crate) async fn read_first_existing(dir: &Path, name: &str, extensions: &[&str]) -> Result<Option<(Vec<u8>, usize)>, InfrastructureError> {
+    for (index, extension) in extensions.iter().enumerate() {
+        match fs::read(dir.join(format!("{}.{}", name, extension))).await {
+            Ok(data) => return Ok(Some((data, index))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,58 @@
+use super::error::InfrastructureError;
+use rusttype::Font;
+
+// 同梱フォント (DejaVu Sans Bold) には日本語のグリフがないので、
+// LGTM_FALLBACK_FONT に日本語フォントのパスを指定すると、足りない文字だけそちらで描く
+pub fn load_font_file(path: &str) -> Result<Font<'static>, InfrastructureError> {
+    let data = std::fs::read(path)?;
+    Font::try_from_vec(data).ok_or_else(|| InfrastructureError::ImageProcessingError(format!("Failed to load font: {}", path)))
+}
+
+// 起動時に読む。読めなくても同梱フォントだけで動かす
+pub fn fallback_font_from_env() -> Option<Font<'static>> {
+    let path = std::env::var("LGTM_FALLBACK_FONT").ok()?;
+    match load_font_file(&path) {
+        Ok(font) => Some(font),
+        Err(e) => {
+            eprintln!("LGTM_FALLBACK_FONT を読み込めませんでした: {}", e);
+            None
+        }
+    }
+}
+
+// 1 文字ずつ描くときに使うフォントの組み合わせ
+#[derive(Clone, Copy)]
+pub struct FontSet<'a> {
+    pub primary: &'a Font<'static>,
+    pub fallback: Option<&'a Font<'static>>,
+}
+
+impl<'a> FontSet<'a> {
+    // primary にグリフがなければ fallback を使う (どちらにもなければ primary の豆腐)
+    pub fn for_char(&self, c: char) -> &'a Font<'static> {
+        if self.primary.glyph(c).id().0 != 0 || c.is_whitespace() {
+            return self.primary;
+        }
+        match self.fallback {
+            Some(fallback) if fallback.glyph(c).id().0 != 0 => fallback,
+            _ => self.primary,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_char_falls_back_only_for_missing_glyphs() {
+        let primary = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        // 同じフォントを fallback にしても、primary にある文字は primary を使う
+        let fallback = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        let fonts = FontSet { primary: &primary, fallback: Some(&fallback) };
+        assert!(std::ptr::eq(fonts.for_char('L'), &primary));
+        // どちらにもない文字は primary
+        assert!(std::ptr::eq(fonts.for_char('承'), &primary));
+        assert!(load_font_file("/nonexistent/font.ttf").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use super::error::InfrastructureError;
+use super::file_storage::{is_safe_name, read_first_existing};
+use crate::domain::error::DomainError;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+const GALLERY_EXTENSIONS: [(&str, &str); 4] = [("png", "image/png"), ("jpg", "image/jpeg"), ("gif", "image/gif"), ("webp", "image/webp")];
+
+// 残しておく枚数のデフォルト。超えたら古いものから消す
+const DEFAULT_MAX_ENTRIES: usize = 500;
+
+// 作った LGTM 画像を ID を付けて残しておく場所
+// ID は保存した日時 ("20261018-090512123")。同じミリ秒のものには "-1", "-2", ... が付く
+#[derive(Debug, Clone)]
+pub struct LocalGalleryStore {
+    dir: PathBuf,
+    max_entries: usize,
+}
+
+impl Default for LocalGalleryStore {
+    fn default() -> Self {
+        Self::new("gallery")
+    }
+}
+
+impl LocalGalleryStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), max_entries: DEFAULT_MAX_ENTRIES }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    // LGTM_GALLERY_DIR で置き場所 (デフォルトは ./gallery)、LGTM_GALLERY_MAX_ENTRIES で残す枚数を変えられる
+    pub fn from_env() -> Self {
+        let store = match std::env::var("LGTM_GALLERY_DIR") {
+            Ok(dir) => Self::new(dir),
+            Err(_) => Self::default(),
+        };
+        match std::env::var("LGTM_GALLERY_MAX_ENTRIES").ok().and_then(|v| v.parse().ok()) {
+            Some(max_entries) => store.with_max_entries(max_entries),
+            None => store,
+        }
+    }
+
+    fn path(&self, id: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, extension))
+    }
+
+    // content_type に合う拡張子で保存して ID を返す。同じミリ秒に保存したものには "-1" などを足す
+    // 保存したあと max_entries を超えた分は古いものから消す
+    pub async fn save(&self, data: &[u8], content_type: &str) -> Result<String, InfrastructureError> {
+        let (extension, _) = GALLERY_EXTENSIONS
+            .iter()
+            .find(|(_, ct)| *ct == content_type)
+            .ok_or_else(|| InfrastructureError::FileStorageError(format!("Unsupported gallery content type: {}", content_type)))?;
+        fs::create_dir_all(&self.dir).await?;
+        let base = chrono::Local::now().format("%Y%m%d-%H%M%S%3f").to_string();
+        let mut n = 0;
+        loop {
+            let id = if n == 0 { base.clone() } else { format!("{}-{}", base, n) };
+            n += 1;
+            if GALLERY_EXTENSIONS.iter().any(|(ext, _)| self.path(&id, ext).exists()) {
+                continue;
+            }
+            match fs::OpenOptions::new().write(true).create_new(true).open(self.path(&id, extension)).await {
+                Ok(mut file) => {
+                    file.write_all(data).await?;
+                    self.prune().await?;
+                    return Ok(id);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // max_entries 個より古い画像を消す。ほかのリクエストが先に消していても気にしない
+    async fn prune(&self) -> Result<(), InfrastructureError> {
+        for id in self.list_recent(usize::MAX).await?.into_iter().skip(self.max_entries) {
+            for (extension, _) in GALLERY_EXTENSIONS {
+                match fs::remove_file(self.path(&id, extension)).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 新しい順に最大 limit 個の ID。まだ何も保存していなければ空
+    pub async fn list_recent(&self, limit: usize) -> Result<Vec<String>, InfrastructureError> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ids = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let (Some(id), Some(extension)) = (path.file_stem().and_then(|s| s.to_str()), path.extension().and_then(|e| e.to_str())) else {
+                continue;
+            };
+            if valid_id(id) && GALLERY_EXTENSIONS.iter().any(|(ext, _)| *ext == extension) {
+                ids.push(id.to_string());
+            }
+        }
+        // "-10" が "-2" より前に来ないように、末尾の番号は数として比べる
+        ids.sort_unstable_by(|a, b| sort_key(b).cmp(&sort_key(a)));
+        ids.dedup();
+        ids.truncate(limit);
+        Ok(ids)
+    }
+
+    // ID の画像と Content-Type。ID が不正な場合はリクエストの誤り (400)、無い場合は NotFound (404)
+    // 読めなかったときのそれ以外の I/O エラーはそのまま返す (500)
+    pub async fn load(&self, id: &str) -> Result<(Vec<u8>, &'static str), InfrastructureError> {
+        if !valid_id(id) {
+            return Err(DomainError::InvalidInput(format!("Invalid gallery id: {}", id)).into());
+        }
+        let extensions = GALLERY_EXTENSIONS.map(|(extension, _)| extension);
+        match read_first_existing(&self.dir, id, &extensions).await? {
+            Some((data, index)) => Ok((data, GALLERY_EXTENSIONS[index].1)),
+            None => Err(InfrastructureError::NotFound(format!("Unknown gallery id: {}", id))),
+        }
+    }
+}
+
+fn valid_id(id: &str) -> bool {
+    is_safe_name(id, |c| c.is_ascii_digit() || c == '-')
+}
+
+// "20261018-090512123-10" を ("20261018-090512123", 10) にする。番号が無ければ 0
+fn sort_key(id: &str) -> (&str, u64) {
+    match id.match_indices('-').nth(1) {
+        Some((i, _)) => (&id[..i], id[i + 1..].parse().unwrap_or(0)),
+        None => (id, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gallery_saves_lists_and_loads_by_id() {
+        let dir = std::env::temp_dir().join(format!("lgtm-gallery-{}", std::process::id()));
+        let store = LocalGalleryStore::new(&dir);
+        assert!(store.list_recent(10).await.unwrap().is_empty());
+
+        let first = store.save(b"first", "image/png").await.unwrap();
+        let second = store.save(b"second", "image/jpeg").await.unwrap();
+        let third = store.save(b"third", "image/png").await.unwrap();
+        std::fs::write(dir.join("notes.txt"), b"not an image").unwrap();
+
+        // 同じミリ秒に保存しても ID は重ならず、新しい順に並ぶ
+        assert_eq!(store.list_recent(10).await.unwrap(), vec![third.clone(), second.clone(), first.clone()]);
+        assert_eq!(store.list_recent(2).await.unwrap(), vec![third, second.clone()]);
+        assert_eq!(store.load(&second).await.unwrap(), (b"second".to_vec(), "image/jpeg"));
+        assert!(matches!(store.load("19700101-000000000").await, Err(InfrastructureError::NotFound(_))));
+        assert!(matches!(store.load("../notes").await, Err(InfrastructureError::DomainErrorWrapper(_))));
+        assert!(store.save(b"avif", "image/avif").await.is_err());
+
+        // ファイルではなくディレクトリになっているなど、見つからない以外の読み込みエラーは I/O エラー
+        std::fs::create_dir(dir.join("20000101-000000000.png")).unwrap();
+        assert!(matches!(store.load("20000101-000000000").await, Err(InfrastructureError::IoError(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_gallery_prunes_oldest_entries() {
+        let dir = std::env::temp_dir().join(format!("lgtm-gallery-prune-{}", std::process::id()));
+        let store = LocalGalleryStore::new(&dir).with_max_entries(2);
+        let first = store.save(b"first", "image/png").await.unwrap();
+        let second = store.save(b"second", "image/webp").await.unwrap();
+        let third = store.save(b"third", "image/jpeg").await.unwrap();
+
+        assert_eq!(store.list_recent(10).await.unwrap(), vec![third, second.clone()]);
+        assert_eq!(store.load(&second).await.unwrap(), (b"second".to_vec(), "image/webp"));
+        assert!(matches!(store.load(&first).await, Err(InfrastructureError::NotFound(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_gallery_orders_same_millisecond_ids_by_number() {
+        let dir = std::env::temp_dir().join(format!("lgtm-gallery-order-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for id in ["20261018-090512123", "20261018-090512123-2", "20261018-090512123-10", "20261018-090512124"] {
+            std::fs::write(dir.join(format!("{}.png", id)), id).unwrap();
+        }
+
+        let store = LocalGalleryStore::new(&dir);
+        assert_eq!(
+            store.list_recent(10).await.unwrap(),
+            vec!["20261018-090512124", "20261018-090512123-10", "20261018-090512123-2", "20261018-090512123"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use crate::domain::collage::{Collage, CollageLayout};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+// マスの高さを cell_height にして並べたときの全体の大きさ
+fn collage_size(sizes: &[(u32, u32)], collage: &Collage, cell_height: u32, cell_width: u32) -> (u32, u32) {
+    let gutter = collage.gutter;
+    let count = sizes.len() as u32;
+    match collage.layout {
+        CollageLayout::Strip => {
+            let widths: u32 = sizes.iter().map(|&(w, h)| scaled_width((w, h), cell_height)).sum();
+            (widths + gutter * (count + 1), cell_height + gutter * 2)
+        }
+        CollageLayout::Grid { columns } => {
+            let columns = columns.clamp(1, count);
+            let rows = count.div_ceil(columns);
+            (columns * cell_width + gutter * (columns + 1), rows * cell_height + gutter * (rows + 1))
+        }
+    }
+}
+
+// 高さ height にそろえたときの幅
+fn scaled_width((width, height): (u32, u32), target_height: u32) -> u32 {
+    ((width as f32 * target_height as f32 / height as f32).round() as u32).max(1)
+}
+
+// 画素数が max_pixels を超えるなら縦横比を保って縮める
+// コラージュでは 1 枚ずつデコード直後に呼び、全部を合わせても 1 枚分の上限ほどしかメモリに持たないようにする
+pub fn shrink_to_pixels(img: RgbaImage, max_pixels: u64) -> RgbaImage {
+    let pixels = img.width() as u64 * img.height() as u64;
+    if pixels <= max_pixels.max(1) {
+        return img;
+    }
+    let factor = (max_pixels.max(1) as f64 / pixels as f64).sqrt();
+    let height = ((img.height() as f64 * factor).floor() as u32).max(1);
+    // 極端に細長いと短い辺が 1px で止まるので、長い辺の側で予算に合わせる
+    let width = ((img.width() as f64 * factor).floor() as u32).clamp(1, (max_pixels / height as u64).max(1) as u32);
+    let height = height.min((max_pixels / width as u64).max(1) as u32);
+    imageops::resize(&img, width, height, FilterType::Triangle)
+}
+
+// images を collage の並べ方で 1 枚にする
+// マスの高さはいちばん低い画像に合わせ、グリッドのマスの幅は縦横比の平均から決める
+// 全体が max_side を超えるなら、すき間はそのままで画像を縮める
+pub fn compose_collage(images: &[RgbaImage], collage: &Collage, (max_width, max_height): (u32, u32)) -> RgbaImage {
+    let background = Rgba([collage.background.r, collage.background.g, collage.background.b, collage.background.a]);
+    let sizes: Vec<(u32, u32)> = images.iter().map(|img| (img.width().max(1), img.height().max(1))).collect();
+    let Some(min_height) = sizes.iter().map(|&(_, h)| h).min() else {
+        return RgbaImage::from_pixel(1, 1, background);
+    };
+    let aspect = sizes.iter().map(|&(w, h)| w as f32 / h as f32).sum::<f32>() / sizes.len() as f32;
+    let cell_width_for = |height: u32| ((height as f32 * aspect).round() as u32).max(1);
+
+    let mut cell_height = min_height;
+    let mut size = collage_size(&sizes, collage, cell_height, cell_width_for(cell_height));
+    while (size.0 > max_width || size.1 > max_height) && cell_height > 1 {
+        let factor = (max_width as f32 / size.0 as f32).min(max_height as f32 / size.1 as f32);
+        cell_height = ((cell_height as f32 * factor).floor() as u32).clamp(1, cell_height - 1);
+        size = collage_size(&sizes, collage, cell_height, cell_width_for(cell_height));
+    }
+
+    let gutter = collage.gutter as i64;
+    let mut canvas = RgbaImage::from_pixel(size.0, size.1, background);
+    match collage.layout {
+        CollageLayout::Strip => {
+            let mut x = gutter;
+            for img in images {
+                let width = scaled_width((img.width().max(1), img.height().max(1)), cell_height);
+                imageops::overlay(&mut canvas, &imageops::resize(img, width, cell_height, FilterType::Lanczos3), x, gutter);
+                x += width as i64 + gutter;
+            }
+        }
+        CollageLayout::Grid { columns } => {
+            let columns = columns.clamp(1, images.len() as u32) as usize;
+            let cell_width = cell_width_for(cell_height);
+            for (i, img) in images.iter().enumerate() {
+                let (w, h) = (img.width().max(1) as f32, img.height().max(1) as f32);
+                let fit = (cell_width as f32 / w).min(cell_height as f32 / h);
+                let (width, height) = (((w * fit).round() as u32).max(1), ((h * fit).round() as u32).max(1));
+                let cell_x = gutter + (i % columns) as i64 * (cell_width as i64 + gutter);
+                let cell_y = gutter + (i / columns) as i64 * (cell_height as i64 + gutter);
+                let x = cell_x + (cell_width as i64 - width as i64) / 2;
+                let y = cell_y + (cell_height as i64 - height as i64) / 2;
+                imageops::overlay(&mut canvas, &imageops::resize(img, width, height, FilterType::Lanczos3), x, y);
+            }
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::color::Color;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const GRAY: Rgba<u8> = Rgba([128, 128, 128, 255]);
+
+    fn collage(layout: CollageLayout, gutter: u32) -> Collage {
+        Collage { layout, gutter, background: Color::new(128, 128, 128, 255) }
+    }
+
+    #[test]
+    fn test_compose_collage_strip_matches_heights() {
+        let images = [RgbaImage::from_pixel(100, 50, RED), RgbaImage::from_pixel(50, 100, BLUE)];
+        let strip = compose_collage(&images, &collage(CollageLayout::Strip, 10), (8192, 8192));
+        // 高さ 50 にそろえるので 2 枚目は 25x50。幅は 10 + 100 + 10 + 25 + 10
+        assert_eq!(strip.dimensions(), (155, 70));
+        assert_eq!(*strip.get_pixel(5, 5), GRAY);
+        assert_eq!(*strip.get_pixel(60, 35), RED);
+        assert_eq!(*strip.get_pixel(115, 35), GRAY);
+        assert_eq!(*strip.get_pixel(132, 35), BLUE);
+        assert_eq!(*strip.get_pixel(150, 35), GRAY);
+    }
+
+    #[test]
+    fn test_compose_collage_grid_fits_images_in_cells() {
+        let images = [
+            RgbaImage::from_pixel(60, 60, RED),
+            RgbaImage::from_pixel(60, 60, BLUE),
+            RgbaImage::from_pixel(120, 60, RED),
+        ];
+        // 縦横比の平均は 4/3 なので、マスは 80x60。2 列 2 行
+        let grid = compose_collage(&images, &collage(CollageLayout::Grid { columns: 2 }, 4), (8192, 8192));
+        assert_eq!(grid.dimensions(), (4 + 80 + 4 + 80 + 4, 4 + 60 + 4 + 60 + 4));
+        // 正方形の画像はマスの真ん中に置かれ、左右に背景が残る
+        assert_eq!(*grid.get_pixel(4 + 5, 30), GRAY);
+        assert_eq!(*grid.get_pixel(4 + 40, 30), RED);
+        assert_eq!(*grid.get_pixel(88 + 40, 30), BLUE);
+        // 横長の画像はマスの幅に合わせて縮み、上下に背景が残る
+        assert_eq!(*grid.get_pixel(4 + 40, 68 + 5), GRAY);
+        assert_eq!(*grid.get_pixel(4 + 40, 68 + 30), RED);
+        // 空いたマスは背景のまま
+        assert_eq!(*grid.get_pixel(88 + 40, 68 + 30), GRAY);
+    }
+
+    #[test]
+    fn test_compose_collage_shrinks_to_max_side() {
+        let images = [RgbaImage::from_pixel(400, 400, RED), RgbaImage::from_pixel(400, 400, BLUE)];
+        let strip = compose_collage(&images, &collage(CollageLayout::Strip, 8), (300, 300));
+        assert!(strip.width() <= 300 && strip.height() <= 300, "{:?}", strip.dimensions());
+        assert!(strip.width() >= 290, "{:?}", strip.dimensions());
+    }
+
+    #[test]
+    fn test_shrink_to_pixels_keeps_aspect_within_budget() {
+        let shrunk = shrink_to_pixels(RgbaImage::from_pixel(400, 200, RED), 20_000);
+        assert_eq!(shrunk.dimensions(), (200, 100));
+        // 収まっている画像はそのまま
+        assert_eq!(shrink_to_pixels(RgbaImage::from_pixel(40, 20, RED), 20_000).dimensions(), (40, 20));
+        assert_eq!(shrink_to_pixels(RgbaImage::from_pixel(1000, 1, RED), 10).dimensions(), (10, 1));
+        assert_eq!(shrink_to_pixels(RgbaImage::from_pixel(1, 1000, RED), 10).dimensions(), (1, 10));
+    }
+}
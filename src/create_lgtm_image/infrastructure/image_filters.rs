@@ -0,0 +1,235 @@
+use crate::domain::filter::{ImageFilter, TextBackdrop};
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+const MAX_BLUR_SIGMA: f32 = 50.0; // 大きすぎるとぼかしに時間がかかりすぎる
+
+// フィルタを指定された順に適用する。アルファ値は変えない
+pub fn apply_filters(mut img: RgbaImage, filters: &[ImageFilter]) -> RgbaImage {
+    for filter in filters {
+        img = apply_filter(img, filter);
+    }
+    img
+}
+
+fn apply_filter(mut img: RgbaImage, filter: &ImageFilter) -> RgbaImage {
+    match *filter {
+        ImageFilter::Blur { sigma } => {
+            let sigma = sigma.clamp(0.0, MAX_BLUR_SIGMA);
+            if sigma > 0.0 {
+                // ぼかすのは色だけ。透明な部分の形 (角丸やロゴの切り抜き) はそのまま残す
+                let mut blurred = imageops::blur(&img, sigma);
+                for (dst, src) in blurred.pixels_mut().zip(img.pixels()) {
+                    dst[3] = src[3];
+                }
+                img = blurred;
+            }
+        }
+        ImageFilter::Vignette { strength } => vignette(&mut img, strength.clamp(0.0, 1.0)),
+        _ => {
+            for pixel in img.pixels_mut() {
+                *pixel = map_pixel(*pixel, filter);
+            }
+        }
+    }
+    img
+}
+
+fn luma(rgb: [f32; 3]) -> f32 {
+    0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]
+}
+
+fn lerp(from: [f32; 3], to: [f32; 3], t: f32) -> [f32; 3] {
+    std::array::from_fn(|c| from[c] + (to[c] - from[c]) * t)
+}
+
+// ピクセル単位で完結するフィルタ
+fn map_pixel(pixel: Rgba<u8>, filter: &ImageFilter) -> Rgba<u8> {
+    let rgb = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+    let mapped = match *filter {
+        ImageFilter::Grayscale { amount } => {
+            let y = luma(rgb);
+            lerp(rgb, [y, y, y], amount.clamp(0.0, 1.0))
+        }
+        ImageFilter::Sepia { amount } => {
+            let [r, g, b] = rgb;
+            let sepia = [
+                0.393 * r + 0.769 * g + 0.189 * b,
+                0.349 * r + 0.686 * g + 0.168 * b,
+                0.272 * r + 0.534 * g + 0.131 * b,
+            ];
+            lerp(rgb, sepia, amount.clamp(0.0, 1.0))
+        }
+        ImageFilter::Brightness { amount } => rgb.map(|c| c + amount.clamp(-1.0, 1.0) * 255.0),
+        ImageFilter::Contrast { amount } => {
+            let factor = 1.0 + amount.clamp(-1.0, 1.0);
+            rgb.map(|c| (c - 128.0) * factor + 128.0)
+        }
+        ImageFilter::Saturation { factor } => {
+            let y = luma(rgb);
+            rgb.map(|c| y + (c - y) * factor.max(0.0))
+        }
+        ImageFilter::Darken { opacity } => rgb.map(|c| c * (1.0 - opacity.clamp(0.0, 1.0))),
+        ImageFilter::Blur { .. } | ImageFilter::Vignette { .. } => rgb,
+    };
+    let [r, g, b] = mapped.map(|c| c.round().clamp(0.0, 255.0) as u8);
+    Rgba([r, g, b, pixel[3]])
+}
+
+// 中心からの距離の二乗に比例して暗くする
+fn vignette(img: &mut RgbaImage, strength: f32) {
+    let (width, height) = img.dimensions();
+    let (cx, cy) = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+    let max_distance_sq = (cx * cx + cy * cy).max(1.0);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let factor = 1.0 - strength * (dx * dx + dy * dy) / max_distance_sq;
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f32 * factor).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// テキストの範囲 (x, y, width, height) の下だけをぼかして暗くする (すりガラス風)
+// padding で広げた範囲は全体にかけ、その外側 feather px で元の画像になめらかに戻す
+pub fn apply_text_backdrop(img: &mut RgbaImage, (x, y, width, height): (i32, i32, u32, u32), backdrop: &TextBackdrop) {
+    let sigma = backdrop.blur_sigma.clamp(0.0, MAX_BLUR_SIGMA);
+    let darken = backdrop.darken.clamp(0.0, 1.0);
+    let padding = backdrop.padding as i64;
+    let feather = backdrop.feather as i64;
+    let (img_width, img_height) = (img.width() as i64, img.height() as i64);
+
+    // 全体にかける範囲 (半開区間)
+    let inner = (x as i64 - padding, y as i64 - padding, x as i64 + width as i64 + padding, y as i64 + height as i64 + padding);
+    let clip = |(x0, y0, x1, y1): (i64, i64, i64, i64)| (x0.max(0), y0.max(0), x1.min(img_width), y1.min(img_height));
+    let (x0, y0, x1, y1) = clip((inner.0 - feather, inner.1 - feather, inner.2 + feather, inner.3 + feather));
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+
+    // 端で黒がにじまないよう、ぼかしの広がり分だけ外側も含めて切り出す
+    let margin = (sigma * 3.0).ceil() as i64;
+    let (sx0, sy0, sx1, sy1) = clip((x0 - margin, y0 - margin, x1 + margin, y1 + margin));
+    let source = imageops::crop_imm(img, sx0 as u32, sy0 as u32, (sx1 - sx0) as u32, (sy1 - sy0) as u32).to_image();
+    let blurred = if sigma > 0.0 { imageops::blur(&source, sigma) } else { source };
+
+    for py in y0..y1 {
+        for px in x0..x1 {
+            // 全体にかける範囲からの距離で重みを決める
+            let dx = (inner.0 - px).max(px + 1 - inner.2).max(0) as f32;
+            let dy = (inner.1 - py).max(py + 1 - inner.3).max(0) as f32;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let weight = if distance <= 0.0 {
+                1.0
+            } else if feather == 0 {
+                0.0
+            } else {
+                let t = (1.0 - distance / feather as f32).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t) // smoothstep
+            };
+            if weight <= 0.0 {
+                continue;
+            }
+            let effect = blurred.get_pixel((px - sx0) as u32, (py - sy0) as u32);
+            let pixel = img.get_pixel_mut(px as u32, py as u32);
+            for c in 0..3 {
+                let target = effect[c] as f32 * (1.0 - darken);
+                let value = pixel[c] as f32 + (target - pixel[c] as f32) * weight;
+                pixel[c] = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(9, 9, Rgba(color))
+    }
+
+    #[test]
+    fn test_pixel_filters() {
+        let cases = [
+            (ImageFilter::Grayscale { amount: 1.0 }, [200, 100, 50, 255], [124, 124, 124, 255]),
+            (ImageFilter::Sepia { amount: 1.0 }, [100, 100, 100, 255], [135, 120, 94, 255]),
+            (ImageFilter::Brightness { amount: 0.2 }, [100, 250, 0, 255], [151, 255, 51, 255]),
+            (ImageFilter::Contrast { amount: 1.0 }, [100, 200, 128, 255], [72, 255, 128, 255]),
+            (ImageFilter::Saturation { factor: 0.0 }, [200, 100, 50, 255], [124, 124, 124, 255]),
+            (ImageFilter::Darken { opacity: 0.5 }, [200, 100, 50, 128], [100, 50, 25, 128]),
+        ];
+        for (filter, input, expected) in cases {
+            let output = apply_filters(solid(input), std::slice::from_ref(&filter));
+            assert_eq!(output.get_pixel(4, 4).0, expected, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_filters_apply_in_order() {
+        // 明るくしてから暗く と 暗くしてから明るく は結果が違う
+        let brighten_then_darken = apply_filters(
+            solid([100, 100, 100, 255]),
+            &[ImageFilter::Brightness { amount: 1.0 }, ImageFilter::Darken { opacity: 0.5 }],
+        );
+        let darken_then_brighten = apply_filters(
+            solid([100, 100, 100, 255]),
+            &[ImageFilter::Darken { opacity: 0.5 }, ImageFilter::Brightness { amount: 1.0 }],
+        );
+        assert_eq!(brighten_then_darken.get_pixel(0, 0).0, [128, 128, 128, 255]);
+        assert_eq!(darken_then_brighten.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_vignette_darkens_corners_only() {
+        let output = apply_filters(solid([200, 200, 200, 255]), &[ImageFilter::Vignette { strength: 0.8 }]);
+        assert_eq!(output.get_pixel(4, 4).0, [200, 200, 200, 255]);
+        assert!(output.get_pixel(0, 0)[0] < 60);
+    }
+
+    #[test]
+    fn test_blur_smooths_edges() {
+        let mut img = solid([0, 0, 0, 255]);
+        img.put_pixel(4, 4, Rgba([255, 255, 255, 255]));
+        let output = apply_filters(img, &[ImageFilter::Blur { sigma: 1.5 }]);
+        assert!(output.get_pixel(4, 4)[0] < 255);
+        assert!(output.get_pixel(5, 4)[0] > 0);
+
+        // 透明な部分はぼかしても透明なまま
+        let mut img = solid([200, 200, 200, 255]);
+        img.put_pixel(0, 0, Rgba([200, 200, 200, 0]));
+        let output = apply_filters(img, &[ImageFilter::Blur { sigma: 1.5 }]);
+        assert_eq!(output.get_pixel(0, 0)[3], 0);
+        assert_eq!(output.get_pixel(1, 0)[3], 255);
+    }
+
+    #[test]
+    fn test_text_backdrop_only_touches_text_region() {
+        // 白黒の縦縞。ぼかすと灰色になる
+        let mut img = RgbaImage::from_fn(60, 40, |x, _| if x % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) });
+        let original = img.clone();
+        let backdrop = TextBackdrop { blur_sigma: 2.0, darken: 0.5, padding: 2, feather: 4 };
+        apply_text_backdrop(&mut img, (20, 15, 20, 10), &backdrop);
+
+        // テキストの中心はぼけて暗くなる
+        let center = img.get_pixel(30, 20);
+        assert!(center[0] > 20 && center[0] < 100, "{:?}", center);
+        assert_eq!(center[3], 255);
+        // feather の中は元の画像と効果の中間
+        let edge = img.get_pixel(16, 20);
+        assert!(edge[0] < 255 && edge[0] > center[0], "{:?}", edge);
+        // 範囲外はくっきりしたまま
+        assert_eq!(img.get_pixel(5, 5), original.get_pixel(5, 5));
+        assert_eq!(img.get_pixel(12, 20), original.get_pixel(12, 20));
+        assert_eq!(img.get_pixel(30, 36), original.get_pixel(30, 36));
+    }
+
+    #[test]
+    fn test_text_backdrop_clips_to_image() {
+        let mut img = solid([200, 200, 200, 255]);
+        let backdrop = TextBackdrop { blur_sigma: 1.0, darken: 1.0, padding: 0, feather: 0 };
+        apply_text_backdrop(&mut img, (-5, 6, 100, 100), &backdrop);
+        assert_eq!(img.get_pixel(0, 8).0, [0, 0, 0, 255]);
+        assert_eq!(img.get_pixel(0, 5).0, [200, 200, 200, 255]);
+    }
+}
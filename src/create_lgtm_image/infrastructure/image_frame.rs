@@ -0,0 +1,132 @@
+use super::error::InfrastructureError;
+use super::image_transform::{check_size, to_rgba};
+use super::text_layout::Area;
+use crate::domain::frame::{Border, BorderFill, Polaroid};
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+// ポラロイド風の余白を足す。戻り値の 2 つ目はキャプションを描く下の余白の範囲 (x, y, width, height)
+pub fn add_polaroid_margin(
+    img: RgbaImage,
+    polaroid: &Polaroid,
+    max_side: (u32, u32),
+) -> Result<(RgbaImage, Area), InfrastructureError> {
+    let (width, height) = img.dimensions();
+    let side = ((width.min(height) as f32 * 0.06).round() as u32).max(4);
+    let bottom = ((height as f32 * 0.25).round() as u32).max(side * 3);
+    let (canvas_width, canvas_height) = (width as u64 + side as u64 * 2, height as u64 + side as u64 + bottom as u64);
+    check_size(canvas_width, canvas_height, max_side)?;
+
+    let mut canvas = RgbaImage::from_pixel(canvas_width as u32, canvas_height as u32, to_rgba(&polaroid.color));
+    imageops::overlay(&mut canvas, &img, side as i64, side as i64);
+    Ok((canvas, (side as i32, (side + height) as i32, width, bottom)))
+}
+
+// 外側に枠線を足す
+pub fn add_border(img: RgbaImage, border: &Border, max_side: (u32, u32)) -> Result<RgbaImage, InfrastructureError> {
+    if border.width == 0 {
+        return Ok(img);
+    }
+    let (width, height) = img.dimensions();
+    let (canvas_width, canvas_height) = (width as u64 + border.width as u64 * 2, height as u64 + border.width as u64 * 2);
+    check_size(canvas_width, canvas_height, max_side)?;
+
+    let (top, bottom) = match &border.fill {
+        BorderFill::Solid(color) => (to_rgba(color), to_rgba(color)),
+        BorderFill::Gradient { top, bottom } => (to_rgba(top), to_rgba(bottom)),
+    };
+    let last_row = (canvas_height - 1).max(1) as f32;
+    let mut canvas = RgbaImage::from_fn(canvas_width as u32, canvas_height as u32, |_, y| {
+        let t = y as f32 / last_row;
+        Rgba(std::array::from_fn(|c| (top[c] as f32 + (bottom[c] as f32 - top[c] as f32) * t).round() as u8))
+    });
+    imageops::overlay(&mut canvas, &img, border.width as i64, border.width as i64);
+    Ok(canvas)
+}
+
+// 四隅を半径 radius で透明に切り抜く。ふちはピクセルの覆われ具合でなめらかにする
+pub fn round_corners(img: &mut RgbaImage, radius: u32) {
+    let (width, height) = img.dimensions();
+    let radius = radius.min(width / 2).min(height / 2);
+    if radius == 0 {
+        return;
+    }
+    let r = radius as f32;
+    for y in 0..radius {
+        for x in 0..radius {
+            // 左上の角で計算し、4 つの角に同じ値を使う
+            let (dx, dy) = (r - (x as f32 + 0.5), r - (y as f32 + 0.5));
+            let coverage = (r - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            if coverage >= 1.0 {
+                continue;
+            }
+            for (cx, cy) in [(x, y), (width - 1 - x, y), (x, height - 1 - y), (width - 1 - x, height - 1 - y)] {
+                let pixel = img.get_pixel_mut(cx, cy);
+                pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::color::Color;
+
+    const LIMIT: (u32, u32) = (8192, 8192);
+
+    fn solid(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([10, 20, 30, 255]))
+    }
+
+    #[test]
+    fn test_add_border_solid_and_gradient() {
+        let border = Border { width: 5, fill: BorderFill::Solid(Color::new(255, 0, 0, 255)) };
+        let framed = add_border(solid(20, 10), &border, LIMIT).unwrap();
+        assert_eq!(framed.dimensions(), (30, 20));
+        assert_eq!(framed.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(framed.get_pixel(5, 5).0, [10, 20, 30, 255]);
+
+        let border = Border {
+            width: 5,
+            fill: BorderFill::Gradient { top: Color::new(0, 0, 0, 255), bottom: Color::new(255, 255, 255, 255) },
+        };
+        let framed = add_border(solid(20, 10), &border, LIMIT).unwrap();
+        assert_eq!(framed.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(framed.get_pixel(0, 19).0, [255, 255, 255, 255]);
+        assert!(framed.get_pixel(0, 10)[0] > 100 && framed.get_pixel(0, 10)[0] < 155);
+    }
+
+    #[test]
+    fn test_add_border_respects_limits() {
+        let border = Border { width: 100, fill: BorderFill::Solid(Color::new(0, 0, 0, 255)) };
+        let result = add_border(solid(20, 10), &border, (100, 100));
+        assert!(matches!(result, Err(InfrastructureError::ImageLimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_add_polaroid_margin_leaves_caption_area_below() {
+        let polaroid = Polaroid { color: Color::new(250, 250, 250, 255) };
+        let (framed, (x, y, width, height)) = add_polaroid_margin(solid(100, 80), &polaroid, LIMIT).unwrap();
+        assert_eq!((x, y, width, height), (5, 85, 100, 20));
+        assert_eq!(framed.dimensions(), (110, 105));
+        assert_eq!(framed.get_pixel(50, 50).0, [10, 20, 30, 255]);
+        assert_eq!(framed.get_pixel(50, 90).0, [250, 250, 250, 255]);
+    }
+
+    #[test]
+    fn test_round_corners_cuts_transparent_corners() {
+        let mut img = solid(40, 30);
+        round_corners(&mut img, 10);
+        for (x, y) in [(0, 0), (39, 0), (0, 29), (39, 29)] {
+            assert_eq!(img.get_pixel(x, y)[3], 0);
+        }
+        assert_eq!(img.get_pixel(20, 0)[3], 255);
+        assert_eq!(img.get_pixel(20, 15)[3], 255);
+        // 半径は短辺の半分までに抑える
+        let mut img = solid(8, 8);
+        round_corners(&mut img, 100);
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+        assert_eq!(img.get_pixel(4, 4)[3], 255);
+    }
+}
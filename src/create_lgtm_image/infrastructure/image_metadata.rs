@@ -0,0 +1,433 @@
+use super::error::InfrastructureError;
+use crate::domain::metadata_policy::MetadataPolicy;
+use image::ImageFormat as InnerImageFormat;
+use img_parts::jpeg::{markers, Jpeg, JpegSegment};
+use img_parts::png::{Png, PngChunk};
+use img_parts::riff::{RiffChunk, RiffContent};
+use img_parts::webp::{WebP, CHUNK_EXIF, CHUNK_ICCP, CHUNK_VP8X, CHUNK_XMP};
+use img_parts::{Bytes, DynImage, ImageEXIF, ImageICC};
+
+const XMP_JPEG_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_PNG_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+const EXIF_PREFIX: &[u8] = b"Exif\0\0";
+// WebP の拡張形式 (VP8X) のフラグ
+const WEBP_FLAG_ICC: u8 = 0x20;
+const WEBP_FLAG_ALPHA: u8 = 0x10;
+const WEBP_FLAG_EXIF: u8 = 0x08;
+const WEBP_FLAG_XMP: u8 = 0x04;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_GPS_IFD_POINTER: u16 = 0x8825;
+const TAG_EXIF_IFD_POINTER: u16 = 0x8769;
+const TAG_PIXEL_X_DIMENSION: u16 = 0xa002;
+const TAG_PIXEL_Y_DIMENSION: u16 = 0xa003;
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+
+// 入力画像から取り出したメタデータ (JPEG / PNG / WebP に対応)
+#[derive(Debug, Default, Clone)]
+pub struct ImageMetadata {
+    pub icc_profile: Option<Vec<u8>>,
+    pub exif: Option<Vec<u8>>, // "Exif\0\0" を除いた TIFF 形式のバイト列
+    pub xmp: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    pub fn extract(image_bytes: &[u8]) -> Self {
+        let image = match DynImage::from_bytes(Bytes::copy_from_slice(image_bytes)) {
+            Ok(Some(image)) => image,
+            _ => return Self::default(),
+        };
+        let xmp = match &image {
+            DynImage::Jpeg(jpeg) => jpeg
+                .segments_by_marker(markers::APP1)
+                .find_map(|s| s.contents().strip_prefix(XMP_JPEG_PREFIX).map(|x| x.to_vec())),
+            DynImage::Png(png) => png.chunks_by_type(*b"iTXt").find_map(|c| png_xmp_packet(c.contents())),
+            DynImage::WebP(webp) => webp_chunk(webp, CHUNK_XMP),
+        };
+        let exif = match &image {
+            // WebP の EXIF チャンクは仕様では TIFF そのもの。"Exif\0\0" を付けて書くツールもあるのでどちらも読む
+            DynImage::WebP(webp) => webp_chunk(webp, CHUNK_EXIF).map(|exif| exif.strip_prefix(EXIF_PREFIX).map_or(exif.clone(), |e| e.to_vec())),
+            _ => image.exif().map(|b| b.to_vec()),
+        };
+        Self {
+            icc_profile: image.icc_profile().map(|b| b.to_vec()),
+            exif,
+            xmp,
+        }
+    }
+
+    // ポリシーに従って残すメタデータだけを返す
+    // EXIF はデコード時に向きを直しているので Orientation を 1 に戻し、GPS は必ず消す
+    pub fn filtered(&self, policy: MetadataPolicy) -> Self {
+        match policy {
+            MetadataPolicy::StripAll => Self::default(),
+            MetadataPolicy::PreserveIcc => Self {
+                icc_profile: self.icc_profile.clone(),
+                ..Self::default()
+            },
+            MetadataPolicy::PreserveAllExceptGps => Self {
+                icc_profile: self.icc_profile.clone(),
+                exif: self.exif.as_deref().and_then(sanitize_exif),
+                // XMP にも exif:GPS* が書かれることがあるので、その場合は XMP ごと落とす
+                xmp: self.xmp.clone().filter(|x| !contains(x, b"GPS")),
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icc_profile.is_none() && self.exif.is_none() && self.xmp.is_none()
+    }
+}
+
+// エンコード済みの画像にメタデータを書き込む。GIF などメタデータを扱わない形式はそのまま返す
+pub fn embed_metadata(
+    encoded: Vec<u8>,
+    format: InnerImageFormat,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, InfrastructureError> {
+    if metadata.is_empty() {
+        return Ok(encoded);
+    }
+    let to_err = |e: img_parts::Error| InfrastructureError::ImageProcessingError(format!("Failed to write metadata: {}", e));
+
+    match format {
+        InnerImageFormat::Jpeg => {
+            let mut jpeg = Jpeg::from_bytes(encoded.into()).map_err(to_err)?;
+            jpeg.set_icc_profile(metadata.icc_profile.clone().map(Bytes::from));
+            jpeg.set_exif(metadata.exif.clone().map(Bytes::from));
+            if let Some(xmp) = &metadata.xmp {
+                let mut contents = XMP_JPEG_PREFIX.to_vec();
+                contents.extend_from_slice(xmp);
+                let position = jpeg.segments().len().min(3);
+                jpeg.segments_mut()
+                    .insert(position, JpegSegment::new_with_contents(markers::APP1, contents.into()));
+            }
+            Ok(jpeg.encoder().bytes().to_vec())
+        }
+        InnerImageFormat::Png => {
+            let mut png = Png::from_bytes(encoded.into()).map_err(to_err)?;
+            png.set_icc_profile(metadata.icc_profile.clone().map(Bytes::from));
+            png.set_exif(metadata.exif.clone().map(Bytes::from));
+            if let Some(xmp) = &metadata.xmp {
+                // keyword\0 + 圧縮フラグ 0 + 圧縮方式 0 + 言語タグ\0 + 翻訳キーワード\0 + 本文
+                let mut contents = XMP_PNG_KEYWORD.to_vec();
+                contents.extend_from_slice(&[0, 0, 0, 0, 0]);
+                contents.extend_from_slice(xmp);
+                let position = png.chunks().len() - 1; // IEND の手前
+                png.chunks_mut().insert(position, PngChunk::new(*b"iTXt", contents.into()));
+            }
+            Ok(png.encoder().bytes().to_vec())
+        }
+        InnerImageFormat::WebP => {
+            // img-parts の VP8X はアルファと XMP のフラグを立てないので、拡張形式はここで組み立てる
+            let mut webp = WebP::from_bytes(encoded.into()).map_err(to_err)?;
+            for id in [CHUNK_VP8X, CHUNK_ICCP, CHUNK_EXIF, CHUNK_XMP] {
+                webp.remove_chunks_by_id(id);
+            }
+            let (width, height) = webp
+                .dimensions()
+                .ok_or_else(|| InfrastructureError::ImageProcessingError("Failed to read WebP dimensions".to_string()))?;
+            let chunk = |id, data: Vec<u8>| RiffChunk::new(id, RiffContent::Data(data.into()));
+
+            let mut flags = WEBP_FLAG_ALPHA;
+            let mut chunks = Vec::new();
+            if let Some(icc) = &metadata.icc_profile {
+                flags |= WEBP_FLAG_ICC;
+                chunks.push(chunk(CHUNK_ICCP, icc.clone()));
+            }
+            chunks.append(webp.chunks_mut());
+            if let Some(exif) = &metadata.exif {
+                flags |= WEBP_FLAG_EXIF;
+                chunks.push(chunk(CHUNK_EXIF, exif.clone()));
+            }
+            if let Some(xmp) = &metadata.xmp {
+                flags |= WEBP_FLAG_XMP;
+                chunks.push(chunk(CHUNK_XMP, xmp.clone()));
+            }
+            // フラグ 1 バイト + 予約 3 バイト + (幅 - 1) と (高さ - 1) を 3 バイトずつ
+            let mut vp8x = vec![flags, 0, 0, 0];
+            vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+            chunks.insert(0, chunk(CHUNK_VP8X, vp8x));
+            *webp.chunks_mut() = chunks;
+            Ok(webp.encoder().bytes().to_vec())
+        }
+        _ => Ok(encoded),
+    }
+}
+
+fn webp_chunk(webp: &WebP, id: [u8; 4]) -> Option<Vec<u8>> {
+    webp.chunk_by_id(id)?.content().data().map(|data| data.to_vec())
+}
+
+fn png_xmp_packet(contents: &[u8]) -> Option<Vec<u8>> {
+    let rest = contents.strip_prefix(XMP_PNG_KEYWORD)?.strip_prefix(&[0, 0, 0])?; // 非圧縮のみ
+    let rest = &rest[rest.iter().position(|&b| b == 0)? + 1..]; // 言語タグ
+    let rest = &rest[rest.iter().position(|&b| b == 0)? + 1..]; // 翻訳キーワード
+    Some(rest.to_vec())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+// TIFF (EXIF) のバイト列を読み書きするための小さなヘルパ
+struct Tiff {
+    data: Vec<u8>,
+    little_endian: bool,
+}
+
+impl Tiff {
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    fn set_u16(&mut self, offset: usize, value: u16) {
+        let b = if self.little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+        self.data[offset..offset + 2].copy_from_slice(&b);
+    }
+
+    fn set_u32(&mut self, offset: usize, value: u32) {
+        let b = if self.little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+        self.data[offset..offset + 4].copy_from_slice(&b);
+    }
+
+    fn zero(&mut self, start: usize, len: usize) -> Option<()> {
+        self.data.get_mut(start..start.checked_add(len)?)?.fill(0);
+        Some(())
+    }
+
+    // IFD の外に置かれた値と IFD そのものをゼロ埋めする
+    fn zero_ifd(&mut self, ifd: usize) -> Option<()> {
+        let count = self.u16_at(ifd)? as usize;
+        for j in 0..count {
+            let entry = ifd + 2 + j * 12;
+            let size = tiff_type_size(self.u16_at(entry + 2)?).checked_mul(self.u32_at(entry + 4)? as usize)?;
+            if size > 4 {
+                let value_offset = self.u32_at(entry + 8)? as usize;
+                self.zero(value_offset, size)?;
+            }
+        }
+        self.zero(ifd, 2 + count * 12 + 4)
+    }
+
+    // index 番目のエントリを消し、後ろのエントリと「次の IFD」オフセットを 12 バイト詰める
+    fn remove_entry(&mut self, ifd: usize, index: usize) -> Option<()> {
+        let count = self.u16_at(ifd)? as usize;
+        let entry = ifd + 2 + index * 12;
+        let end = ifd + 2 + count * 12 + 4;
+        self.data.get(entry..end)?;
+        self.data.copy_within(entry + 12..end, entry);
+        self.zero(end - 12, 12)?;
+        self.set_u16(ifd, (count - 1) as u16);
+        Some(())
+    }
+}
+
+fn tiff_type_size(field_type: u16) -> usize {
+    match field_type {
+        3 | 8 => 2,        // SHORT, SSHORT
+        4 | 9 | 11 => 4,   // LONG, SLONG, FLOAT
+        5 | 10 | 12 => 8,  // RATIONAL, SRATIONAL, DOUBLE
+        _ => 1,            // BYTE, ASCII, SBYTE, UNDEFINED
+    }
+}
+
+// Orientation を 1 にし、GPS IFD を中身ごとゼロ埋めしてから IFD0 の参照を外す
+// 元の寸法のままになる PixelXDimension / PixelYDimension は Exif IFD から消す
+// IFD1 のサムネイルは元の写真の縮小版なので、JPEG の中身ごとゼロ埋めして IFD0 の「次の IFD」を 0 にする
+// 構造が読めない EXIF は安全側に倒して None (= 残さない) を返す
+fn sanitize_exif(exif: &[u8]) -> Option<Vec<u8>> {
+    let little_endian = match exif.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let mut tiff = Tiff { data: exif.to_vec(), little_endian };
+    if tiff.u16_at(2)? != 42 {
+        return None;
+    }
+    let ifd0 = tiff.u32_at(4)? as usize;
+    tiff.data.get(ifd0..ifd0 + 2 + tiff.u16_at(ifd0)? as usize * 12 + 4)?;
+
+    let mut index = 0;
+    while index < tiff.u16_at(ifd0)? as usize {
+        let entry = ifd0 + 2 + index * 12;
+        match tiff.u16_at(entry)? {
+            TAG_ORIENTATION => tiff.set_u16(entry + 8, 1),
+            TAG_EXIF_IFD_POINTER => {
+                let exif_ifd = tiff.u32_at(entry + 8)? as usize;
+                let mut j = 0;
+                while j < tiff.u16_at(exif_ifd)? as usize {
+                    match tiff.u16_at(exif_ifd + 2 + j * 12)? {
+                        TAG_PIXEL_X_DIMENSION | TAG_PIXEL_Y_DIMENSION => tiff.remove_entry(exif_ifd, j)?,
+                        _ => j += 1,
+                    }
+                }
+            }
+            TAG_GPS_IFD_POINTER => {
+                let gps_ifd = tiff.u32_at(entry + 8)? as usize;
+                tiff.zero_ifd(gps_ifd)?;
+                tiff.remove_entry(ifd0, index)?;
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    let next_ifd = ifd0 + 2 + tiff.u16_at(ifd0)? as usize * 12;
+    let ifd1 = tiff.u32_at(next_ifd)? as usize;
+    if ifd1 != 0 {
+        let (mut thumbnail_offset, mut thumbnail_len) = (None, None);
+        for j in 0..tiff.u16_at(ifd1)? as usize {
+            let entry = ifd1 + 2 + j * 12;
+            match tiff.u16_at(entry)? {
+                TAG_JPEG_INTERCHANGE_FORMAT => thumbnail_offset = Some(tiff.u32_at(entry + 8)? as usize),
+                TAG_JPEG_INTERCHANGE_FORMAT_LENGTH => thumbnail_len = Some(tiff.u32_at(entry + 8)? as usize),
+                _ => {}
+            }
+        }
+        if let (Some(offset), Some(len)) = (thumbnail_offset, thumbnail_len) {
+            tiff.zero(offset, len)?;
+        }
+        tiff.zero_ifd(ifd1)?;
+        tiff.set_u32(next_ifd, 0);
+    }
+    Some(tiff.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IFD0 に Orientation=6 と GPS ポインタ、GPS IFD に緯度 (RATIONAL x3) を持つ EXIF
+    fn exif_with_gps() -> Vec<u8> {
+        let mut t = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0 (offset 8): 2 entries
+        t.extend_from_slice(&2u16.to_le_bytes());
+        t.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]); // Orientation = 6
+        t.extend_from_slice(&[0x25, 0x88, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0]); // GPS IFD → 38
+        t.extend_from_slice(&0u32.to_le_bytes());
+        // GPS IFD (offset 38): 1 entry, GPSLatitude → 56
+        t.extend_from_slice(&1u16.to_le_bytes());
+        t.extend_from_slice(&[0x02, 0x00, 5, 0, 3, 0, 0, 0, 56, 0, 0, 0]);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        // 緯度 35/1, 41/1, 22/1 (offset 56)
+        for v in [35u32, 1, 41, 1, 22, 1] {
+            t.extend_from_slice(&v.to_le_bytes());
+        }
+        t
+    }
+
+    #[test]
+    fn test_sanitize_exif_removes_gps_and_resets_orientation() {
+        let sanitized = sanitize_exif(&exif_with_gps()).unwrap();
+
+        let exif = exif::Reader::new().read_raw(sanitized.clone()).unwrap();
+        let orientation = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY).unwrap();
+        assert_eq!(orientation.value.get_uint(0), Some(1));
+        assert!(exif.fields().all(|f| f.ifd_num != exif::In::PRIMARY || f.tag != exif::Tag::GPSInfoIFDPointer));
+        assert!(exif.get_field(exif::Tag::GPSLatitude, exif::In::PRIMARY).is_none());
+        // 緯度の値そのものもバイト列から消えていること
+        assert!(sanitized[56..].iter().all(|&b| b == 0));
+    }
+
+    // IFD0 (Exif IFD へのポインタ) → Exif IFD (PixelX/YDimension) と、IFD1 の JPEG サムネイルを持つ EXIF
+    fn exif_with_thumbnail() -> Vec<u8> {
+        let mut t = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0 (offset 8): 1 entry, Exif IFD → 26, 次の IFD → 56
+        t.extend_from_slice(&1u16.to_le_bytes());
+        t.extend_from_slice(&[0x69, 0x87, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0]);
+        t.extend_from_slice(&56u32.to_le_bytes());
+        // Exif IFD (offset 26): PixelXDimension = 4000, PixelYDimension = 3000
+        t.extend_from_slice(&2u16.to_le_bytes());
+        t.extend_from_slice(&[0x02, 0xa0, 4, 0, 1, 0, 0, 0, 0xa0, 0x0f, 0, 0]);
+        t.extend_from_slice(&[0x03, 0xa0, 4, 0, 1, 0, 0, 0, 0xb8, 0x0b, 0, 0]);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        // IFD1 (offset 56): サムネイル → 86, 8 バイト
+        t.extend_from_slice(&2u16.to_le_bytes());
+        t.extend_from_slice(&[0x01, 0x02, 4, 0, 1, 0, 0, 0, 86, 0, 0, 0]);
+        t.extend_from_slice(&[0x02, 0x02, 4, 0, 1, 0, 0, 0, 8, 0, 0, 0]);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        // サムネイルの JPEG (offset 86)
+        t.extend_from_slice(&[0xff, 0xd8, 1, 2, 3, 4, 0xff, 0xd9]);
+        t
+    }
+
+    #[test]
+    fn test_sanitize_exif_drops_thumbnail_and_pixel_dimensions() {
+        let sanitized = sanitize_exif(&exif_with_thumbnail()).unwrap();
+
+        let exif = exif::Reader::new().read_raw(sanitized.clone()).unwrap();
+        assert!(exif.fields().all(|f| f.ifd_num == exif::In::PRIMARY));
+        assert!(exif.get_field(exif::Tag::PixelXDimension, exif::In::PRIMARY).is_none());
+        assert!(exif.get_field(exif::Tag::PixelYDimension, exif::In::PRIMARY).is_none());
+        // IFD0 の「次の IFD」が 0 で、IFD1 とサムネイルのバイト列も残っていないこと
+        assert_eq!(&sanitized[22..26], &[0, 0, 0, 0]);
+        assert!(sanitized[56..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_sanitize_exif_rejects_garbage() {
+        assert!(sanitize_exif(b"not a tiff").is_none());
+    }
+
+    #[test]
+    fn test_filtered_follows_policy() {
+        let metadata = ImageMetadata {
+            icc_profile: Some(vec![1, 2, 3]),
+            exif: Some(exif_with_gps()),
+            xmp: Some(b"<x:xmpmeta>exif:GPSLatitude</x:xmpmeta>".to_vec()),
+        };
+
+        assert!(metadata.filtered(MetadataPolicy::StripAll).is_empty());
+
+        let icc_only = metadata.filtered(MetadataPolicy::PreserveIcc);
+        assert_eq!(icc_only.icc_profile, Some(vec![1, 2, 3]));
+        assert!(icc_only.exif.is_none() && icc_only.xmp.is_none());
+
+        let all = metadata.filtered(MetadataPolicy::PreserveAllExceptGps);
+        assert_eq!(all.icc_profile, Some(vec![1, 2, 3]));
+        assert!(all.exif.is_some());
+        assert!(all.xmp.is_none());
+    }
+
+    #[test]
+    fn test_embed_and_extract_round_trip() {
+        let img = image::RgbaImage::from_pixel(4, 4, image::Rgba([10, 20, 30, 255]));
+        let metadata = ImageMetadata {
+            icc_profile: Some(vec![9; 16]),
+            exif: sanitize_exif(&exif_with_gps()),
+            xmp: Some(b"<x:xmpmeta/>".to_vec()),
+        };
+
+        for format in [InnerImageFormat::Png, InnerImageFormat::Jpeg, InnerImageFormat::WebP] {
+            let mut buffer = std::io::Cursor::new(Vec::new());
+            image::DynamicImage::ImageRgba8(img.clone()).to_rgb8().write_to(&mut buffer, format).unwrap();
+            let embedded = embed_metadata(buffer.into_inner(), format, &metadata).unwrap();
+
+            let extracted = ImageMetadata::extract(&embedded);
+            assert_eq!(extracted.icc_profile, metadata.icc_profile, "{:?}", format);
+            assert_eq!(extracted.exif, metadata.exif, "{:?}", format);
+            assert_eq!(extracted.xmp, metadata.xmp, "{:?}", format);
+            assert!(image::load_from_memory(&embedded).is_ok());
+        }
+
+        // 拡張形式のフラグが書いたチャンクと合っている
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(img.clone()).write_to(&mut buffer, InnerImageFormat::WebP).unwrap();
+        let only_exif = ImageMetadata { exif: metadata.exif.clone(), ..ImageMetadata::default() };
+        let embedded = embed_metadata(buffer.into_inner(), InnerImageFormat::WebP, &only_exif).unwrap();
+        let webp = WebP::from_bytes(embedded.clone().into()).unwrap();
+        assert_eq!(webp.chunks()[0].id(), CHUNK_VP8X);
+        assert_eq!(webp.chunks()[0].content().data().unwrap()[0], WEBP_FLAG_ALPHA | WEBP_FLAG_EXIF);
+        assert_eq!(image::load_from_memory(&embedded).unwrap().to_rgba8(), img);
+    }
+}
@@ -1,14 +1,39 @@
 use crate::domain::image_processor_trait::ImageProcessor;
-use crate::domain::image::Image as DomainImage; // Keep if used, or remove
+use crate::domain::image::Image as DomainImage;
 use crate::domain::text_overlay::TextOverlay as DomainTextOverlay;
 use crate::domain::color::Color as DomainColor;
-use crate::domain::position::Position as DomainPosition;
+use crate::domain::encode_settings::EncodeSettings;
 use super::error::InfrastructureError; // Changed from anyhow::Result
 // use anyhow::Result; // Remove if fully transitioned
-use crate::domain::error::DomainError; // Not directly used in signature, but good for context
-use image::{Rgba, RgbaImage, ImageFormat as InnerImageFormat}; // imageクレートの型
-use imageproc::drawing::draw_text_mut;
-use rusttype::{Font, Scale, point};
+use image::{DynamicImage, Rgba, RgbaImage, ImageFormat as InnerImageFormat}; // imageクレートの型
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use super::palette_quantizer::{encode_gif, encode_png8, quantize};
+use super::image_metadata::{embed_metadata, ImageMetadata};
+use crate::domain::metadata_policy::MetadataPolicy;
+use crate::domain::render_options::RenderOptions;
+use crate::domain::filter::TextBackdrop;
+use super::image_transform::apply_transform;
+use super::image_filters::{apply_filters, apply_text_backdrop};
+use super::image_frame::{add_border, add_polaroid_margin, round_corners};
+use super::image_collage::{compose_collage, shrink_to_pixels};
+use super::contact_sheet::{placeholder_thumbnail, render_contact_tile};
+use crate::domain::collage::{Collage, ContactSheet};
+use super::text_layout::{default_scale, layout_text, layout_text_in, layout_text_with_subtitle, layout_wrapped_text, Area, GlyphLayout, TextLayout};
+use super::text_path::layout_text_on_path;
+use super::vertical_layout::layout_vertical_text;
+use crate::domain::text_overlay::WritingMode;
+use super::overlay_renderer::{draw_image_overlay, draw_layer};
+use super::stamp_renderer::render_stamp;
+use super::bubble_renderer::draw_bubble;
+use super::qr_renderer::render_qr;
+use super::fonts::FontSet;
+use super::text_renderer::{draw_glyph_layout, draw_text_runs, TextPaint};
+use super::text_effects::draw_text_effect;
+use super::text_tiling::render_tiled_text;
+use crate::domain::text_effect::TextEffect;
+use crate::domain::overlay::Overlay;
+use rusttype::Font;
 use std::io::Cursor;
 
 // ドメイン層で定義する ImageProcessor トレイトの具体的な実装
@@ -23,11 +48,288 @@ pub trait ImageProcessor {
 }
 */
 
-pub struct DefaultImageProcessor;
+// デコード時の上限。寸法だけ巨大に宣言した小さなファイル (解凍爆弾) で
+// 何GBも確保させないよう、ピクセルを展開する前にヘッダの寸法で弾く
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_alloc: u64, // デコーダが一度に確保してよいバイト数
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_width: 8192,
+            max_height: 8192,
+            max_alloc: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl DecodeLimits {
+    // LGTM_MAX_IMAGE_WIDTH / LGTM_MAX_IMAGE_HEIGHT / LGTM_MAX_DECODE_ALLOC で上書きできる
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+            std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+        let defaults = Self::default();
+        Self {
+            max_width: env_or("LGTM_MAX_IMAGE_WIDTH", defaults.max_width),
+            max_height: env_or("LGTM_MAX_IMAGE_HEIGHT", defaults.max_height),
+            max_alloc: env_or("LGTM_MAX_DECODE_ALLOC", defaults.max_alloc),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DefaultImageProcessor {
+    limits: DecodeLimits,
+    fallback_font: Option<Font<'static>>, // 同梱フォントにない文字 (日本語など) を描くフォント
+}
 
 impl DefaultImageProcessor {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_limits(limits: DecodeLimits) -> Self {
+        Self { limits, ..Self::default() }
+    }
+
+    pub fn with_fallback_font(mut self, font: Font<'static>) -> Self {
+        self.fallback_font = Some(font);
+        self
+    }
+
+    fn decode_image(
+        &self,
+        image_bytes: Vec<u8>,
+        input_format_opt: Option<InnerImageFormat>,
+    ) -> Result<RgbaImage, InfrastructureError> {
+        // decode() は EXIF の Orientation を無視するので、先に読んでおいて回転を戻す
+        let orientation = read_exif_orientation(&image_bytes);
+        let mut reader = match input_format_opt {
+            Some(format) => image::io::Reader::with_format(Cursor::new(image_bytes), format),
+            None => image::io::Reader::new(Cursor::new(image_bytes)).with_guessed_format().map_err(InfrastructureError::IoError)?,
+        };
+        let mut limits = image::io::Limits::default();
+        limits.max_image_width = Some(self.limits.max_width);
+        limits.max_image_height = Some(self.limits.max_height);
+        limits.max_alloc = Some(self.limits.max_alloc);
+        reader.limits(limits);
+
+        let img = reader.decode().map_err(|e| match e {
+            image::ImageError::Limits(limit_err) => InfrastructureError::ImageLimitExceeded(limit_err.to_string()),
+            e => InfrastructureError::ImageLibError(e),
+        })?.to_rgba8();
+        Ok(apply_exif_orientation(img, orientation))
+    }
+
+    // ポリシーで残すことになっている元画像のメタデータ
+    fn source_metadata(&self, image_bytes: &[u8], policy: MetadataPolicy) -> ImageMetadata {
+        match policy {
+            MetadataPolicy::StripAll => ImageMetadata::default(),
+            policy => ImageMetadata::extract(image_bytes).filtered(policy),
+        }
+    }
+
+    // 縮小 → (必要なら減色) → エンコード → メタデータ書き込みの順に EncodeSettings を適用する
+    fn encode_image(
+        &self,
+        mut img: RgbaImage,
+        encode_settings: &EncodeSettings,
+        metadata: &ImageMetadata,
+    ) -> Result<DomainImage, InfrastructureError> {
+        if encode_settings.scale < 1.0 {
+            let width = ((img.width() as f32 * encode_settings.scale).round() as u32).max(1);
+            let height = ((img.height() as f32 * encode_settings.scale).round() as u32).max(1);
+            img = imageops::resize(&img, width, height, FilterType::Lanczos3);
+        }
+
+        let data = match (encode_settings.format, encode_settings.max_colors) {
+            (InnerImageFormat::Jpeg, _) => {
+                // JPEG はアルファを持てないので RGB に落としてから品質指定でエンコード
+                let mut buffer = Cursor::new(Vec::new());
+                let rgb = DynamicImage::ImageRgba8(img.clone()).to_rgb8();
+                JpegEncoder::new_with_quality(&mut buffer, encode_settings.quality.clamp(1, 100))
+                    .encode_image(&rgb)
+                    .map_err(InfrastructureError::ImageLibError)?;
+                buffer.into_inner()
+            }
+            (InnerImageFormat::Gif, max_colors) => {
+                encode_gif(&quantize(&img, max_colors.unwrap_or(256), encode_settings.dither))?
+            }
+            (InnerImageFormat::Png, Some(max_colors)) => {
+                encode_png8(&quantize(&img, max_colors, encode_settings.dither))?
+            }
+            (format, _) => {
+                let mut buffer = Cursor::new(Vec::new());
+                img.write_to(&mut buffer, format).map_err(InfrastructureError::ImageLibError)?;
+                buffer.into_inner()
+            }
+        };
+        let data = embed_metadata(data, encode_settings.format, metadata)?;
+        Ok(DomainImage::new(data, img.width(), img.height(), encode_settings.format))
+    }
+}
+
+// 配置を決めたテキスト。横書きは 1 行まとめて、縦書きや曲線は 1 文字ずつ描く
+// 折り返した横書きは行ごとに配置と文字列を持つ
+enum PlacedText {
+    Line(TextLayout),
+    Lines(Vec<(TextLayout, String)>),
+    Glyphs(GlyphLayout),
+}
+
+impl PlacedText {
+    // 描く文字の大きさ (px)。効果の太さを文字に合わせるのに使う
+    fn text_size(&self) -> f32 {
+        match self {
+            PlacedText::Line(layout) => layout.scale.y,
+            PlacedText::Lines(lines) => lines.first().map_or(0.0, |(layout, _)| layout.scale.y),
+            PlacedText::Glyphs(layout) => layout.scale.y,
+        }
+    }
+
+    fn bounds(&self) -> Area {
+        let line_bounds = |layout: &TextLayout| (layout.x, layout.y, layout.width, layout.height);
+        match self {
+            PlacedText::Line(layout) => line_bounds(layout),
+            PlacedText::Lines(lines) => {
+                let (mut x0, mut y0, mut x1, mut y1) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+                for (layout, _) in lines {
+                    x0 = x0.min(layout.x);
+                    y0 = y0.min(layout.y);
+                    x1 = x1.max(layout.x + layout.width as i32);
+                    y1 = y1.max(layout.y + layout.height as i32);
+                }
+                if lines.is_empty() {
+                    return (0, 0, 0, 0);
+                }
+                (x0, y0, (x1 - x0) as u32, (y1 - y0) as u32)
+            }
+            PlacedText::Glyphs(layout) => layout.bounds(),
+        }
+    }
+}
+
+// 折り返すときに画像のふちから空ける距離 (短辺に対する比率) と、縮める前の文字の大きさ (高さに対する比率)
+const WRAP_MARGIN_RATIO: f32 = 0.03;
+const WRAP_BASE_SCALE_RATIO: f32 = 1.0 / 7.0;
+
+// テキストを 1 つ配置して描く。caption (範囲, 文字の大きさ) がなければ画像全体に置く
+// backdrop があれば、測った文字の範囲の下に先に敷く
+fn draw_text_overlay(
+    img: &mut RgbaImage,
+    fonts: &FontSet,
+    text_overlay: &DomainTextOverlay,
+    caption: Option<(Area, f32)>,
+    backdrop: Option<&TextBackdrop>,
+) {
+    let color = Rgba([
+        text_overlay.color.r,
+        text_overlay.color.g,
+        text_overlay.color.b,
+        text_overlay.color.a,
+    ]);
+    let text = &text_overlay.text;
+    let (width, height) = img.dimensions();
+    let paint = match text_overlay.effect {
+        Some(TextEffect::Rainbow { hue_step }) => TextPaint::Rainbow { base: color, hue_step },
+        _ => TextPaint::Solid(color),
+    };
+    // ふちの半透明な画素が黒ずまないよう、レイヤーの地は文字の色で透明にしておく
+    let transparent = Rgba([color[0], color[1], color[2], 0]);
+
+    // 繰り返しは position や caption を使わず画像全体に敷き詰め、効果をかけてから薄くして重ねる
+    if let Some(tile) = &text_overlay.tile {
+        let (layer, text_size) = render_tiled_text(fonts.primary, text, paint, transparent, (width, height), tile);
+        let mut tiled = match &text_overlay.effect {
+            Some(effect) => {
+                let mut canvas = RgbaImage::from_pixel(width, height, transparent);
+                draw_text_effect(&mut canvas, &layer, effect, text_size);
+                canvas
+            }
+            None => layer,
+        };
+        let opacity = tile.opacity.clamp(0.0, 1.0);
+        for pixel in tiled.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+        }
+        imageops::overlay(img, &tiled, 0, 0);
+        return;
+    }
+
+    let (area, base_scale) = caption.unwrap_or(((0, 0, width, height), default_scale(text, height)));
+
+    let glyph_layout = match text_overlay.writing_mode {
+        WritingMode::Vertical => Some(layout_vertical_text(fonts, text, &text_overlay.position, area, base_scale)),
+        WritingMode::Horizontal => layout_text_on_path(fonts, text, &text_overlay.path, &text_overlay.position, area, base_scale),
+    };
+    let placed = match (glyph_layout, caption, &text_overlay.subtitle) {
+        (Some(layout), _, _) => PlacedText::Glyphs(layout),
+        // 略語の下に展開を添えるときは 2 行をひとかたまりにして置く
+        (None, _, Some(subtitle)) => {
+            PlacedText::Lines(layout_text_with_subtitle(fonts.primary, text, &subtitle.text, &text_overlay.position, area, base_scale, subtitle.scale))
+        }
+        (None, _, None) if text_overlay.wrap.is_some() => {
+            let max_height = text_overlay.wrap.map_or(1.0, |wrap| wrap.max_height.clamp(0.0, 1.0));
+            let (area, base_scale) = match caption {
+                Some(caption) => caption,
+                None => {
+                    let margin = (width.min(height) as f32 * WRAP_MARGIN_RATIO) as u32;
+                    let area = (margin as i32, margin as i32, width.saturating_sub(margin * 2), height.saturating_sub(margin * 2));
+                    (area, height as f32 * WRAP_BASE_SCALE_RATIO)
+                }
+            };
+            PlacedText::Lines(layout_wrapped_text(fonts.primary, text, &text_overlay.position, area, base_scale, area.3 as f32 * max_height))
+        }
+        (None, Some((area, base_scale)), None) => PlacedText::Line(layout_text_in(fonts.primary, text, &text_overlay.position, area, base_scale)),
+        // テキストのスケールと位置計算 (main.rs のロジックを text_layout に移した)
+        (None, None, None) => PlacedText::Line(layout_text(fonts.primary, text, &text_overlay.position, (width, height))),
+    };
+    if let Some(backdrop) = backdrop {
+        apply_text_backdrop(img, placed.bounds(), backdrop);
+    }
+
+    let draw = |target: &mut RgbaImage| match &placed {
+        PlacedText::Line(layout) => draw_text_runs(target, paint, layout, fonts.primary, text),
+        PlacedText::Lines(lines) => {
+            for (layout, line) in lines {
+                draw_text_runs(target, paint, layout, fonts.primary, line);
+            }
+        }
+        PlacedText::Glyphs(layout) => draw_glyph_layout(target, paint, layout, fonts),
+    };
+    match &text_overlay.effect {
+        // 効果をかける場合は透明なレイヤーに描いてから重ねる
+        Some(effect) => {
+            let mut layer = RgbaImage::from_pixel(width, height, transparent);
+            draw(&mut layer);
+            draw_text_effect(img, &layer, effect, placed.text_size());
+        }
+        None => draw(img),
+    }
+}
+
+// EXIF の Orientation タグ (1-8) を読む。EXIF が無い・読めない場合は None
+fn read_exif_orientation(image_bytes: &[u8]) -> Option<u32> {
+    let exif = exif::Reader::new().read_from_container(&mut Cursor::new(image_bytes)).ok()?;
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?.value.get_uint(0)
+}
+
+// 保存されたピクセルを Orientation に従って正立させる
+fn apply_exif_orientation(img: RgbaImage, orientation: Option<u32>) -> RgbaImage {
+    match orientation {
+        Some(2) => imageops::flip_horizontal(&img),
+        Some(3) => imageops::rotate180(&img),
+        Some(4) => imageops::flip_vertical(&img),
+        Some(5) => imageops::flip_horizontal(&imageops::rotate90(&img)), // transpose
+        Some(6) => imageops::rotate90(&img),
+        Some(7) => imageops::flip_horizontal(&imageops::rotate270(&img)), // transverse
+        Some(8) => imageops::rotate270(&img),
+        _ => img,
     }
 }
 
@@ -39,78 +341,110 @@ impl ImageProcessor for DefaultImageProcessor {
         image_bytes: Vec<u8>, // 元の画像のバイト列
         input_format_opt: Option<InnerImageFormat>, // 元の画像のフォーマット (推測に任せる場合はNone)
         text_overlay: &DomainTextOverlay,
-        output_format: InnerImageFormat,
-    ) -> Result<Vec<u8>, InfrastructureError> { // Changed to InfrastructureError
-        let reader = match input_format_opt {
-            Some(format) => image::io::Reader::with_format(Cursor::new(image_bytes), format),
-            None => image::io::Reader::new(Cursor::new(image_bytes)).with_guessed_format().map_err(InfrastructureError::IoError)?,
-        };
-        let mut img = reader.decode().map_err(InfrastructureError::ImageLibError)?.to_rgba8();
+        render_options: &RenderOptions,
+        encode_settings: &EncodeSettings,
+    ) -> Result<DomainImage, InfrastructureError> { // Changed to InfrastructureError
+        let metadata = self.source_metadata(&image_bytes, encode_settings.metadata_policy);
+        let img = self.decode_image(image_bytes, input_format_opt)?;
+        // テキストは変形後の画像の大きさに合わせて配置する
+        let img = apply_transform(img, &render_options.transform, (self.limits.max_width, self.limits.max_height))?;
+        let img = apply_filters(img, &render_options.filters);
 
         let font_data = include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf");
         let font = Font::try_from_bytes(font_data).ok_or_else(|| InfrastructureError::ImageProcessingError("Failed to load font".to_string()))?;
 
-        let color = Rgba([
-            text_overlay.color.r,
-            text_overlay.color.g,
-            text_overlay.color.b,
-            text_overlay.color.a,
-        ]);
-
-        // テキストのスケールと位置計算 (main.rs のロジックを適用)
-        // この部分は main.rs の add_text 関数のロジックをほぼそのまま持ってくる
-        // ただし、入力として DomainTextOverlay の position を使う
-        let text = &text_overlay.text;
-        let mut current_scale_val = if text.len() > 20 {
-            img.height() as f32 / (text.len() as f32 / 2.5)
-        } else if text.len() > 10 {
-            img.height() as f32 / (text.len() as f32 / 1.8)
-        } else {
-            img.height() as f32 / 5.0
-        };
-        if current_scale_val < 1.0 { current_scale_val = 1.0; }
-        let mut scale = Scale::uniform(current_scale_val);
-        let mut v_metrics = font.v_metrics(scale);
-        let mut glyphs: Vec<_> = font.layout(text, scale, point(0.0, 0.0)).collect();
-        let mut text_width = glyphs.iter().filter_map(|g| g.pixel_bounding_box()).map(|bb| bb.max.x as f32).last().unwrap_or(0.0);
-        let mut text_height = v_metrics.ascent - v_metrics.descent;
-
-        let max_text_width_ratio = 0.90;
-        if text_width > img.width() as f32 * max_text_width_ratio && text_width > 0.0 {
-            let new_scale_factor = (img.width() as f32 * max_text_width_ratio) / text_width;
-            current_scale_val *= new_scale_factor;
-            if current_scale_val < 1.0 { current_scale_val = 1.0; }
-            scale = Scale::uniform(current_scale_val);
-            v_metrics = font.v_metrics(scale);
-            glyphs = font.layout(text, scale, point(0.0, 0.0)).collect();
-            text_width = glyphs.iter().filter_map(|g| g.pixel_bounding_box()).map(|bb| bb.max.x as f32).last().unwrap_or(0.0);
-            text_height = v_metrics.ascent - v_metrics.descent;
-        }
-
-        let final_scale = scale;
-        let v_metrics_final = v_metrics;
-        let actual_text_glyph_height = text_height;
-
-        let (mut x_pos, mut y_pos_base) = match text_overlay.position {
-            DomainPosition::TopLeft => (0.0, 0.0),
-            DomainPosition::TopCenter => ((img.width() as f32 - text_width) / 2.0, 0.0),
-            DomainPosition::TopRight => (img.width() as f32 - text_width, 0.0),
-            DomainPosition::CenterLeft => (0.0, (img.height() as f32 - actual_text_glyph_height) / 2.0),
-            DomainPosition::Center | DomainPosition::Custom { .. } => ((img.width() as f32 - text_width) / 2.0, (img.height() as f32 - actual_text_glyph_height) / 2.0), // Customは一旦Centerと同じ扱い
-            DomainPosition::CenterRight => (img.width() as f32 - text_width, (img.height() as f32 - actual_text_glyph_height) / 2.0),
-            DomainPosition::BottomLeft => (0.0, img.height() as f32 - actual_text_glyph_height),
-            DomainPosition::BottomCenter => ((img.width() as f32 - text_width) / 2.0, img.height() as f32 - actual_text_glyph_height),
-            DomainPosition::BottomRight => (img.width() as f32 - text_width, img.height() as f32 - actual_text_glyph_height),
-        };
-        if x_pos < 0.0 { x_pos = 0.0; }
-        if y_pos_base < 0.0 {y_pos_base = 0.0; }
-        let y_pos = y_pos_base + v_metrics_final.ascent;
-
-        draw_text_mut(&mut img, color, x_pos as i32, y_pos as i32, final_scale, &font, text);
+        let fonts = FontSet { primary: &font, fallback: self.fallback_font.as_ref() };
 
-        let mut buffer = Cursor::new(Vec::new());
-        img.write_to(&mut buffer, output_format).map_err(InfrastructureError::ImageLibError)?;
-        Ok(buffer.into_inner())
+        // ポラロイドではテキストを下の余白に描く
+        let frame = &render_options.frame;
+        let max_side = (self.limits.max_width, self.limits.max_height);
+        let (mut img, caption) = match &frame.polaroid {
+            Some(polaroid) => {
+                let (img, caption_area) = add_polaroid_margin(img, polaroid, max_side)?;
+                (img, Some((caption_area, caption_area.3 as f32 * 0.5)))
+            }
+            None => (img, None),
+        };
+        // 測ったテキストの範囲の下だけをすりガラス風にする
+        draw_text_overlay(&mut img, &fonts, text_overlay, caption, render_options.text_backdrop.as_ref());
+
+        for overlay in &render_options.overlays {
+            match overlay {
+                Overlay::Image(image_overlay) => {
+                    // ロゴも元画像と同じ上限でデコードする
+                    let layer = self.decode_image(image_overlay.image_bytes.clone(), None)?;
+                    draw_image_overlay(&mut img, &layer, image_overlay);
+                }
+                Overlay::Stamp(stamp) => {
+                    let size = (img.width().min(img.height()) as f32 * stamp.scale.clamp(0.0, 1.0)).round().max(16.0) as u32;
+                    let layer = render_stamp(stamp, &fonts, size);
+                    draw_layer(&mut img, &layer, &stamp.position, stamp.margin);
+                }
+                // 追加のテキストはポラロイドでも画像全体に置く
+                Overlay::Text(extra) => draw_text_overlay(&mut img, &fonts, extra, None, None),
+                Overlay::Bubble(bubble) => draw_bubble(&mut img, &fonts, bubble),
+                Overlay::Qr(qr) => {
+                    let size = (img.width().min(img.height()) as f32 * qr.size.clamp(0.0, 1.0)).round() as u32;
+                    // draw_layer と同じく余白を除いた範囲に収める
+                    let margin = qr.margin.min(img.width() / 2).min(img.height() / 2);
+                    let layer = render_qr(qr, size, (img.width() - margin * 2).min(img.height() - margin * 2))?;
+                    draw_layer(&mut img, &layer, &qr.position, qr.margin);
+                }
+            }
+        }
+
+        if let Some(border) = &frame.border {
+            img = add_border(img, border, max_side)?;
+        }
+        round_corners(&mut img, frame.corner_radius);
+
+        self.encode_image(img, encode_settings, &metadata)
+    }
+
+    fn reencode_image(
+        &self,
+        image_bytes: Vec<u8>,
+        encode_settings: &EncodeSettings,
+    ) -> Result<DomainImage, InfrastructureError> {
+        let metadata = self.source_metadata(&image_bytes, encode_settings.metadata_policy);
+        let img = self.decode_image(image_bytes, None)?;
+        self.encode_image(img, encode_settings, &metadata)
+    }
+
+    fn compose_collage(
+        &self,
+        images: Vec<Vec<u8>>,
+        collage: &Collage,
+    ) -> Result<DomainImage, InfrastructureError> {
+        // 1 枚ずつ元画像と同じ上限でデコードし、すぐに 1 枚分の上限 (寸法と max_alloc) を枚数で割った画素数まで縮める
+        // 並べた後も上限に収める
+        let max_pixels = (self.limits.max_width as u64 * self.limits.max_height as u64).min(self.limits.max_alloc / 4);
+        let budget = max_pixels / images.len().max(1) as u64;
+        let images = images
+            .into_iter()
+            .map(|bytes| Ok(shrink_to_pixels(self.decode_image(bytes, None)?, budget)))
+            .collect::<Result<Vec<_>, InfrastructureError>>()?;
+        let img = compose_collage(&images, collage, (self.limits.max_width, self.limits.max_height));
+        self.encode_image(img, &EncodeSettings::new(InnerImageFormat::Png), &ImageMetadata::default())
+    }
+
+    fn render_contact_sheet(
+        &self,
+        images: Vec<(String, Vec<u8>)>,
+        sheet: &ContactSheet,
+    ) -> Result<DomainImage, InfrastructureError> {
+        let font_data = include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf");
+        let font = Font::try_from_bytes(font_data).ok_or_else(|| InfrastructureError::ImageProcessingError("Failed to load font".to_string()))?;
+        // 壊れた画像や上限を超える画像があっても一覧ごと失敗させず、そのマスは灰色にして ID だけ書く
+        let tiles: Vec<RgbaImage> = images
+            .into_iter()
+            .map(|(id, bytes)| {
+                let img = self.decode_image(bytes, None).unwrap_or_else(|_| placeholder_thumbnail(sheet.thumbnail_size));
+                render_contact_tile(&font, &img, &id, sheet)
+            })
+            .collect();
+        let img = compose_collage(&tiles, &sheet.collage, (self.limits.max_width, self.limits.max_height));
+        self.encode_image(img, &EncodeSettings::new(InnerImageFormat::Png), &ImageMetadata::default())
     }
 
     // main.rs の parse_hex_color をここに移植
@@ -142,7 +476,7 @@ mod tests {
     use super::*;
     use crate::domain::color::Color as DomainColor;
     use crate::domain::position::Position as DomainPosition;
-    use crate::domain::text_overlay::TextOverlay;
+    use crate::domain::text_overlay::{TextOverlay, TextPath};
     use image::ImageFormat; // image クレートの ImageFormat
     use crate::infrastructure::error::InfrastructureError; // For error matching
 
@@ -174,21 +508,18 @@ mod tests {
         let base64_image = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
         let image_bytes = base64::decode(base64_image).unwrap();
 
-        let text_overlay = TextOverlay {
-            text: "Test".to_string(),
-            color: DomainColor::new(255,0,0,255),
-            position: DomainPosition::Center,
-        };
+        let text_overlay = TextOverlay::new("Test".to_string(), DomainColor::new(255,0,0,255), DomainPosition::Center);
 
         let result = processor.add_text_to_image(
             image_bytes,
             Some(ImageFormat::Png), // 入力フォーマットを指定
             &text_overlay,
-            ImageFormat::Png // 出力フォーマットを指定
+            &RenderOptions::default(),
+            &EncodeSettings::new(ImageFormat::Png) // 出力フォーマットを指定
         );
         assert!(result.is_ok());
-        if let Ok(output_bytes) = result {
-            assert!(!output_bytes.is_empty());
+        if let Ok(output_image) = result {
+            assert!(!output_image.data.is_empty());
         }
     }
 
@@ -197,17 +528,14 @@ mod tests {
         let processor = DefaultImageProcessor::new();
         let invalid_image_bytes = vec![1, 2, 3, 4]; // 明らかに不正な画像データ
 
-        let text_overlay = TextOverlay {
-            text: "Test".to_string(),
-            color: DomainColor::new(255,0,0,255),
-            position: DomainPosition::Center,
-        };
+        let text_overlay = TextOverlay::new("Test".to_string(), DomainColor::new(255,0,0,255), DomainPosition::Center);
 
         let result = processor.add_text_to_image(
             invalid_image_bytes,
             None, // フォーマット推測させる
             &text_overlay,
-            ImageFormat::Png
+            &RenderOptions::default(),
+            &EncodeSettings::new(ImageFormat::Png)
         );
         assert!(result.is_err());
         if let Err(e) = result {
@@ -220,4 +548,622 @@ mod tests {
             }
         }
     }
+
+    // グラデーションのPNGを作る (減色・縮小の効果が見えるように色数を多めにする)
+    fn gradient_png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 255 / width) as u8, (y * 255 / height) as u8, ((x + y) % 256) as u8, 255])
+        });
+        let mut buffer = Cursor::new(Vec::new());
+        img.write_to(&mut buffer, ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    // 赤一色のPNGを作る
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255])).write_to(&mut buffer, ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_reencode_image_applies_scale() {
+        let processor = DefaultImageProcessor::new();
+        let settings = EncodeSettings { scale: 0.5, ..EncodeSettings::new(ImageFormat::Png) };
+
+        let result = processor.reencode_image(gradient_png(64, 32), &settings).unwrap();
+        assert_eq!((result.width, result.height), (32, 16));
+        let decoded = image::load_from_memory(&result.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 16));
+    }
+
+    #[test]
+    fn test_reencode_image_reduces_palette() {
+        let processor = DefaultImageProcessor::new();
+        let settings = EncodeSettings { max_colors: Some(16), ..EncodeSettings::new(ImageFormat::Png) };
+
+        let result = processor.reencode_image(gradient_png(64, 64), &settings).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        let distinct: std::collections::HashSet<[u8; 4]> = decoded.pixels().map(|p| p.0).collect();
+        assert!(distinct.len() <= 16, "expected at most 16 colors, got {}", distinct.len());
+    }
+
+    #[test]
+    fn test_reencode_image_lower_jpeg_quality_is_smaller() {
+        let processor = DefaultImageProcessor::new();
+        let high = EncodeSettings { quality: 95, ..EncodeSettings::new(ImageFormat::Jpeg) };
+        let low = EncodeSettings { quality: 20, ..EncodeSettings::new(ImageFormat::Jpeg) };
+
+        let high_result = processor.reencode_image(gradient_png(128, 128), &high).unwrap();
+        let low_result = processor.reencode_image(gradient_png(128, 128), &low).unwrap();
+        assert!(low_result.data.len() < high_result.data.len());
+    }
+
+    #[test]
+    fn test_add_text_to_image_outputs_png8_and_gif() {
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 255, 255, 255), DomainPosition::Center);
+
+        let png8 = EncodeSettings { max_colors: Some(32), dither: true, ..EncodeSettings::new(ImageFormat::Png) };
+        let result = processor.add_text_to_image(gradient_png(64, 64), None, &text_overlay, &RenderOptions::default(), &png8).unwrap();
+        let reader = png::Decoder::new(result.data.as_slice()).read_info().unwrap();
+        assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+
+        let gif = EncodeSettings { max_colors: Some(64), ..EncodeSettings::new(ImageFormat::Gif) };
+        let result = processor.add_text_to_image(gradient_png(64, 64), None, &text_overlay, &RenderOptions::default(), &gif).unwrap();
+        assert_eq!(image::guess_format(&result.data).unwrap(), ImageFormat::Gif);
+        assert_eq!((result.width, result.height), (64, 64));
+    }
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const GREEN: [u8; 3] = [0, 255, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+    const WHITE: [u8; 3] = [255, 255, 255];
+
+    // 4 分割した各区画を [左上, 右上, 左下, 右下] の色で塗った画像
+    fn quadrants(width: u32, height: u32, colors: [[u8; 3]; 4]) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let [r, g, b] = colors[(x >= width / 2) as usize + 2 * (y >= height / 2) as usize];
+            Rgba([r, g, b, 255])
+        })
+    }
+
+    // 四隅の色で向きが分かる 32x16 の画像 (左上: 赤, 右上: 緑, 左下: 青, 右下: 白)
+    fn orientation_marker() -> RgbaImage {
+        quadrants(32, 16, [RED, GREEN, BLUE, WHITE])
+    }
+
+    // JPEG の SOI 直後に Orientation だけを持つ APP1 (EXIF) セグメントを差し込む
+    fn jpeg_with_orientation(stored: &RgbaImage, orientation: u16) -> Vec<u8> {
+        let mut jpeg = Cursor::new(Vec::new());
+        let rgb = DynamicImage::ImageRgba8(stored.clone()).to_rgb8();
+        JpegEncoder::new_with_quality(&mut jpeg, 100).encode_image(&rgb).unwrap();
+        let jpeg = jpeg.into_inner();
+
+        let mut tiff = b"MM\x00\x2a\x00\x00\x00\x08".to_vec(); // ビッグエンディアン, IFD0 はオフセット 8
+        tiff.extend_from_slice(&1u16.to_be_bytes()); // エントリ数
+        tiff.extend_from_slice(&0x0112u16.to_be_bytes()); // Orientation
+        tiff.extend_from_slice(&3u16.to_be_bytes()); // SHORT
+        tiff.extend_from_slice(&1u32.to_be_bytes()); // count
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+        tiff.extend_from_slice(&0u32.to_be_bytes()); // 次の IFD なし
+
+        let mut app1 = b"Exif\x00\x00".to_vec();
+        app1.extend_from_slice(&tiff);
+        let mut out = jpeg[..2].to_vec(); // SOI
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn assert_close(actual: &Rgba<u8>, expected: [u8; 3], orientation: u16) {
+        for c in 0..3 {
+            assert!(
+                (actual[c] as i16 - expected[c] as i16).abs() < 40,
+                "orientation {}: expected {:?}, got {:?}", orientation, expected, actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_image_applies_all_exif_orientations() {
+        let processor = DefaultImageProcessor::new();
+
+        // カメラが保存するピクセルの並び (EXIF の仕様から手で書いたもの)。どれも正立させると orientation_marker になる
+        // 5〜8 は縦長の 16x32 で保存される
+        let cases = [
+            (1, quadrants(32, 16, [RED, GREEN, BLUE, WHITE])),
+            (2, quadrants(32, 16, [GREEN, RED, WHITE, BLUE])),
+            (3, quadrants(32, 16, [WHITE, BLUE, GREEN, RED])),
+            (4, quadrants(32, 16, [BLUE, WHITE, RED, GREEN])),
+            (5, quadrants(16, 32, [RED, BLUE, GREEN, WHITE])),
+            (6, quadrants(16, 32, [GREEN, WHITE, RED, BLUE])),
+            (7, quadrants(16, 32, [WHITE, GREEN, BLUE, RED])),
+            (8, quadrants(16, 32, [BLUE, RED, WHITE, GREEN])),
+        ];
+        for (orientation, stored) in cases {
+            let bytes = jpeg_with_orientation(&stored, orientation);
+            assert_eq!(read_exif_orientation(&bytes), Some(orientation as u32));
+
+            let decoded = processor.decode_image(bytes, None).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (32, 16), "orientation {}", orientation);
+            assert_close(decoded.get_pixel(4, 4), RED, orientation);
+            assert_close(decoded.get_pixel(27, 4), GREEN, orientation);
+            assert_close(decoded.get_pixel(4, 11), BLUE, orientation);
+            assert_close(decoded.get_pixel(27, 11), WHITE, orientation);
+        }
+    }
+
+    #[test]
+    fn test_decode_image_without_exif_keeps_pixels() {
+        let processor = DefaultImageProcessor::new();
+        let mut png = Cursor::new(Vec::new());
+        orientation_marker().write_to(&mut png, ImageFormat::Png).unwrap();
+        let png = png.into_inner();
+
+        assert_eq!(read_exif_orientation(&png), None);
+        let decoded = processor.decode_image(png, None).unwrap();
+        assert_eq!(decoded, orientation_marker());
+    }
+
+    #[test]
+    fn test_add_text_to_image_metadata_policy() {
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 255, 255, 255), DomainPosition::Center);
+        // Orientation=6 の EXIF と ICC プロファイルを持つ JPEG
+        let mut source = img_parts::jpeg::Jpeg::from_bytes(jpeg_with_orientation(&orientation_marker(), 6).into()).unwrap();
+        img_parts::ImageICC::set_icc_profile(&mut source, Some(vec![7u8; 32].into()));
+        let source = source.encoder().bytes().to_vec();
+
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+            let strip = EncodeSettings::new(format);
+            let output = processor.add_text_to_image(source.clone(), None, &text_overlay, &RenderOptions::default(), &strip).unwrap();
+            let metadata = ImageMetadata::extract(&output.data);
+            assert!(metadata.is_empty(), "{:?}: {:?}", format, metadata);
+
+            let icc = EncodeSettings { metadata_policy: MetadataPolicy::PreserveIcc, ..EncodeSettings::new(format) };
+            let output = processor.add_text_to_image(source.clone(), None, &text_overlay, &RenderOptions::default(), &icc).unwrap();
+            let metadata = ImageMetadata::extract(&output.data);
+            assert_eq!(metadata.icc_profile, Some(vec![7u8; 32]));
+            assert!(metadata.exif.is_none());
+
+            let all = EncodeSettings { metadata_policy: MetadataPolicy::PreserveAllExceptGps, ..EncodeSettings::new(format) };
+            let output = processor.add_text_to_image(source.clone(), None, &text_overlay, &RenderOptions::default(), &all).unwrap();
+            let metadata = ImageMetadata::extract(&output.data);
+            assert_eq!(metadata.icc_profile, Some(vec![7u8; 32]));
+            // ピクセルはすでに正立させてあるので、残した EXIF の Orientation は 1 になっている
+            assert_eq!(read_exif_orientation(&output.data), Some(1));
+            assert_eq!((output.width, output.height), (16, 32));
+        }
+    }
+
+    // 1x1 の PNG の IHDR だけを書き換えて、寸法を偽った PNG を作る
+    fn png_declaring(width: u32, height: u32) -> Vec<u8> {
+        let mut png = img_parts::png::Png::from_bytes(base64::decode(
+            "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII="
+        ).unwrap().into()).unwrap();
+        let mut ihdr = png.chunk_by_type(*b"IHDR").unwrap().contents().to_vec();
+        ihdr[0..4].copy_from_slice(&width.to_be_bytes());
+        ihdr[4..8].copy_from_slice(&height.to_be_bytes());
+        png.chunks_mut()[0] = img_parts::png::PngChunk::new(*b"IHDR", ihdr.into());
+        png.encoder().bytes().to_vec()
+    }
+
+    #[test]
+    fn test_decode_image_rejects_decompression_bomb_header() {
+        let processor = DefaultImageProcessor::new();
+        let bomb = png_declaring(60000, 60000);
+        assert!(bomb.len() < 100);
+
+        match processor.decode_image(bomb, None) {
+            Err(InfrastructureError::ImageLimitExceeded(_)) => {}
+            other => panic!("Expected ImageLimitExceeded, got {:?}", other.map(|i| i.dimensions())),
+        }
+    }
+
+    #[test]
+    fn test_decode_image_rejects_each_dimension_limit() {
+        let processor = DefaultImageProcessor::with_limits(DecodeLimits { max_width: 100, max_height: 50, ..DecodeLimits::default() });
+
+        for (width, height) in [(101, 1), (1, 51)] {
+            let result = processor.decode_image(png_declaring(width, height), Some(ImageFormat::Png));
+            assert!(matches!(result, Err(InfrastructureError::ImageLimitExceeded(_))), "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn test_add_text_to_image_respects_alloc_limit() {
+        let processor = DefaultImageProcessor::with_limits(DecodeLimits { max_alloc: 1024, ..DecodeLimits::default() });
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 255, 255, 255), DomainPosition::Center);
+
+        // 64x64 RGBA は 16KiB なので 1KiB の上限を超える
+        let result = processor.add_text_to_image(gradient_png(64, 64), None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png));
+        assert!(matches!(result, Err(InfrastructureError::ImageLimitExceeded(_))));
+
+        // 上限内の画像は通る
+        let result = processor.add_text_to_image(gradient_png(8, 8), None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_add_text_to_image_upscales_tiny_input_so_text_is_drawn() {
+        use crate::domain::transform::{MinSize, Transform, UpscaleFilter};
+
+        let processor = DefaultImageProcessor::new();
+        let image_bytes = base64::decode("iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=").unwrap();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::Center);
+        let render_options = RenderOptions {
+            transform: Transform {
+                min_size: Some(MinSize { min_side: 256, filter: UpscaleFilter::Auto }),
+                ..Transform::default()
+            },
+            ..RenderOptions::default()
+        };
+
+        let result = processor.add_text_to_image(image_bytes, None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        assert_eq!((result.width, result.height), (256, 256));
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert!(decoded.pixels().any(|p| p[0] > 200 && p[1] < 50 && p[2] < 50), "text should be visible");
+    }
+
+    #[test]
+    fn test_add_text_to_image_filters_background_but_not_text() {
+        use crate::domain::filter::ImageFilter;
+
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::Center);
+        let render_options = RenderOptions {
+            filters: vec![ImageFilter::Grayscale { amount: 1.0 }, ImageFilter::Darken { opacity: 0.5 }],
+            ..RenderOptions::default()
+        };
+
+        let result = processor.add_text_to_image(gradient_png(128, 128), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        // 背景はモノクロで暗く、テキストはフィルタ後に描くので赤いまま
+        let corner = decoded.get_pixel(127, 127);
+        assert!(corner[0] == corner[1] && corner[1] == corner[2] && corner[0] <= 128);
+        assert!(decoded.pixels().any(|p| p[0] > 200 && p[1] < 50 && p[2] < 50));
+    }
+
+    #[test]
+    fn test_add_text_to_image_backdrop_keeps_rest_of_image_crisp() {
+        use crate::domain::filter::TextBackdrop;
+
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::Center);
+        let render_options = RenderOptions {
+            text_backdrop: Some(TextBackdrop { blur_sigma: 4.0, darken: 0.5, padding: 4, feather: 4 }),
+            ..RenderOptions::default()
+        };
+
+        let plain = processor.add_text_to_image(gradient_png(256, 256), None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let frosted = processor.add_text_to_image(gradient_png(256, 256), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let plain = image::load_from_memory(&plain.data).unwrap().to_rgba8();
+        let frosted = image::load_from_memory(&frosted.data).unwrap().to_rgba8();
+
+        // 四隅は元のまま、テキストの周りは暗くなる
+        for (x, y) in [(0, 0), (255, 0), (0, 255), (255, 255)] {
+            assert_eq!(plain.get_pixel(x, y), frosted.get_pixel(x, y));
+        }
+        let darker = plain.pixels().zip(frosted.pixels()).filter(|(p, f)| f[1] < p[1]).count();
+        assert!(darker > 1000, "darker pixels: {}", darker);
+        // テキストは暗くしたあとで描くので赤いまま
+        assert!(frosted.pixels().any(|p| p[0] > 200 && p[1] < 50 && p[2] < 50));
+    }
+
+    #[test]
+    fn test_add_text_to_image_polaroid_caption_border_and_corners() {
+        use crate::domain::frame::{Border, BorderFill, Frame, Polaroid};
+
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::Center);
+        let render_options = RenderOptions {
+            frame: Frame {
+                polaroid: Some(Polaroid { color: DomainColor::new(250, 250, 250, 255) }),
+                border: Some(Border { width: 4, fill: BorderFill::Solid(DomainColor::new(0, 0, 255, 255)) }),
+                corner_radius: 8,
+            },
+            ..RenderOptions::default()
+        };
+
+        let result = processor.add_text_to_image(gradient_png(100, 80), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        // 100x80 + 余白 (左右上 5, 下 20) + 枠線 4
+        assert_eq!((result.width, result.height), (118, 113));
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0)[3], 0);
+        assert_eq!(decoded.get_pixel(59, 1).0, [0, 0, 255, 255]);
+        // テキストは写真の上ではなく下の余白に描かれる
+        let red = |p: &&Rgba<u8>| p[0] > 200 && p[1] < 50 && p[2] < 50;
+        let red_rows: Vec<u32> = decoded.enumerate_pixels().filter(|(_, _, p)| red(p)).map(|(_, y, _)| y).collect();
+        assert!(!red_rows.is_empty());
+        assert!(red_rows.iter().all(|y| (4 + 85..4 + 105).contains(y)), "{:?}", red_rows.iter().min());
+
+        // WebP も角の透明を保つ
+        let webp = processor.add_text_to_image(gradient_png(100, 80), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::WebP)).unwrap();
+        assert_eq!(image::guess_format(&webp.data).unwrap(), ImageFormat::WebP);
+        let decoded = image::load_from_memory(&webp.data).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0)[3], 0);
+        assert_eq!(decoded.get_pixel(59, 1).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_add_text_to_image_wraps_arc_text_around_center() {
+        use crate::domain::overlay::Overlay;
+
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::Center);
+        let arc_text = TextOverlay {
+            path: TextPath::Arc { radius: 0.3, angle: 0.0 },
+            ..TextOverlay::new("LOOKS GOOD TO ME".to_string(), DomainColor::new(0, 0, 255, 255), DomainPosition::Center)
+        };
+        let render_options = RenderOptions { overlays: vec![Overlay::Text(arc_text)], ..RenderOptions::default() };
+
+        let result = processor.add_text_to_image(gradient_png(300, 300), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        let blue: Vec<(u32, u32)> = decoded
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[2] > 200 && p[0] < 50 && p[1] < 50)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        // 円弧の文字は半径 90px の円の外側にあり、真上を中心に並ぶ
+        assert!(blue.len() > 200, "{}", blue.len());
+        assert!(blue.iter().all(|&(x, y)| ((x as f32 - 150.0).powi(2) + (y as f32 - 150.0).powi(2)).sqrt() > 85.0));
+        assert!(blue.iter().any(|&(x, y)| y < 60 && (140..160).contains(&x)));
+        assert!(blue.iter().all(|&(_, y)| y < 250));
+        assert!(decoded.pixels().any(|p| p[0] > 200 && p[1] < 50 && p[2] < 50));
+    }
+
+    #[test]
+    fn test_add_text_to_image_vertical_text_is_a_tall_column() {
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay {
+            writing_mode: WritingMode::Vertical,
+            ..TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::TopRight)
+        };
+
+        let result = processor.add_text_to_image(gradient_png(300, 300), None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        let red: Vec<(u32, u32)> = decoded
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[0] > 200 && p[1] < 50 && p[2] < 50)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let (min_x, max_x) = (red.iter().map(|p| p.0).min().unwrap(), red.iter().map(|p| p.0).max().unwrap());
+        let (min_y, max_y) = (red.iter().map(|p| p.1).min().unwrap(), red.iter().map(|p| p.1).max().unwrap());
+        // 右上から下へ 1 列に積まれる
+        assert!(max_y - min_y > 2 * (max_x - min_x), "{:?}", (min_x, max_x, min_y, max_y));
+        assert!(min_x > 200 && min_y < 20);
+    }
+
+    #[test]
+    fn test_add_text_to_image_applies_text_effects() {
+        let processor = DefaultImageProcessor::new();
+        let render = |effect: TextEffect| {
+            let text_overlay = TextOverlay {
+                effect: Some(effect),
+                ..TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 255, 255, 255), DomainPosition::Center)
+            };
+            let result = processor.add_text_to_image(gradient_png(300, 150), None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png)).unwrap();
+            image::load_from_memory(&result.data).unwrap().to_rgba8()
+        };
+
+        // 虹色は文字ごとに色が変わる (赤・緑・青がそれぞれ一番強い画素がある)
+        let rainbow = render(TextEffect::Rainbow { hue_step: 90.0 });
+        let saturated = |p: &Rgba<u8>, c: usize| p[c] > 200 && (0..3).filter(|&i| i != c).all(|i| p[i] < 100);
+        assert!(rainbow.pixels().any(|p| saturated(p, 0)));
+        assert!(rainbow.pixels().any(|p| saturated(p, 1)));
+
+        // 立体は白い文字の右下に灰色の側面ができる
+        let plain = render(TextEffect::Rainbow { hue_step: 0.0 });
+        let extruded = render(TextEffect::Extrude { depth: 6, angle: 45.0, shade: 0.5 });
+        let gray = |img: &RgbaImage| img.pixels().filter(|p| p[0] == 128 && p[1] == 128 && p[2] == 128).count();
+        assert!(gray(&extruded) > gray(&plain) + 100);
+
+        // ネオンは文字の外側に光の色がにじむ
+        let neon = render(TextEffect::Neon { color: DomainColor::new(255, 0, 255, 255), stroke: 2, glow: 6.0 });
+        let magenta = |img: &RgbaImage| img.pixels().filter(|p| p[0] > 200 && p[2] > 200 && p[1] < 60).count();
+        assert!(magenta(&neon) > magenta(&plain) + 100);
+    }
+
+    #[test]
+    fn test_add_text_to_image_tiles_faded_text() {
+        use crate::domain::text_overlay::TileLayout;
+
+        let processor = DefaultImageProcessor::new();
+        let black = DynamicImage::ImageRgba8(RgbaImage::from_pixel(300, 200, Rgba([0, 0, 0, 255])));
+        let mut input = Vec::new();
+        black.write_to(&mut Cursor::new(&mut input), ImageFormat::Png).unwrap();
+        let text_overlay = TextOverlay {
+            tile: Some(TileLayout { angle: -30.0, spacing: 1.0, opacity: 0.5 }),
+            ..TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 255, 255, 255), DomainPosition::Center)
+        };
+        let result = processor.add_text_to_image(input, None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+
+        // 四隅の近くにも文字があり、どれも半分の濃さより明るくならない
+        for (x, y) in [(0, 0), (200, 0), (0, 133), (200, 133)] {
+            let lit = (y..y + 67).flat_map(|y| (x..x + 100).map(move |x| (x, y))).filter(|&(x, y)| decoded.get_pixel(x, y)[0] > 64).count();
+            assert!(lit > 50, "{:?} {}", (x, y), lit);
+        }
+        assert!(decoded.pixels().all(|p| p[0] <= 129));
+    }
+
+    #[test]
+    fn test_add_text_to_image_wraps_outlined_captions() {
+        use crate::domain::overlay::Overlay;
+        use crate::domain::text_overlay::TextWrap;
+
+        let processor = DefaultImageProcessor::new();
+        let gray = DynamicImage::ImageRgba8(RgbaImage::from_pixel(300, 300, Rgba([128, 128, 128, 255])));
+        let mut input = Vec::new();
+        gray.write_to(&mut Cursor::new(&mut input), ImageFormat::Png).unwrap();
+        let caption = |text: &str, position| TextOverlay {
+            effect: Some(TextEffect::Outline { color: DomainColor::new(0, 0, 0, 255), width: 0.08 }),
+            wrap: Some(TextWrap { max_height: 0.3 }),
+            ..TextOverlay::new(text.to_string(), DomainColor::new(255, 255, 255, 255), position)
+        };
+        let render_options = RenderOptions {
+            overlays: vec![Overlay::Text(caption("BRACE YOURSELVES", DomainPosition::BottomCenter))],
+            ..RenderOptions::default()
+        };
+        let top = caption("ONE DOES NOT SIMPLY MERGE WITHOUT REVIEW", DomainPosition::TopCenter);
+        let result = processor.add_text_to_image(input, None, &top, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+
+        let rows_with = |pred: &dyn Fn(&Rgba<u8>) -> bool| (0..300).filter(|&y| (0..300).any(|x| pred(decoded.get_pixel(x, y)))).collect::<Vec<u32>>();
+        let white = rows_with(&|p| p[0] > 240);
+        let black = rows_with(&|p| p[0] < 16);
+        // 上下の 3 割に白い文字と黒いふちがあり、真ん中には何もない
+        assert!(white.iter().all(|&y| !(90..=210).contains(&y)), "{:?}", white);
+        assert!(white.iter().any(|&y| y < 90) && white.iter().any(|&y| y > 210));
+        assert!(black.len() > white.len());
+        // 長い上のキャプションは 1 行に収まらないので複数行になる (白い行が途切れる)
+        let top_rows: Vec<u32> = white.iter().cloned().filter(|&y| y < 90).collect();
+        assert!(top_rows.windows(2).any(|w| w[1] - w[0] > 1), "{:?}", top_rows);
+        // 左右にはみ出さない
+        assert!((0..300).all(|y| decoded.get_pixel(0, y)[0] == 128 && decoded.get_pixel(299, y)[0] == 128));
+    }
+
+    #[test]
+    fn test_add_text_to_image_stacks_subtitle_under_text() {
+        use crate::domain::text_overlay::Subtitle;
+
+        let processor = DefaultImageProcessor::new();
+        let gray = DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 300, Rgba([128, 128, 128, 255])));
+        let mut input = Vec::new();
+        gray.write_to(&mut Cursor::new(&mut input), ImageFormat::Png).unwrap();
+        let text_overlay = TextOverlay {
+            subtitle: Some(Subtitle { text: "Looks Good To Me".to_string(), scale: 0.3 }),
+            ..TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 255, 255, 255), DomainPosition::BottomCenter)
+        };
+        let result = processor.add_text_to_image(input, None, &text_overlay, &RenderOptions::default(), &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+
+        let white: Vec<u32> = (0..300).filter(|&y| (0..400).any(|x| decoded.get_pixel(x, y)[0] > 240)).collect();
+        // 白い行は 2 つの帯に分かれ、下の帯 (展開) は上の帯 (LGTM) より低く、画像の下端に寄る
+        let split = white.windows(2).position(|w| w[1] - w[0] > 1).expect("two bands");
+        let (upper, lower) = (&white[..=split], &white[split + 1..]);
+        assert!(lower.len() * 2 < upper.len(), "{:?} {:?}", upper, lower);
+        assert!(lower.windows(2).all(|w| w[1] - w[0] == 1), "{:?}", lower);
+        assert!(*lower.last().unwrap() >= 290 && upper[0] > 150, "{:?}", white);
+    }
+
+    #[test]
+    fn test_compose_collage_decodes_and_outputs_png() {
+        use crate::domain::collage::{Collage, CollageLayout};
+
+        let processor = DefaultImageProcessor::new();
+        let collage = Collage { layout: CollageLayout::Strip, gutter: 2, background: DomainColor::new(0, 0, 0, 255) };
+        let result = processor.compose_collage(vec![png_bytes(40, 20), png_bytes(20, 20)], &collage).unwrap();
+        assert_eq!(result.format, ImageFormat::Png);
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), (2 + 40 + 2 + 20 + 2, 24));
+        assert_eq!((result.width, result.height), (66, 24));
+        // 壊れた画像が混ざっていればエラー
+        assert!(processor.compose_collage(vec![png_bytes(40, 20), vec![1, 2, 3]], &collage).is_err());
+    }
+
+    #[test]
+    fn test_render_contact_sheet_lays_out_labelled_tiles() {
+        use crate::domain::collage::{Collage, CollageLayout, ContactSheet};
+
+        let processor = DefaultImageProcessor::new();
+        let sheet = ContactSheet {
+            collage: Collage { layout: CollageLayout::Grid { columns: 2 }, gutter: 4, background: DomainColor::new(255, 255, 255, 255) },
+            thumbnail_size: 50,
+            label_color: DomainColor::new(0, 0, 0, 255),
+        };
+        let images = vec![("3".to_string(), png_bytes(100, 100)), ("2".to_string(), png_bytes(80, 40)), ("1".to_string(), png_bytes(10, 10))];
+        let result = processor.render_contact_sheet(images, &sheet).unwrap();
+        assert_eq!(result.format, ImageFormat::Png);
+        // マスは 50x58 (ID 欄 8px)。2 列 2 行
+        assert_eq!((result.width, result.height), (4 + 50 + 4 + 50 + 4, 4 + 58 + 4 + 58 + 4));
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(*decoded.get_pixel(4 + 25, 4 + 25), Rgba([255, 0, 0, 255]));
+
+        // 読めない画像は灰色のマスになり、ほかの画像はそのまま並ぶ
+        let images = vec![("2".to_string(), vec![1, 2, 3]), ("1".to_string(), png_bytes(10, 10))];
+        let result = processor.render_contact_sheet(images, &sheet).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(*decoded.get_pixel(4 + 25, 4 + 25), Rgba([204, 204, 204, 255]));
+        assert_eq!(*decoded.get_pixel(58 + 25, 4 + 25), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_add_text_to_image_draws_logo_overlay() {
+        use crate::domain::overlay::{ImageOverlay, Overlay};
+
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new("LGTM".to_string(), DomainColor::new(255, 0, 0, 255), DomainPosition::Center);
+        let mut logo = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([0, 255, 0, 255])))
+            .write_to(&mut Cursor::new(&mut logo), ImageFormat::Png)
+            .unwrap();
+        let render_options = RenderOptions {
+            overlays: vec![Overlay::Image(ImageOverlay {
+                image_bytes: logo,
+                position: DomainPosition::TopLeft,
+                scale: 0.2,
+                opacity: 1.0,
+                margin: 8,
+            })],
+            ..RenderOptions::default()
+        };
+
+        let result = processor.add_text_to_image(gradient_png(100, 100), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(8, 8).0, [0, 255, 0, 255]);
+        assert_eq!(decoded.get_pixel(27, 27).0, [0, 255, 0, 255]);
+        assert_ne!(decoded.get_pixel(28, 28).0, [0, 255, 0, 255]);
+
+        // 壊れたロゴはエラーになる
+        let render_options = RenderOptions {
+            overlays: vec![Overlay::Image(ImageOverlay {
+                image_bytes: vec![1, 2, 3],
+                position: DomainPosition::TopLeft,
+                scale: 0.2,
+                opacity: 1.0,
+                margin: 8,
+            })],
+            ..RenderOptions::default()
+        };
+        assert!(processor.add_text_to_image(gradient_png(100, 100), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).is_err());
+    }
+
+    #[test]
+    fn test_add_text_to_image_draws_qr_overlay() {
+        use crate::domain::overlay::Overlay;
+        use crate::domain::qr::QrOverlay;
+
+        let processor = DefaultImageProcessor::new();
+        let text_overlay = TextOverlay::new(String::new(), DomainColor::new(255, 255, 255, 255), DomainPosition::Center);
+        let render_options = RenderOptions {
+            overlays: vec![Overlay::Qr(QrOverlay {
+                content: "https://github.com/koshiro1234/Create_LGTM_Image/pull/42".to_string(),
+                position: DomainPosition::BottomRight,
+                size: 0.5,
+                margin: 10,
+                quiet_zone: 4,
+                dark: DomainColor::new(0, 0, 0, 255),
+                light: DomainColor::new(255, 255, 255, 255),
+            })],
+            ..RenderOptions::default()
+        };
+
+        let result = processor.add_text_to_image(gradient_png(200, 200), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png)).unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        // 100px に 41 モジュールなので 1 モジュール 2px、一辺 82px の QR が右下から 10px 内側に置かれる
+        assert_eq!(decoded.get_pixel(108, 108).0, [255, 255, 255, 255]);
+        assert_eq!(decoded.get_pixel(117, 117).0, [0, 0, 0, 255]);
+        assert_eq!(decoded.get_pixel(189, 189).0, [255, 255, 255, 255]);
+        assert_ne!(decoded.get_pixel(107, 107).0, [255, 255, 255, 255]);
+        assert_ne!(decoded.get_pixel(190, 190).0, [255, 255, 255, 255]);
+
+        // 小さい画像に収まらない QR は切れたまま描かずにエラー (400)
+        let result = processor.add_text_to_image(gradient_png(60, 60), None, &text_overlay, &render_options, &EncodeSettings::new(ImageFormat::Png));
+        assert!(matches!(result, Err(InfrastructureError::DomainErrorWrapper(_))));
+    }
 }
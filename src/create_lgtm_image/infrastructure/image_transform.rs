@@ -0,0 +1,336 @@
+use super::error::InfrastructureError;
+use crate::domain::color::Color as DomainColor;
+use crate::domain::error::DomainError;
+use crate::domain::transform::{AspectRatio, CropRect, MinSize, Resize, ResizeMode, Transform, UpscaleFilter};
+use std::collections::HashSet;
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+// テキスト描画前の変形をまとめて適用する (crop → aspect → resize → max_width/max_height → min_size)
+// max_side は出力の一辺の上限 (デコード時の上限と同じ値で、巨大なリサイズ指定を弾く)
+pub fn apply_transform(
+    mut img: RgbaImage,
+    transform: &Transform,
+    max_side: (u32, u32),
+) -> Result<RgbaImage, InfrastructureError> {
+    let padding = to_rgba(&transform.padding_color);
+
+    if let Some(rect) = &transform.crop {
+        img = crop(img, rect)?;
+    }
+    if let Some(aspect) = transform.aspect {
+        let (width, height) = aspect_target(img.dimensions(), aspect);
+        check_size(width, height, max_side)?;
+        img = pad_to(img, width as u32, height as u32, padding);
+    }
+    if let Some(resize) = &transform.resize {
+        let (width, height) = resize_target(img.dimensions(), resize)?;
+        check_size(width as u64, height as u64, max_side)?;
+        img = resize_with_mode(img, width, height, resize.mode, padding);
+    }
+    if transform.max_width.is_some() || transform.max_height.is_some() {
+        let max_width = transform.max_width.unwrap_or(u32::MAX);
+        let max_height = transform.max_height.unwrap_or(u32::MAX);
+        let (width, height) = img.dimensions();
+        if width > max_width || height > max_height {
+            img = resize_with_mode(img, max_width.min(width), max_height.min(height), ResizeMode::Fit, padding);
+        }
+    }
+    if let (Some(min_size), None) = (&transform.min_size, &transform.resize) {
+        // 拡大しても max_width/max_height とデコード上限は超えないようにする
+        let max_width = transform.max_width.unwrap_or(u32::MAX).min(max_side.0);
+        let max_height = transform.max_height.unwrap_or(u32::MAX).min(max_side.1);
+        img = upscale_to_min_size(img, min_size, (max_width, max_height));
+    }
+    Ok(img)
+}
+
+// 色数が少なければドット絵とみなす
+const PIXEL_ART_MAX_COLORS: usize = 64;
+
+fn looks_like_pixel_art(img: &RgbaImage) -> bool {
+    let mut colors = HashSet::new();
+    img.pixels().all(|p| {
+        colors.insert(p.0);
+        colors.len() <= PIXEL_ART_MAX_COLORS
+    })
+}
+
+fn upscale_to_min_size(img: RgbaImage, min_size: &MinSize, max_side: (u32, u32)) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let short_side = width.min(height);
+    if short_side >= min_size.min_side {
+        return img;
+    }
+    let nearest = match min_size.filter {
+        UpscaleFilter::Nearest => true,
+        UpscaleFilter::Lanczos => false,
+        UpscaleFilter::Auto => looks_like_pixel_art(&img),
+    };
+
+    let wanted = min_size.min_side as f64 / short_side as f64;
+    let cap = (max_side.0 as f64 / width as f64).min(max_side.1 as f64 / height as f64);
+    // Nearest は整数倍にしてドットの大きさを揃える
+    let scale = if nearest {
+        let integer = wanted.ceil();
+        if integer <= cap { integer } else { cap.floor() }
+    } else {
+        wanted.min(cap)
+    };
+    if scale <= 1.0 {
+        return img;
+    }
+
+    let new_width = ((width as f64 * scale).round() as u32).max(1);
+    let new_height = ((height as f64 * scale).round() as u32).max(1);
+    let filter = if nearest { FilterType::Nearest } else { FilterType::Lanczos3 };
+    imageops::resize(&img, new_width, new_height, filter)
+}
+
+pub fn to_rgba(color: &DomainColor) -> Rgba<u8> {
+    Rgba([color.r, color.g, color.b, color.a])
+}
+
+pub fn check_size(width: u64, height: u64, max_side: (u32, u32)) -> Result<(), InfrastructureError> {
+    if width > max_side.0 as u64 || height > max_side.1 as u64 {
+        return Err(InfrastructureError::ImageLimitExceeded(format!(
+            "transformed size {}x{} exceeds {}x{}", width, height, max_side.0, max_side.1
+        )));
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> InfrastructureError {
+    InfrastructureError::DomainErrorWrapper(DomainError::InvalidInput(message))
+}
+
+// 画像からはみ出した分は切り詰める。画像の外を指定した場合はエラー
+fn crop(img: RgbaImage, rect: &CropRect) -> Result<RgbaImage, InfrastructureError> {
+    let (width, height) = img.dimensions();
+    if rect.x >= width || rect.y >= height || rect.width == 0 || rect.height == 0 {
+        return Err(invalid(format!(
+            "crop {}x{}+{}+{} is outside of {}x{} image", rect.width, rect.height, rect.x, rect.y, width, height
+        )));
+    }
+    let crop_width = rect.width.min(width - rect.x);
+    let crop_height = rect.height.min(height - rect.y);
+    Ok(imageops::crop_imm(&img, rect.x, rect.y, crop_width, crop_height).to_image())
+}
+
+// 縦横比を合わせたときの大きさ。元画像は中央に置き、足りない方向をパディング色で埋める
+fn aspect_target((width, height): (u32, u32), aspect: AspectRatio) -> (u64, u64) {
+    let (width, height) = (width as u64, height as u64);
+    let (ratio_w, ratio_h) = (aspect.width.max(1) as u64, aspect.height.max(1) as u64);
+    if width * ratio_h > height * ratio_w {
+        (width, (width * ratio_h).div_ceil(ratio_w))
+    } else {
+        ((height * ratio_w).div_ceil(ratio_h), height)
+    }
+}
+
+fn pad_to(img: RgbaImage, width: u32, height: u32, padding: Rgba<u8>) -> RgbaImage {
+    if img.dimensions() == (width, height) {
+        return img;
+    }
+    let mut canvas = RgbaImage::from_pixel(width, height, padding);
+    let x = (width as i64 - img.width() as i64) / 2;
+    let y = (height as i64 - img.height() as i64) / 2;
+    imageops::replace(&mut canvas, &img, x, y);
+    canvas
+}
+
+// 片方だけ指定された場合は縦横比を保ってもう片方を決める
+fn resize_target((width, height): (u32, u32), resize: &Resize) -> Result<(u32, u32), InfrastructureError> {
+    let scaled = |value: u32, numerator: u32, denominator: u32| {
+        ((value as u64 * numerator as u64) as f64 / denominator as f64).round().max(1.0) as u32
+    };
+    match (resize.width, resize.height) {
+        (Some(0), _) | (_, Some(0)) => Err(invalid("resize width and height must be positive".to_string())),
+        (Some(w), Some(h)) => Ok((w, h)),
+        (Some(w), None) => Ok((w, scaled(height, w, width))),
+        (None, Some(h)) => Ok((scaled(width, h, height), h)),
+        (None, None) => Ok((width, height)),
+    }
+}
+
+fn resize_with_mode(img: RgbaImage, width: u32, height: u32, mode: ResizeMode, padding: Rgba<u8>) -> RgbaImage {
+    // Fill は先に枠と同じ縦横比で中央を切り抜いてから縮める
+    // (先に拡大すると、極端に細長い画像では中間の画像が枠よりずっと大きくなるため)
+    if mode == ResizeMode::Fill {
+        let cropped = crop_to_aspect(img, width, height);
+        return if cropped.dimensions() == (width, height) {
+            cropped
+        } else {
+            imageops::resize(&cropped, width, height, FilterType::Lanczos3)
+        };
+    }
+
+    let scale_x = width as f64 / img.width() as f64;
+    let scale_y = height as f64 / img.height() as f64;
+    let scale = scale_x.min(scale_y);
+    let scaled_width = ((img.width() as f64 * scale).round() as u32).max(1);
+    let scaled_height = ((img.height() as f64 * scale).round() as u32).max(1);
+    let resized = if (scaled_width, scaled_height) == img.dimensions() {
+        img
+    } else {
+        imageops::resize(&img, scaled_width, scaled_height, FilterType::Lanczos3)
+    };
+
+    match mode {
+        ResizeMode::Contain => pad_to(resized, width, height, padding),
+        _ => resized,
+    }
+}
+
+// width:height と同じ縦横比になるよう、はみ出る方向の両端を切り落とす
+fn crop_to_aspect(img: RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let (src_width, src_height) = (img.width() as u64, img.height() as u64);
+    let (crop_width, crop_height) = if src_width * height as u64 > src_height * width as u64 {
+        ((src_height * width as u64 / height as u64).clamp(1, src_width), src_height)
+    } else {
+        (src_width, (src_width * height as u64 / width as u64).clamp(1, src_height))
+    };
+    if (crop_width, crop_height) == (src_width, src_height) {
+        return img;
+    }
+    let x = (src_width - crop_width) / 2;
+    let y = (src_height - crop_height) / 2;
+    imageops::crop_imm(&img, x as u32, y as u32, crop_width as u32, crop_height as u32).to_image()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_LIMIT: (u32, u32) = (u32::MAX, u32::MAX);
+
+    fn solid(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([200, 100, 50, 255]))
+    }
+
+    fn resize(width: Option<u32>, height: Option<u32>, mode: ResizeMode) -> Transform {
+        Transform { resize: Some(Resize { width, height, mode }), ..Transform::default() }
+    }
+
+    #[test]
+    fn test_resize_modes() {
+        let fit = apply_transform(solid(200, 100), &resize(Some(50), Some(50), ResizeMode::Fit), NO_LIMIT).unwrap();
+        assert_eq!(fit.dimensions(), (50, 25));
+
+        let fill = apply_transform(solid(200, 100), &resize(Some(50), Some(50), ResizeMode::Fill), NO_LIMIT).unwrap();
+        assert_eq!(fill.dimensions(), (50, 50));
+        assert_eq!(fill.get_pixel(0, 0)[3], 255);
+
+        let contain = apply_transform(solid(200, 100), &resize(Some(50), Some(50), ResizeMode::Contain), NO_LIMIT).unwrap();
+        assert_eq!(contain.dimensions(), (50, 50));
+        assert_eq!(contain.get_pixel(25, 0)[3], 0); // 上下の余白は透明
+        assert_eq!(contain.get_pixel(25, 25)[3], 255);
+    }
+
+    #[test]
+    fn test_fill_extreme_aspect_stays_within_target() {
+        // 1x8000 を 8000x1 の枠に Fill しても、8000x8000000 のような中間の画像は作らない
+        let fill = apply_transform(solid(1, 8000), &resize(Some(8000), Some(1), ResizeMode::Fill), (8192, 8192)).unwrap();
+        assert_eq!(fill.dimensions(), (8000, 1));
+        assert_eq!(*fill.get_pixel(4000, 0), Rgba([200, 100, 50, 255]));
+
+        let fill = apply_transform(solid(8000, 1), &resize(Some(1), Some(8000), ResizeMode::Fill), (8192, 8192)).unwrap();
+        assert_eq!(fill.dimensions(), (1, 8000));
+    }
+
+    #[test]
+    fn test_resize_single_side_keeps_aspect() {
+        let img = apply_transform(solid(400, 300), &resize(Some(200), None, ResizeMode::Fit), NO_LIMIT).unwrap();
+        assert_eq!(img.dimensions(), (200, 150));
+        let img = apply_transform(solid(400, 300), &resize(None, Some(30), ResizeMode::Fill), NO_LIMIT).unwrap();
+        assert_eq!(img.dimensions(), (40, 30));
+    }
+
+    #[test]
+    fn test_resize_and_aspect_reject_targets_over_limit() {
+        let result = apply_transform(solid(10, 10), &resize(Some(5000), Some(5000), ResizeMode::Fit), (4096, 4096));
+        assert!(matches!(result, Err(InfrastructureError::ImageLimitExceeded(_))));
+
+        let transform = Transform { aspect: Some(AspectRatio { width: 1, height: 10000 }), ..Transform::default() };
+        let result = apply_transform(solid(100, 10), &transform, (4096, 4096));
+        assert!(matches!(result, Err(InfrastructureError::ImageLimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_max_width_only_shrinks() {
+        let transform = Transform { max_width: Some(400), ..Transform::default() };
+        assert_eq!(apply_transform(solid(800, 200), &transform, NO_LIMIT).unwrap().dimensions(), (400, 100));
+        assert_eq!(apply_transform(solid(100, 50), &transform, NO_LIMIT).unwrap().dimensions(), (100, 50));
+    }
+
+    #[test]
+    fn test_crop_clamps_and_rejects_outside() {
+        let transform = Transform { crop: Some(CropRect { x: 10, y: 5, width: 100, height: 10 }), ..Transform::default() };
+        assert_eq!(apply_transform(solid(50, 50), &transform, NO_LIMIT).unwrap().dimensions(), (40, 10));
+
+        let transform = Transform { crop: Some(CropRect { x: 60, y: 0, width: 10, height: 10 }), ..Transform::default() };
+        assert!(matches!(
+            apply_transform(solid(50, 50), &transform, NO_LIMIT),
+            Err(InfrastructureError::DomainErrorWrapper(DomainError::InvalidInput(_)))
+        ));
+    }
+
+    #[test]
+    fn test_aspect_presets_pad_with_color() {
+        let padding = DomainColor::new(255, 0, 0, 255);
+        let cases = [
+            (AspectRatio::SQUARE, (300, 100), (300, 300)),
+            (AspectRatio::WIDE, (100, 100), (178, 100)),
+            (AspectRatio::STANDARD, (400, 100), (400, 300)),
+        ];
+        for (aspect, input, expected) in cases {
+            let transform = Transform { aspect: Some(aspect), padding_color: padding.clone(), ..Transform::default() };
+            let img = apply_transform(solid(input.0, input.1), &transform, NO_LIMIT).unwrap();
+            assert_eq!(img.dimensions(), expected, "{:?}", aspect);
+            assert_eq!(*img.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+            assert_eq!(*img.get_pixel(expected.0 / 2, expected.1 / 2), Rgba([200, 100, 50, 255]));
+        }
+    }
+
+    fn min_size(min_side: u32, filter: UpscaleFilter) -> Transform {
+        Transform { min_size: Some(MinSize { min_side, filter }), ..Transform::default() }
+    }
+
+    #[test]
+    fn test_min_size_upscales_pixel_art_with_integer_nearest() {
+        // 2x1 の 2 色画像 → 短辺 1 を 100 にするので 100 倍
+        let mut img = RgbaImage::from_pixel(2, 1, Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 0, Rgba([0, 0, 255, 255]));
+
+        let upscaled = apply_transform(img, &min_size(100, UpscaleFilter::Auto), NO_LIMIT).unwrap();
+        assert_eq!(upscaled.dimensions(), (200, 100));
+        // Nearest なので境界がぼけない
+        assert_eq!(*upscaled.get_pixel(99, 50), Rgba([255, 0, 0, 255]));
+        assert_eq!(*upscaled.get_pixel(100, 50), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_min_size_uses_lanczos_for_photos() {
+        let photo = RgbaImage::from_fn(20, 10, |x, y| Rgba([(x * 12) as u8, (y * 25) as u8, ((x * y) % 256) as u8, 255]));
+        let upscaled = apply_transform(photo, &min_size(25, UpscaleFilter::Auto), NO_LIMIT).unwrap();
+        assert_eq!(upscaled.dimensions(), (50, 25)); // 2.5 倍 (整数に丸めない)
+    }
+
+    #[test]
+    fn test_min_size_is_capped_and_skipped_when_large_or_resized() {
+        // 1x100 を短辺 256 にすると 256x25600 になるので、デコード上限で止める
+        let strip = solid(1, 100);
+        let upscaled = apply_transform(strip, &min_size(256, UpscaleFilter::Nearest), (4096, 4096)).unwrap();
+        assert_eq!(upscaled.dimensions(), (40, 4000));
+
+        let large = apply_transform(solid(300, 300), &min_size(256, UpscaleFilter::Auto), NO_LIMIT).unwrap();
+        assert_eq!(large.dimensions(), (300, 300));
+
+        // resize を明示したときはそちらを優先する
+        let transform = Transform {
+            resize: Some(Resize { width: Some(50), height: None, mode: ResizeMode::Fit }),
+            ..min_size(256, UpscaleFilter::Auto)
+        };
+        assert_eq!(apply_transform(solid(10, 10), &transform, NO_LIMIT).unwrap().dimensions(), (50, 50));
+    }
+}
@@ -1,5 +1,27 @@
 pub mod axum_handler;
 pub mod image_processor;
+pub mod palette_quantizer;
+pub mod image_metadata;
+pub mod image_transform;
+pub mod image_filters;
+pub mod image_frame;
+pub mod image_collage;
+pub mod contact_sheet;
+pub mod text_layout;
+pub mod overlay_renderer;
+pub mod sticker_library;
+pub mod text_renderer;
+pub mod text_path;
+pub mod vertical_layout;
+pub mod text_effects;
+pub mod text_tiling;
+pub mod fonts;
+pub mod stamp_renderer;
+pub mod bubble_renderer;
+pub mod qr_renderer;
 pub mod file_storage;
+pub mod asset_store;
+pub mod gallery_store;
+pub mod phrase_dictionary;
 pub mod external_image_fetcher;
 pub mod error;
@@ -0,0 +1,77 @@
+use super::text_layout::place;
+use crate::domain::overlay::ImageOverlay;
+use crate::domain::position::Position;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+// デコード済みの layer を overlay の指定 (大きさ・不透明度・位置・余白) で canvas に重ねる
+pub fn draw_image_overlay(canvas: &mut RgbaImage, layer: &RgbaImage, overlay: &ImageOverlay) {
+    let (canvas_width, canvas_height) = canvas.dimensions();
+    let (layer_width, layer_height) = layer.dimensions();
+    if layer_width == 0 || layer_height == 0 {
+        return;
+    }
+
+    // 幅を下の画像に対する比率で決め、高さは縦横比から。どちらも下の画像からはみ出さない
+    let scale = overlay.scale.clamp(0.0, 1.0);
+    let mut width = (canvas_width as f32 * scale).round().max(1.0);
+    let mut height = width * layer_height as f32 / layer_width as f32;
+    if height > canvas_height as f32 {
+        width *= canvas_height as f32 / height;
+        height = canvas_height as f32;
+    }
+    let (width, height) = (width.round().max(1.0) as u32, height.round().max(1.0) as u32);
+    let mut resized = imageops::resize(layer, width, height, FilterType::Lanczos3);
+
+    let opacity = overlay.opacity.clamp(0.0, 1.0);
+    if opacity < 1.0 {
+        for pixel in resized.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+        }
+    }
+
+    draw_layer(canvas, &resized, &overlay.position, overlay.margin);
+}
+
+// 大きさの決まったレイヤーを、ふちから margin 内側の範囲で position に従って重ねる
+pub fn draw_layer(canvas: &mut RgbaImage, layer: &RgbaImage, position: &Position, margin: u32) {
+    let (canvas_width, canvas_height) = canvas.dimensions();
+    let margin = margin.min(canvas_width / 2).min(canvas_height / 2);
+    let area = (margin as i32, margin as i32, canvas_width - margin * 2, canvas_height - margin * 2);
+    let (x, y) = place(position, (layer.width() as f32, layer.height() as f32), area);
+    imageops::overlay(canvas, layer, x as i64, y as i64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn logo_overlay(position: Position, opacity: f32) -> ImageOverlay {
+        ImageOverlay { image_bytes: Vec::new(), position, scale: 0.25, opacity, margin: 10 }
+    }
+
+    #[test]
+    fn test_draw_image_overlay_scales_and_places_with_margin() {
+        let mut canvas = RgbaImage::from_pixel(200, 100, Rgba([0, 0, 0, 255]));
+        let logo = RgbaImage::from_pixel(20, 10, Rgba([255, 255, 255, 255]));
+        draw_image_overlay(&mut canvas, &logo, &logo_overlay(Position::BottomRight, 1.0));
+
+        // 幅 200 * 0.25 = 50、高さ 25 のロゴが右下から 10px 内側に置かれる
+        assert_eq!(canvas.get_pixel(189, 89).0, [255, 255, 255, 255]);
+        assert_eq!(canvas.get_pixel(141, 66).0, [255, 255, 255, 255]);
+        assert_eq!(canvas.get_pixel(190, 90).0, [0, 0, 0, 255]);
+        assert_eq!(canvas.get_pixel(138, 89).0, [0, 0, 0, 255]);
+        assert_eq!(canvas.get_pixel(189, 63).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_draw_image_overlay_applies_opacity() {
+        let mut canvas = RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255]));
+        let logo = RgbaImage::from_pixel(10, 10, Rgba([200, 200, 200, 255]));
+        draw_image_overlay(&mut canvas, &logo, &logo_overlay(Position::Center, 0.5));
+        let center = canvas.get_pixel(50, 50);
+        assert!(center[0] > 90 && center[0] < 110, "{:?}", center);
+        assert_eq!(canvas.get_pixel(5, 5).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,173 @@
+use super::error::InfrastructureError;
+use color_quant::NeuQuant;
+use image::RgbaImage;
+
+// パレット化した画像 (PNG8 / GIF の書き出し用)
+pub struct IndexedImage {
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<[u8; 4]>, // RGBA。PNG8 では tRNS としてアルファも書き出す
+    pub indices: Vec<u8>,
+}
+
+// NeuQuant で max_colors 色のパレットを作り、各ピクセルをパレット番号に置き換える
+// dither が true なら Floyd–Steinberg で誤差を周囲に拡散する
+pub fn quantize(img: &RgbaImage, max_colors: u16, dither: bool) -> IndexedImage {
+    let colors = max_colors.clamp(2, 256) as usize;
+    let quantizer = NeuQuant::new(10, colors, img.as_raw());
+    let palette: Vec<[u8; 4]> = quantizer
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+        .collect();
+
+    let indices = if dither {
+        dither_floyd_steinberg(img, &quantizer, &palette)
+    } else {
+        img.pixels().map(|p| quantizer.index_of(&p.0) as u8).collect()
+    };
+
+    IndexedImage {
+        width: img.width(),
+        height: img.height(),
+        palette,
+        indices,
+    }
+}
+
+fn dither_floyd_steinberg(img: &RgbaImage, quantizer: &NeuQuant, palette: &[[u8; 4]]) -> Vec<u8> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let mut buffer: Vec<[f32; 4]> = img
+        .pixels()
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32, p[3] as f32])
+        .collect();
+    let mut indices = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let old = buffer[y * width + x];
+            let clamped = old.map(|v| v.round().clamp(0.0, 255.0) as u8);
+            let index = quantizer.index_of(&clamped);
+            indices.push(index as u8);
+
+            let new = palette[index];
+            let error: [f32; 4] = std::array::from_fn(|c| old[c] - new[c] as f32);
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                let ny = y + dy;
+                if nx >= 0 && (nx as usize) < width && ny < height {
+                    let target = &mut buffer[ny * width + nx as usize];
+                    for c in 0..4 {
+                        target[c] += error[c] * weight;
+                    }
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    indices
+}
+
+pub fn encode_png8(indexed: &IndexedImage) -> Result<Vec<u8>, InfrastructureError> {
+    let rgb: Vec<u8> = indexed.palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+    let trns: Vec<u8> = indexed.palette.iter().map(|c| c[3]).collect();
+
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, indexed.width, indexed.height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(rgb);
+        if trns.iter().any(|&a| a < 255) {
+            encoder.set_trns(trns);
+        }
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| InfrastructureError::ImageProcessingError(format!("PNG8 encoding failed: {}", e)))?;
+        writer
+            .write_image_data(&indexed.indices)
+            .map_err(|e| InfrastructureError::ImageProcessingError(format!("PNG8 encoding failed: {}", e)))?;
+    }
+    Ok(buffer)
+}
+
+// GIF は透明色を 1 つしか持てないので、半透明以下のパレット色はすべて 1 つの透明色にまとめる
+pub fn encode_gif(indexed: &IndexedImage) -> Result<Vec<u8>, InfrastructureError> {
+    let width = u16::try_from(indexed.width)
+        .map_err(|_| InfrastructureError::ImageProcessingError("Image is too wide for GIF".to_string()))?;
+    let height = u16::try_from(indexed.height)
+        .map_err(|_| InfrastructureError::ImageProcessingError("Image is too tall for GIF".to_string()))?;
+
+    let transparent = indexed.palette.iter().position(|c| c[3] < 128);
+    let indices: Vec<u8> = match transparent {
+        Some(t) => indexed
+            .indices
+            .iter()
+            .map(|&i| if indexed.palette[i as usize][3] < 128 { t as u8 } else { i })
+            .collect(),
+        None => indexed.indices.clone(),
+    };
+    let rgb: Vec<u8> = indexed.palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+
+    let mut buffer = Vec::new();
+    {
+        let frame = gif::Frame::from_palette_pixels(width, height, indices, rgb, transparent.map(|t| t as u8));
+        let mut encoder = gif::Encoder::new(&mut buffer, width, height, &[])
+            .map_err(|e| InfrastructureError::ImageProcessingError(format!("GIF encoding failed: {}", e)))?;
+        encoder
+            .write_frame(&frame)
+            .map_err(|e| InfrastructureError::ImageProcessingError(format!("GIF encoding failed: {}", e)))?;
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 255 / width) as u8, (y * 255 / height) as u8, 128, 255])
+        })
+    }
+
+    #[test]
+    fn test_quantize_limits_palette_size() {
+        let indexed = quantize(&gradient(32, 32), 16, false);
+        assert_eq!(indexed.palette.len(), 16);
+        assert_eq!(indexed.indices.len(), 32 * 32);
+        assert!(indexed.indices.iter().all(|&i| (i as usize) < indexed.palette.len()));
+    }
+
+    #[test]
+    fn test_dither_uses_more_palette_entries_on_gradient() {
+        // ディザありの方がグラデーションで多くのパレット色が混ざる
+        let img = gradient(64, 8);
+        let plain = quantize(&img, 4, false);
+        let dithered = quantize(&img, 4, true);
+        assert_eq!(dithered.indices.len(), plain.indices.len());
+        assert_ne!(dithered.indices, plain.indices);
+    }
+
+    #[test]
+    fn test_encode_png8_is_indexed() {
+        let bytes = encode_png8(&quantize(&gradient(16, 16), 8, true)).unwrap();
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+    }
+
+    #[test]
+    fn test_encode_gif_keeps_transparency() {
+        let mut img = gradient(8, 8);
+        img.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        let bytes = encode_gif(&quantize(&img, 16, false)).unwrap();
+        let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::Gif).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0)[3], 0);
+        assert_eq!(decoded.get_pixel(7, 7)[3], 255);
+    }
+}
@@ -0,0 +1,203 @@
+use super::error::InfrastructureError;
+use crate::domain::error::DomainError;
+
+// 同梱の辞書 (リポジトリ直下の phrases.txt)
+const DEFAULT_PHRASES: &str = include_str!("../../../phrases.txt");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phrase {
+    pub locale: String, // "en" / "ja" / "en-US" など
+    pub text: String,
+}
+
+// text=random で選ぶ LGTM の言葉の辞書
+// 1 行に "ロケール 言葉" を書く。# から始まる行と空行は読み飛ばす
+#[derive(Debug, Clone)]
+pub struct PhraseDictionary {
+    phrases: Vec<Phrase>,
+}
+
+impl Default for PhraseDictionary {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PHRASES).expect("bundled phrases.txt must be valid")
+    }
+}
+
+impl PhraseDictionary {
+    pub fn parse(source: &str) -> Result<Self, InfrastructureError> {
+        let mut phrases = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(char::is_whitespace) {
+                Some((locale, text)) if !text.trim().is_empty() => {
+                    phrases.push(Phrase { locale: locale.to_string(), text: text.trim().to_string() });
+                }
+                _ => {
+                    return Err(InfrastructureError::ImageProcessingError(format!(
+                        "Invalid phrase on line {}: expected \"<locale> <phrase>\"",
+                        i + 1
+                    )))
+                }
+            }
+        }
+        if phrases.is_empty() {
+            return Err(InfrastructureError::ImageProcessingError("Phrase dictionary is empty".to_string()));
+        }
+        Ok(Self { phrases })
+    }
+
+    pub fn load(path: &str) -> Result<Self, InfrastructureError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    // 起動時に読む。LGTM_PHRASES_FILE を指定すればその辞書、読めなければ同梱の辞書を使う
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("LGTM_PHRASES_FILE") else {
+            return Self::default();
+        };
+        match Self::load(&path) {
+            Ok(dictionary) => dictionary,
+            Err(e) => {
+                eprintln!("LGTM_PHRASES_FILE を読み込めませんでした: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn phrases(&self) -> &[Phrase] {
+        &self.phrases
+    }
+
+    // locale に合う言葉から seed で 1 つ選ぶ。同じ辞書・locale・seed なら毎回同じ言葉になる
+    // locale "en" は "en-US" などにも合う。None ならすべての言葉から選ぶ
+    pub fn pick(&self, locale: Option<&str>, seed: u64) -> Result<&str, DomainError> {
+        let candidates: Vec<&Phrase> = self
+            .phrases
+            .iter()
+            .filter(|p| locale.is_none_or(|l| locale_matches(&p.locale, l)))
+            .collect();
+        if candidates.is_empty() {
+            return Err(DomainError::InvalidInput(format!("No phrases for locale: {}", locale.unwrap_or_default())));
+        }
+        Ok(&candidates[(mix(seed) % candidates.len() as u64) as usize].text)
+    }
+
+    // 2 語以上の言葉から seed で 1 つ選び、頭文字 ("Let's Go To Mars" なら "LGTM") と一緒に返す
+    // text=random に subtitle=auto / random を合わせたとき、頭文字を大きく、言葉を下に添えるのに使う
+    pub fn pick_acronym(&self, locale: Option<&str>, seed: u64) -> Result<(String, &str), DomainError> {
+        let candidates: Vec<&Phrase> = self
+            .phrases
+            .iter()
+            .filter(|p| locale.is_none_or(|l| locale_matches(&p.locale, l)))
+            .filter(|p| initials(&p.text).chars().count() >= 2)
+            .collect();
+        if candidates.is_empty() {
+            return Err(DomainError::InvalidInput(format!("No multi-word phrases for locale: {}", locale.unwrap_or_default())));
+        }
+        let phrase = &candidates[(mix(seed) % candidates.len() as u64) as usize].text;
+        Ok((initials(phrase), phrase))
+    }
+
+    // 単語の頭文字をつなげると acronym になる言葉 ("LGTM" なら "Looks Good To Me" など)
+    // seed がなければ辞書で最初に書いた言葉、あれば seed で選ぶ
+    pub fn pick_expansion(&self, acronym: &str, locale: Option<&str>, seed: Option<u64>) -> Result<&str, DomainError> {
+        let acronym: String = acronym.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_uppercase).collect();
+        let candidates: Vec<&Phrase> = self
+            .phrases
+            .iter()
+            .filter(|p| locale.is_none_or(|l| locale_matches(&p.locale, l)))
+            .filter(|p| !acronym.is_empty() && initials(&p.text) == acronym)
+            .collect();
+        match (candidates.first(), seed) {
+            (None, _) => Err(DomainError::InvalidInput(format!("No phrase expands \"{}\"", acronym))),
+            (Some(first), None) => Ok(&first.text),
+            (Some(_), Some(seed)) => Ok(&candidates[(mix(seed) % candidates.len() as u64) as usize].text),
+        }
+    }
+}
+
+// 単語ごとの最初の英数字を大文字にしてつなげる ("Let's Go To Mars" なら "LGTM")
+fn initials(text: &str) -> String {
+    text.split_whitespace()
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+fn locale_matches(phrase_locale: &str, requested: &str) -> bool {
+    let (phrase_locale, requested) = (phrase_locale.to_lowercase().replace('_', "-"), requested.to_lowercase().replace('_', "-"));
+    phrase_locale == requested || phrase_locale.starts_with(&format!("{}-", requested))
+}
+
+// 連続した seed でも選ぶ言葉がばらけるようにかき混ぜる (splitmix64)
+fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "# comment\n\nen Looks Good To Me\nen-US Let's Go To Mars\n  ja   よさそう  \n";
+
+    #[test]
+    fn test_parse_skips_comments_and_trims() {
+        let dictionary = PhraseDictionary::parse(SOURCE).unwrap();
+        assert_eq!(dictionary.phrases().len(), 3);
+        assert_eq!(dictionary.phrases()[2], Phrase { locale: "ja".to_string(), text: "よさそう".to_string() });
+        assert!(PhraseDictionary::parse("en Looks Good To Me\nja\n").is_err());
+        assert!(PhraseDictionary::parse("# only comments\n").is_err());
+        // 同梱の辞書は英語と日本語の両方を持つ
+        let bundled = PhraseDictionary::default();
+        assert!(bundled.pick(Some("en"), 0).is_ok() && bundled.pick(Some("ja"), 0).is_ok());
+    }
+
+    #[test]
+    fn test_pick_filters_by_locale() {
+        let dictionary = PhraseDictionary::parse(SOURCE).unwrap();
+        for seed in 0..20 {
+            assert_eq!(dictionary.pick(Some("ja"), seed).unwrap(), "よさそう");
+            assert_ne!(dictionary.pick(Some("EN"), seed).unwrap(), "よさそう");
+        }
+        assert_eq!(dictionary.pick(Some("en_us"), 7).unwrap(), "Let's Go To Mars");
+        assert!(matches!(dictionary.pick(Some("fr"), 0), Err(DomainError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_pick_is_reproducible_and_spread_by_seed() {
+        let dictionary = PhraseDictionary::parse(SOURCE).unwrap();
+        assert_eq!(dictionary.pick(None, 42).unwrap(), dictionary.pick(None, 42).unwrap());
+        let picked: std::collections::HashSet<&str> = (0..20).map(|seed| dictionary.pick(None, seed).unwrap()).collect();
+        assert_eq!(picked.len(), 3);
+    }
+
+    #[test]
+    fn test_pick_expansion_matches_initials() {
+        let dictionary = PhraseDictionary::parse("en Looks Good To Me\nen Let's Go To Mars\nen Ship It\nja よさそう\n").unwrap();
+        assert_eq!(dictionary.pick_expansion("LGTM", None, None).unwrap(), "Looks Good To Me");
+        assert_eq!(dictionary.pick_expansion("s.i.", Some("en"), None).unwrap(), "Ship It");
+        let picked: std::collections::HashSet<&str> = (0..20).map(|seed| dictionary.pick_expansion("LGTM", None, Some(seed)).unwrap()).collect();
+        assert_eq!(picked.len(), 2);
+        assert!(matches!(dictionary.pick_expansion("LGTM", Some("ja"), None), Err(DomainError::InvalidInput(_))));
+        assert!(matches!(dictionary.pick_expansion("SGTM", None, None), Err(DomainError::InvalidInput(_))));
+        assert!(dictionary.pick_expansion("!!", None, None).is_err());
+    }
+
+    #[test]
+    fn test_pick_acronym_skips_single_word_phrases() {
+        let dictionary = PhraseDictionary::parse("en Looks Good To Me\nen Let's Go To Mars\nen Shipit\nja よさそう\n").unwrap();
+        for seed in 0..20 {
+            let (acronym, phrase) = dictionary.pick_acronym(None, seed).unwrap();
+            assert_eq!(acronym, "LGTM");
+            assert!(phrase == "Looks Good To Me" || phrase == "Let's Go To Mars");
+        }
+        assert_eq!(dictionary.pick_acronym(None, 5).unwrap(), dictionary.pick_acronym(None, 5).unwrap());
+        assert!(matches!(dictionary.pick_acronym(Some("ja"), 0), Err(DomainError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,93 @@
+use super::error::InfrastructureError;
+use crate::domain::color::Color;
+use crate::domain::error::DomainError;
+use crate::domain::qr::QrOverlay;
+use image::{Rgba, RgbaImage};
+use qrcode::{EcLevel, QrCode};
+
+// 一辺がだいたい size px の QR コードを、一辺 max_side px (置ける場所の大きさ) を超えないように描く
+// 読み取りやすいよう 1 モジュールを整数 px にそろえるので、実際の一辺は size 以下になる (小さすぎる場合は 1 モジュール 1 px)
+// 1 モジュール 1 px でも max_side に収まらない (端が切れて読めなくなる) 場合は InvalidInput
+pub fn render_qr(qr: &QrOverlay, size: u32, max_side: u32) -> Result<RgbaImage, InfrastructureError> {
+    let code = QrCode::with_error_correction_level(qr.content.as_bytes(), EcLevel::M)
+        .map_err(|e| InfrastructureError::ImageProcessingError(format!("Failed to encode QR code: {}", e)))?;
+    let modules = code.width() as u32;
+    let total = modules + qr.quiet_zone * 2;
+    if total > max_side {
+        return Err(DomainError::InvalidInput(format!(
+            "QR code needs at least {} px ({} modules with the quiet zone) but only {} px fit; shorten the content or reduce quietZone / margin",
+            total, total, max_side
+        ))
+        .into());
+    }
+    let module_size = (size / total).clamp(1, max_side / total);
+    let colors = code.to_colors();
+
+    let to_rgba = |c: &Color| Rgba([c.r, c.g, c.b, c.a]);
+    let (dark, light) = (to_rgba(&qr.dark), to_rgba(&qr.light));
+    Ok(RgbaImage::from_fn(total * module_size, total * module_size, |x, y| {
+        let (mx, my) = ((x / module_size) as i64 - qr.quiet_zone as i64, (y / module_size) as i64 - qr.quiet_zone as i64);
+        let in_code = (0..modules as i64).contains(&mx) && (0..modules as i64).contains(&my);
+        match in_code && colors[(my * modules as i64 + mx) as usize] == qrcode::Color::Dark {
+            true => dark,
+            false => light,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::position::Position;
+    use crate::domain::qr::MAX_QR_BYTES;
+
+    fn qr(content: &str, quiet_zone: u32) -> QrOverlay {
+        QrOverlay {
+            content: content.to_string(),
+            position: Position::BottomLeft,
+            size: 0.2,
+            margin: 16,
+            quiet_zone,
+            dark: Color::new(0, 0, 128, 255),
+            light: Color::new(255, 255, 224, 255),
+        }
+    }
+
+    #[test]
+    fn test_render_qr_uses_whole_pixel_modules_and_quiet_zone() {
+        let img = render_qr(&qr("https://github.com/koshiro1234/Create_LGTM_Image/pull/42", 4), 200, 1000).unwrap();
+        // 57 バイトの URL はバージョン 4 (33 モジュール) + 余白 4 モジュール x 2 = 41 モジュール、1 モジュール 4 px
+        assert_eq!(img.dimensions(), (164, 164));
+        let module = |mx: u32, my: u32| img.get_pixel(mx * 4 + 2, my * 4 + 2).0;
+        let (dark, light) = ([0, 0, 128, 255], [255, 255, 224, 255]);
+        // 余白は明るい色、左上のファインダパターンは 黒 → 白 → 黒 の入れ子
+        assert_eq!(module(0, 0), light);
+        assert_eq!(module(4, 4), dark);
+        assert_eq!(module(5, 5), light);
+        assert_eq!(module(7, 7), dark);
+        // 右上と左下にもファインダパターンがある
+        assert_eq!(module(36, 4), dark);
+        assert_eq!(module(4, 36), dark);
+        assert_eq!(module(40, 40), light);
+    }
+
+    #[test]
+    fn test_render_qr_rejects_too_long_content() {
+        assert!(render_qr(&qr(&"a".repeat(MAX_QR_BYTES + 1), 4), 200, 1000).is_err());
+        // 小さすぎても 1 モジュール 1 px で描く
+        let img = render_qr(&qr("LGTM", 0), 5, 100).unwrap();
+        assert_eq!(img.dimensions(), (21, 21));
+    }
+
+    #[test]
+    fn test_render_qr_fits_in_max_side() {
+        // 21 + 4 x 2 = 29 モジュール。300 px なら 1 モジュール 10 px だが、置ける場所が 100 px なら 3 px に抑える
+        let img = render_qr(&qr("LGTM", 4), 300, 100).unwrap();
+        assert_eq!(img.dimensions(), (87, 87));
+        // 1 モジュール 1 px でもはみ出すなら、端の切れた読めないコードを描かずにエラーにする
+        let big = qr(&"a".repeat(MAX_QR_BYTES), 16);
+        assert!(matches!(render_qr(&big, 50, 100), Err(InfrastructureError::DomainErrorWrapper(DomainError::InvalidInput(_)))));
+        assert!(matches!(render_qr(&qr("LGTM", 4), 10, 28), Err(InfrastructureError::DomainErrorWrapper(_))));
+        assert_eq!(render_qr(&qr("LGTM", 4), 10, 29).unwrap().dimensions(), (29, 29));
+    }
+}
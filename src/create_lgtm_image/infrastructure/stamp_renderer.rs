@@ -0,0 +1,307 @@
+use super::fonts::FontSet;
+use super::text_renderer::{draw_glyph, glyph_advance};
+use crate::domain::stamp::{StampLayout, StampOverlay, StampShape};
+use image::{Rgba, RgbaImage};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use rusttype::Scale;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+const RING_WIDTH_RATIO: f32 = 0.055; // 外枠の太さ (判子の大きさに対する比率)
+const MAX_ARC_ANGLE: f32 = PI * 0.8; // 弧に沿わせる文字列が占める角度の上限
+
+// 一辺 size px の判子を透明な背景に描く。傾ける場合は角が切れないよう少し大きな画像になる
+pub fn render_stamp(stamp: &StampOverlay, fonts: &FontSet, size: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(size, size);
+    let color = Rgba([stamp.color.r, stamp.color.g, stamp.color.b, 255]);
+    let s = size as f32;
+    let center = s / 2.0;
+    let ring_width = (s * RING_WIDTH_RATIO).max(1.0);
+    let outer = center - 1.0;
+    let inner = outer - ring_width;
+    draw_ring(&mut img, stamp.shape, outer, ring_width, color);
+
+    // 文字は枠の内側に少し余白を空けて置く
+    let content_radius = inner - s * 0.03;
+    match stamp.layout {
+        StampLayout::Arc => {
+            draw_on_arc(&mut img, fonts, &stamp.text, center, content_radius, s * 0.2, true, color);
+            if let Some(name) = &stamp.name {
+                draw_on_arc(&mut img, fonts, name, center, content_radius, s * 0.16, false, color);
+            }
+            if let Some(date) = &stamp.date {
+                // 中央の帯を 2 本の線で区切る (データ印)
+                let band = s * 0.13;
+                for y in [center - band, center + band] {
+                    let half_chord = (inner * inner - (y - center) * (y - center)).max(0.0).sqrt();
+                    fill_rect(&mut img, (center - half_chord, y - ring_width * 0.3), (center + half_chord, y + ring_width * 0.3), color);
+                }
+                let chord = 2.0 * (inner * inner - band * band).max(0.0).sqrt();
+                draw_line(&mut img, fonts, date, (center, center), chord * 0.85, band * 1.4, color);
+            }
+        }
+        StampLayout::Vertical => {
+            // date を下の弧に置く場合は、その分だけ列を上に詰める
+            let (column_height, column_center) = match stamp.date {
+                Some(_) => (content_radius * 1.3, center - s * 0.07),
+                None => (content_radius * 1.6, center),
+            };
+            match &stamp.name {
+                Some(name) => {
+                    // 縦書きは右から左へ読むので、name は text の左の列
+                    draw_column(&mut img, fonts, &stamp.text, (center + s * 0.1, column_center), column_height, s * 0.4, color);
+                    draw_column(&mut img, fonts, name, (center - s * 0.18, column_center), column_height * 0.8, s * 0.2, color);
+                }
+                None => draw_column(&mut img, fonts, &stamp.text, (center, column_center), column_height, s * 0.55, color),
+            }
+            if let Some(date) = &stamp.date {
+                draw_on_arc(&mut img, fonts, date, center, content_radius, s * 0.11, false, color);
+            }
+        }
+    }
+
+    distress(&mut img, stamp.distress.clamp(0.0, 1.0), seed(&stamp.text));
+    let opacity = stamp.color.a as f32 / 255.0;
+    for pixel in img.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+
+    if stamp.angle.abs() < f32::EPSILON {
+        return img;
+    }
+    // 回しても四隅が切れないよう √2 倍の余白を取ってから回す
+    let padded_size = (s * std::f32::consts::SQRT_2).ceil() as u32;
+    let mut padded = RgbaImage::new(padded_size, padded_size);
+    let offset = ((padded_size - size) / 2) as i64;
+    image::imageops::overlay(&mut padded, &img, offset, offset);
+    rotate_about_center(&padded, stamp.angle.to_radians(), Interpolation::Bilinear, Rgba([0, 0, 0, 0]))
+}
+
+// 外枠。ピクセルの中心から枠の外周までの距離で覆われ具合を決める
+fn draw_ring(img: &mut RgbaImage, shape: StampShape, outer: f32, ring_width: f32, color: Rgba<u8>) {
+    let center = img.width() as f32 / 2.0;
+    let corner = outer * 0.35;
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (dx, dy) = ((x as f32 + 0.5 - center).abs(), (y as f32 + 0.5 - center).abs());
+        // 外周までの距離 (内側が負)
+        let distance = match shape {
+            StampShape::Circle => (dx * dx + dy * dy).sqrt() - outer,
+            StampShape::RoundedSquare => {
+                let (qx, qy) = (dx - (outer - corner), dy - (outer - corner));
+                let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+                outside + qx.max(qy).min(0.0) - corner
+            }
+        };
+        let coverage = (0.5 - distance).clamp(0.0, 1.0) * (distance + ring_width + 0.5).clamp(0.0, 1.0);
+        if coverage > 0.0 {
+            *pixel = Rgba([color[0], color[1], color[2], (coverage * 255.0).round() as u8]);
+        }
+    }
+}
+
+fn fill_rect(img: &mut RgbaImage, (x0, y0): (f32, f32), (x1, y1): (f32, f32), color: Rgba<u8>) {
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    for y in y0.max(0.0).round() as u32..y1.min(height).round() as u32 {
+        for x in x0.max(0.0).round() as u32..x1.min(width).round() as u32 {
+            img.put_pixel(x, y, color);
+        }
+    }
+}
+
+fn line_scale(fonts: &FontSet, text: &str, scale: f32, max_width: f32) -> (Scale, f32) {
+    let width: f32 = text.chars().map(|c| glyph_advance(fonts.for_char(c), c, Scale::uniform(scale))).sum();
+    if width > max_width && width > 0.0 {
+        (Scale::uniform(scale * max_width / width), max_width)
+    } else {
+        (Scale::uniform(scale), width)
+    }
+}
+
+// グリフの見た目の上下中央がその位置に来るベースラインまでのずれ
+fn baseline_offset(fonts: &FontSet, scale: Scale) -> f32 {
+    let v_metrics = fonts.primary.v_metrics(scale);
+    (v_metrics.ascent + v_metrics.descent) / 2.0
+}
+
+// center を中心に横書き 1 行
+fn draw_line(img: &mut RgbaImage, fonts: &FontSet, text: &str, center: (f32, f32), max_width: f32, scale: f32, color: Rgba<u8>) {
+    let (scale, width) = line_scale(fonts, text, scale, max_width);
+    let baseline = center.1 + baseline_offset(fonts, scale);
+    let mut x = center.0 - width / 2.0;
+    for c in text.chars() {
+        let font = fonts.for_char(c);
+        let advance = glyph_advance(font, c, scale);
+        draw_glyph(img, font, c, scale, (x + advance / 2.0, baseline), 0.0, color);
+        x += advance;
+    }
+}
+
+// center を中心に 1 文字ずつ縦に積む
+fn draw_column(img: &mut RgbaImage, fonts: &FontSet, text: &str, center: (f32, f32), height: f32, max_scale: f32, color: Rgba<u8>) {
+    let count = text.chars().count();
+    if count == 0 {
+        return;
+    }
+    let cell = height / count as f32;
+    let scale = Scale::uniform((cell * 0.95).min(max_scale));
+    let offset = baseline_offset(fonts, scale);
+    let top = center.1 - cell * count as f32 / 2.0;
+    for (i, c) in text.chars().enumerate() {
+        let baseline = top + cell * (i as f32 + 0.5) + offset;
+        draw_glyph(img, fonts.for_char(c), c, scale, (center.0, baseline), 0.0, color);
+    }
+}
+
+// 円の内側に沿わせる。upper なら上の弧で文字の頭を外に、そうでなければ下の弧で頭を中心に向ける
+#[allow(clippy::too_many_arguments)]
+fn draw_on_arc(img: &mut RgbaImage, fonts: &FontSet, text: &str, center: f32, radius: f32, scale: f32, upper: bool, color: Rgba<u8>) {
+    let mut scale = Scale::uniform(scale);
+    let v_metrics = fonts.primary.v_metrics(scale);
+    // 上の弧は文字の頭、下の弧は文字の足が枠の内側に触れる高さにベースラインを置く
+    let mut baseline_radius = if upper { radius - v_metrics.ascent } else { radius + v_metrics.descent };
+    let mut advances: Vec<f32> = text.chars().map(|c| glyph_advance(fonts.for_char(c), c, scale)).collect();
+    let total: f32 = advances.iter().sum();
+    if baseline_radius <= 0.0 || total <= 0.0 {
+        return;
+    }
+    if total / baseline_radius > MAX_ARC_ANGLE {
+        let shrink = MAX_ARC_ANGLE * baseline_radius / total;
+        scale = Scale::uniform(scale.x * shrink);
+        let v_metrics = fonts.primary.v_metrics(scale);
+        baseline_radius = if upper { radius - v_metrics.ascent } else { radius + v_metrics.descent };
+        advances.iter_mut().for_each(|a| *a *= shrink);
+    }
+    let total: f32 = advances.iter().sum();
+
+    let mut distance = -total / 2.0;
+    for (c, advance) in text.chars().zip(advances) {
+        let offset = (distance + advance / 2.0) / baseline_radius;
+        // 上の弧は左から右へ時計回り、下の弧は左から右へ反時計回り
+        let (theta, rotation) = if upper {
+            (-FRAC_PI_2 + offset, offset)
+        } else {
+            (FRAC_PI_2 - offset, -offset)
+        };
+        let anchor = (center + baseline_radius * theta.cos(), center + baseline_radius * theta.sin());
+        draw_glyph(img, fonts.for_char(c), c, scale, anchor, rotation, color);
+        distance += advance;
+    }
+}
+
+fn seed(text: &str) -> u32 {
+    // FNV-1a。同じ文字列なら毎回同じかすれ方になる
+    text.bytes().fold(0x811c9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x01000193))
+}
+
+fn hash(x: u32, y: u32, seed: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x27d4eb2d) ^ y.wrapping_mul(0x165667b1) ^ seed;
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a2d39);
+    h ^= h >> 15;
+    (h & 0xffff) as f32 / 65535.0
+}
+
+// 粗い格子のなめらかなノイズ (インクのムラ)
+fn value_noise(x: f32, y: f32, seed: u32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (sx, sy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let (ix, iy) = (x0 as u32, y0 as u32);
+    let top = hash(ix, iy, seed) * (1.0 - sx) + hash(ix + 1, iy, seed) * sx;
+    let bottom = hash(ix, iy + 1, seed) * (1.0 - sx) + hash(ix + 1, iy + 1, seed) * sx;
+    top * (1.0 - sy) + bottom * sy
+}
+
+// 朱肉のかすれ。インクのムラで全体を薄くし、ところどころ点状に抜く
+fn distress(img: &mut RgbaImage, amount: f32, seed: u32) {
+    if amount <= 0.0 {
+        return;
+    }
+    let cell = (img.width() as f32 / 12.0).max(1.0);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        if pixel[3] == 0 {
+            continue;
+        }
+        let unevenness = value_noise(x as f32 / cell, y as f32 / cell, seed);
+        let speck = hash(x, y, seed.rotate_left(7)) < amount * 0.2 * (1.0 - unevenness);
+        let factor = if speck { 0.0 } else { 1.0 - amount * 0.5 * (1.0 - unevenness) };
+        pixel[3] = (pixel[3] as f32 * factor).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::color::Color;
+    use crate::domain::position::Position;
+    use rusttype::Font;
+
+    fn stamp(layout: StampLayout, distress: f32) -> StampOverlay {
+        StampOverlay {
+            text: "LGTM".to_string(),
+            name: Some("SATO".to_string()),
+            date: Some("2026.10.18".to_string()),
+            shape: StampShape::Circle,
+            layout,
+            color: Color::new(208, 49, 45, 255),
+            position: Position::BottomRight,
+            scale: 0.3,
+            margin: 0,
+            distress,
+            angle: 0.0,
+        }
+    }
+
+    fn with_fonts<T>(f: impl FnOnce(&FontSet) -> T) -> T {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        f(&FontSet { primary: &font, fallback: None })
+    }
+
+    fn ink(img: &RgbaImage) -> usize {
+        img.pixels().filter(|p| p[3] > 0).count()
+    }
+
+    #[test]
+    fn test_render_stamp_draws_ring_and_text_inside_circle() {
+        for layout in [StampLayout::Arc, StampLayout::Vertical] {
+            let img = with_fonts(|fonts| render_stamp(&stamp(layout, 0.0), fonts, 200));
+            assert_eq!(img.dimensions(), (200, 200));
+            // 四隅は透明、枠の上は朱色
+            assert_eq!(img.get_pixel(2, 2)[3], 0);
+            assert_eq!(img.get_pixel(100, 3).0, [208, 49, 45, 255]);
+            // 枠の内側にも文字が描かれている
+            let inside = img
+                .enumerate_pixels()
+                .filter(|(x, y, p)| p[3] > 0 && ((*x as f32 - 100.0).powi(2) + (*y as f32 - 100.0).powi(2)).sqrt() < 80.0)
+                .count();
+            assert!(inside > 500, "{:?}: {}", layout, inside);
+        }
+    }
+
+    #[test]
+    fn test_render_stamp_rounded_square_fills_corners_more_than_circle() {
+        let circle = with_fonts(|fonts| render_stamp(&stamp(StampLayout::Arc, 0.0), fonts, 200));
+        let square = with_fonts(|fonts| {
+            render_stamp(&StampOverlay { shape: StampShape::RoundedSquare, ..stamp(StampLayout::Arc, 0.0) }, fonts, 200)
+        });
+        assert_eq!(circle.get_pixel(16, 16)[3], 0);
+        assert!(square.get_pixel(12, 12)[3] > 0);
+    }
+
+    #[test]
+    fn test_render_stamp_distress_is_deterministic_and_removes_ink() {
+        let clean = with_fonts(|fonts| render_stamp(&stamp(StampLayout::Arc, 0.0), fonts, 200));
+        let worn = with_fonts(|fonts| render_stamp(&stamp(StampLayout::Arc, 1.0), fonts, 200));
+        let worn_again = with_fonts(|fonts| render_stamp(&stamp(StampLayout::Arc, 1.0), fonts, 200));
+        assert!(ink(&worn) < ink(&clean));
+        assert_eq!(worn, worn_again);
+    }
+
+    #[test]
+    fn test_render_stamp_rotation_pads_canvas() {
+        let img = with_fonts(|fonts| render_stamp(&StampOverlay { angle: -10.0, ..stamp(StampLayout::Arc, 0.0) }, fonts, 100));
+        assert_eq!(img.dimensions(), (142, 142));
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+    }
+}
@@ -0,0 +1,56 @@
+// 同梱のステッカー (stickers/ 以下の PNG)。rusttype ではカラー絵文字を描けないので画像で持つ
+// ショートコード (":thumbsup:" の中身) で引く
+const STICKERS: [(&str, &[u8]); 10] = [
+    ("thumbsup", include_bytes!("../../../stickers/thumbsup.png")),
+    ("tada", include_bytes!("../../../stickers/tada.png")),
+    ("white_check_mark", include_bytes!("../../../stickers/white_check_mark.png")),
+    ("heart", include_bytes!("../../../stickers/heart.png")),
+    ("star", include_bytes!("../../../stickers/star.png")),
+    ("zap", include_bytes!("../../../stickers/zap.png")),
+    ("smile", include_bytes!("../../../stickers/smile.png")),
+    ("100", include_bytes!("../../../stickers/100.png")),
+    ("ok", include_bytes!("../../../stickers/ok.png")),
+    ("eyes", include_bytes!("../../../stickers/eyes.png")),
+];
+
+// GitHub / Slack でよく使う別名
+const ALIASES: [(&str, &str); 4] = [
+    ("+1", "thumbsup"),
+    ("check", "white_check_mark"),
+    ("smiley", "smile"),
+    ("ok_button", "ok"),
+];
+
+// "thumbsup" でも ":thumbsup:" でも引ける。知らない名前は None
+pub fn sticker_png(shortcode: &str) -> Option<&'static [u8]> {
+    let name = shortcode.strip_prefix(':').and_then(|s| s.strip_suffix(':')).unwrap_or(shortcode);
+    let name = ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name, |(_, target)| *target);
+    STICKERS.iter().find(|(sticker, _)| *sticker == name).map(|(_, data)| *data)
+}
+
+// 使えるステッカーの名前 (エラーメッセージ用。別名は含まない)
+pub fn sticker_names() -> Vec<&'static str> {
+    STICKERS.iter().map(|(name, _)| *name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_sticker_is_a_decodable_square_png() {
+        for name in sticker_names() {
+            let img = image::load_from_memory(sticker_png(name).unwrap()).unwrap();
+            assert_eq!(img.width(), img.height(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_sticker_png_accepts_colons_and_aliases() {
+        assert_eq!(sticker_png(":+1:"), sticker_png("thumbsup"));
+        assert_eq!(sticker_png("check"), sticker_png(":white_check_mark:"));
+        assert!(sticker_png("thumbsup").is_some());
+        assert!(sticker_png("party_parrot").is_none());
+        assert!(sticker_png("::").is_none());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::domain::text_effect::TextEffect;
+use image::imageops;
+use image::{GrayImage, Luma, Rgba, RgbaImage};
+use imageproc::distance_transform::{euclidean_squared_distance_transform, Norm};
+use imageproc::morphology::erode;
+
+const MAX_GLOW_SIGMA: f32 = 50.0;
+const MAX_EXTRUDE_DEPTH: u32 = 64; // 1 px ごとに重ねるので深すぎると遅い
+const GHOST_OPACITY: f32 = 0.8; // glitch のずれた影の濃さ
+const MAX_OUTLINE_WIDTH: f32 = 32.0;
+
+// 透明な layer に描いたテキストに効果をかけて img に重ねる
+// layer は img と同じ大きさで、文字のないところは透明。text_size は描いた文字の大きさ (px)
+pub fn draw_text_effect(img: &mut RgbaImage, layer: &RgbaImage, effect: &TextEffect, text_size: f32) {
+    match *effect {
+        TextEffect::Neon { ref color, stroke, glow } => neon(img, layer, Rgba([color.r, color.g, color.b, color.a]), stroke, glow),
+        TextEffect::Extrude { depth, angle, shade } => extrude(img, layer, depth.min(MAX_EXTRUDE_DEPTH), angle, shade.clamp(0.0, 1.0)),
+        TextEffect::Glitch { offset, slices, seed } => glitch(img, layer, offset.min(layer.width()), slices.max(1), seed),
+        TextEffect::Outline { ref color, width } => outline(img, layer, Rgba([color.r, color.g, color.b, color.a]), width * text_size),
+        // 色は描くときに 1 文字ずつ変えてあるので重ねるだけ
+        TextEffect::Rainbow { .. } => imageops::overlay(img, layer, 0, 0),
+    }
+}
+
+fn alpha_mask(layer: &RgbaImage) -> GrayImage {
+    GrayImage::from_fn(layer.width(), layer.height(), |x, y| Luma([layer.get_pixel(x, y)[3]]))
+}
+
+// 半透明なふちを切り捨てた文字の形 (0 か 255)
+fn solid_mask(alpha: &GrayImage) -> GrayImage {
+    GrayImage::from_fn(alpha.width(), alpha.height(), |x, y| Luma([if alpha.get_pixel(x, y)[0] >= 128 { 255 } else { 0 }]))
+}
+
+// layer の文字の形を color で塗り、opacity だけ薄くしたコピー
+fn tinted(layer: &RgbaImage, color: [u8; 3], opacity: f32) -> RgbaImage {
+    let mut tinted = layer.clone();
+    for pixel in tinted.pixels_mut() {
+        pixel.0[..3].copy_from_slice(&color);
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+    tinted
+}
+
+fn neon(img: &mut RgbaImage, layer: &RgbaImage, color: Rgba<u8>, stroke: u32, glow: f32) {
+    let alpha = alpha_mask(layer);
+    let glow_opacity = color[3] as f32 / 255.0;
+
+    // 外側の光。文字の形をぼかして光の色で 2 回重ね、にじみを濃くする
+    let sigma = glow.clamp(0.0, MAX_GLOW_SIGMA);
+    if sigma > 0.0 {
+        let blurred = imageops::blur(&alpha, sigma);
+        let halo = RgbaImage::from_fn(layer.width(), layer.height(), |x, y| {
+            let a = (blurred.get_pixel(x, y)[0] as f32 * glow_opacity).round() as u8;
+            Rgba([color[0], color[1], color[2], a])
+        });
+        imageops::overlay(img, &halo, 0, 0);
+        imageops::overlay(img, &halo, 0, 0);
+    }
+
+    // 内側の縁。stroke px 削って残った芯は文字の色、削られた縁は光の色にする
+    let core = erode(&solid_mask(&alpha), Norm::LInf, stroke.min(u8::MAX as u32) as u8);
+    let mut lit = layer.clone();
+    for (x, y, pixel) in lit.enumerate_pixels_mut() {
+        if core.get_pixel(x, y)[0] == 0 {
+            pixel.0[..3].copy_from_slice(&color.0[..3]);
+        }
+    }
+    imageops::overlay(img, &lit, 0, 0);
+}
+
+fn extrude(img: &mut RgbaImage, layer: &RgbaImage, depth: u32, angle: f32, shade: f32) {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let mut side = layer.clone();
+    for pixel in side.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f32 * (1.0 - shade)).round() as u8;
+        }
+    }
+    // 奥から順に 1 px ずつずらして重ね、最後に表の文字を重ねる
+    for step in (1..=depth).rev() {
+        let (dx, dy) = ((cos * step as f32).round() as i64, (sin * step as f32).round() as i64);
+        imageops::overlay(img, &side, dx, dy);
+    }
+    imageops::overlay(img, layer, 0, 0);
+}
+
+// 文字の形を width px 太らせて color で塗り、その上に文字を重ねる
+// 文字からの距離で塗るので角は丸くなり、外周は 1 px でなめらかに消える
+fn outline(img: &mut RgbaImage, layer: &RgbaImage, color: Rgba<u8>, width: f32) {
+    let width = width.clamp(1.0, MAX_OUTLINE_WIDTH);
+    let distance = euclidean_squared_distance_transform(&solid_mask(&alpha_mask(layer)));
+    let opacity = color[3] as f32 / 255.0;
+    let stroke = RgbaImage::from_fn(layer.width(), layer.height(), |x, y| {
+        let coverage = (width + 0.5 - distance.get_pixel(x, y)[0].sqrt() as f32).clamp(0.0, 1.0);
+        Rgba([color[0], color[1], color[2], (coverage * opacity * 255.0).round() as u8])
+    });
+    imageops::overlay(img, &stroke, 0, 0);
+    imageops::overlay(img, layer, 0, 0);
+}
+
+// 再現できる疑似乱数 (xorshift)
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    // -max..=max の整数
+    fn shift(&mut self, max: u32) -> i64 {
+        (self.next() % (2 * max + 1)) as i64 - max as i64
+    }
+}
+
+fn glitch(img: &mut RgbaImage, layer: &RgbaImage, offset: u32, slices: u32, seed: u32) {
+    let (width, height) = layer.dimensions();
+    // 0 だと xorshift が 0 のまま進まない
+    let mut rng = Rng(seed.wrapping_mul(0x9e3779b9) | 1);
+
+    // 横の帯に切って、帯ごとに左右にずらす (ずらさない帯も残す)
+    let mut sliced = RgbaImage::new(width, height);
+    let band_height = (height / slices).max(1);
+    let mut top = 0;
+    while top < height {
+        let band = (band_height / 2 + rng.next() % band_height.max(1)).max(1).min(height - top);
+        let dx = if rng.next().is_multiple_of(3) { 0 } else { rng.shift(offset * 2) };
+        let strip = imageops::crop_imm(layer, 0, top, width, band).to_image();
+        imageops::replace(&mut sliced, &strip, dx, top as i64);
+        top += band;
+    }
+
+    // 赤と青緑の影を左右にずらして置き、その上に文字を重ねる
+    imageops::overlay(img, &tinted(&sliced, [255, 0, 64], GHOST_OPACITY), -(offset as i64), 0);
+    imageops::overlay(img, &tinted(&sliced, [0, 224, 255], GHOST_OPACITY), offset as i64, 0);
+    imageops::overlay(img, &sliced, 0, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::color::Color;
+
+    // 黒い背景と、真ん中に白い四角を描いたレイヤー
+    fn fixture() -> (RgbaImage, RgbaImage) {
+        let img = RgbaImage::from_pixel(60, 60, Rgba([0, 0, 0, 255]));
+        let mut layer = RgbaImage::from_pixel(60, 60, Rgba([255, 255, 255, 0]));
+        for y in 20..40 {
+            for x in 20..40 {
+                layer.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+            }
+        }
+        (img, layer)
+    }
+
+    #[test]
+    fn test_neon_colors_rim_and_glows_outside() {
+        let (mut img, layer) = fixture();
+        draw_text_effect(&mut img, &layer, &TextEffect::Neon { color: Color::new(255, 0, 255, 255), stroke: 3, glow: 4.0 }, 20.0);
+        assert_eq!(img.get_pixel(30, 30).0, [255, 255, 255, 255]); // 芯は文字の色
+        assert_eq!(img.get_pixel(21, 30).0, [255, 0, 255, 255]); // 内側の縁は光の色
+        let halo = img.get_pixel(16, 30);
+        assert!(halo[0] > 30 && halo[1] == 0 && halo[0] == halo[2], "{:?}", halo);
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_extrude_draws_darker_side_toward_angle() {
+        let (mut img, layer) = fixture();
+        draw_text_effect(&mut img, &layer, &TextEffect::Extrude { depth: 6, angle: 45.0, shade: 0.5 }, 20.0);
+        assert_eq!(img.get_pixel(30, 30).0, [255, 255, 255, 255]);
+        assert_eq!(img.get_pixel(43, 43).0, [128, 128, 128, 255]); // 右下に押し出した側面
+        assert_eq!(img.get_pixel(17, 17).0, [0, 0, 0, 255]); // 反対側には出ない
+    }
+
+    #[test]
+    fn test_glitch_adds_colored_ghosts_and_is_deterministic() {
+        let (background, layer) = fixture();
+        let effect = TextEffect::Glitch { offset: 4, slices: 4, seed: 7 };
+        let mut first = background.clone();
+        draw_text_effect(&mut first, &layer, &effect, 20.0);
+        let mut second = background.clone();
+        draw_text_effect(&mut second, &layer, &effect, 20.0);
+        assert_eq!(first, second);
+        // 白い四角の外に赤っぽい影と青緑っぽい影が出る
+        assert!(first.pixels().any(|p| p[0] > 150 && p[1] < 50));
+        assert!(first.pixels().any(|p| p[0] < 50 && p[2] > 150));
+
+        let mut other_seed = background;
+        draw_text_effect(&mut other_seed, &layer, &TextEffect::Glitch { offset: 4, slices: 4, seed: 8 }, 20.0);
+        assert_ne!(first, other_seed);
+    }
+
+    #[test]
+    fn test_outline_width_follows_text_size() {
+        let (mut img, layer) = fixture();
+        let effect = TextEffect::Outline { color: Color::new(255, 0, 0, 255), width: 0.25 };
+        draw_text_effect(&mut img, &layer, &effect, 20.0); // ふちは 5 px
+        assert_eq!(img.get_pixel(30, 30).0, [255, 255, 255, 255]);
+        assert_eq!(img.get_pixel(17, 30).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(30, 43).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(10, 30).0, [0, 0, 0, 255]);
+    }
+}
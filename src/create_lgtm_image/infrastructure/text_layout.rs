@@ -0,0 +1,480 @@
+use super::sticker_library::sticker_png;
+use crate::domain::position::Position as DomainPosition;
+use rusttype::{Font, Scale, point};
+
+// 画像上の矩形 (x, y, width, height)
+pub type Area = (i32, i32, u32, u32);
+
+// テキストの大きさと描画位置を決めた結果
+// (x, y) は draw_text_mut に渡す座標で、文字はおおよそ (x, y) から width x height の範囲に描かれる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    pub scale: Scale,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// 1 文字ずつ置くレイアウト (曲線・縦書きなど) の 1 文字分
+// anchor はその文字のベースラインの中央、angle はラジアン (時計回り)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedGlyph {
+    pub c: char,
+    pub anchor: (f32, f32),
+    pub angle: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphLayout {
+    pub scale: Scale,
+    pub glyphs: Vec<PlacedGlyph>,
+}
+
+impl GlyphLayout {
+    // 文字が描かれるおおよその範囲。どちらに回っていても収まるよう anchor から文字の大きさ分広げる
+    pub fn bounds(&self) -> Area {
+        let reach = self.scale.y;
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for glyph in &self.glyphs {
+            x0 = x0.min(glyph.anchor.0 - reach);
+            y0 = y0.min(glyph.anchor.1 - reach);
+            x1 = x1.max(glyph.anchor.0 + reach);
+            y1 = y1.max(glyph.anchor.1 + reach);
+        }
+        if self.glyphs.is_empty() {
+            return (0, 0, 0, 0);
+        }
+        (x0.floor() as i32, y0.floor() as i32, (x1 - x0).ceil() as u32, (y1 - y0).ceil() as u32)
+    }
+}
+
+// テキストを文字の部分とインラインのステッカー (":thumbsup:" など) に分けたもの
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextRun<'a> {
+    Text(&'a str),
+    Sticker(&'static [u8]), // 同梱ステッカーの PNG
+}
+
+// 知っているショートコードだけをステッカーにする。知らないものは文字のまま描く
+pub fn split_runs(text: &str) -> Vec<TextRun<'_>> {
+    let mut runs = Vec::new();
+    let mut text_start = 0;
+    let mut search_from = 0;
+    while let Some(open) = text[search_from..].find(':').map(|i| search_from + i) {
+        let Some(close) = text[open + 1..].find(':').map(|i| open + 1 + i) else { break };
+        match sticker_png(&text[open + 1..close]) {
+            Some(png) => {
+                if text_start < open {
+                    runs.push(TextRun::Text(&text[text_start..open]));
+                }
+                runs.push(TextRun::Sticker(png));
+                text_start = close + 1;
+                search_from = close + 1;
+            }
+            // 閉じの ":" が次のショートコードの開きかもしれない
+            None => search_from = close,
+        }
+    }
+    if text_start < text.len() {
+        runs.push(TextRun::Text(&text[text_start..]));
+    }
+    runs
+}
+
+// インラインのステッカーの一辺。ベースラインに乗せて、行の上端まで届く大きさにする
+pub fn sticker_size(font: &Font, scale: Scale) -> f32 {
+    font.v_metrics(scale).ascent.round().max(1.0)
+}
+
+// 送り幅 (次の文字を置く位置) で測った文字列の幅
+pub fn advance_width(font: &Font, text: &str, scale: Scale) -> f32 {
+    font.layout(text, scale, point(0.0, 0.0))
+        .last()
+        .map_or(0.0, |g| g.position().x + g.unpositioned().h_metrics().advance_width)
+}
+
+fn measure_width(font: &Font, text: &str, scale: Scale) -> f32 {
+    match split_runs(text)[..] {
+        // 文字だけなら最後のグリフの右端まで
+        [] | [TextRun::Text(_)] => font.layout(text, scale, point(0.0, 0.0))
+            .filter_map(|g| g.pixel_bounding_box())
+            .map(|bb| bb.max.x as f32)
+            .last()
+            .unwrap_or(0.0),
+        ref runs => runs
+            .iter()
+            .map(|run| match run {
+                TextRun::Text(text) => advance_width(font, text, scale),
+                TextRun::Sticker(_) => sticker_size(font, scale),
+            })
+            .sum(),
+    }
+}
+
+// 大きさ (width, height) のものを area の中で position に従って置いたときの左上の座標
+// テキスト以外のレイヤー (ロゴなど) も同じ配置にする。はみ出す場合は左上に寄せる
+pub fn place(position: &DomainPosition, (width, height): (f32, f32), (area_x, area_y, area_width, area_height): Area) -> (i32, i32) {
+    let (area_width, area_height) = (area_width as f32, area_height as f32);
+    let (mut x_pos, mut y_pos) = match position {
+        DomainPosition::TopLeft => (0.0, 0.0),
+        DomainPosition::TopCenter => ((area_width - width) / 2.0, 0.0),
+        DomainPosition::TopRight => (area_width - width, 0.0),
+        DomainPosition::CenterLeft => (0.0, (area_height - height) / 2.0),
+        DomainPosition::Center | DomainPosition::Custom { .. } => ((area_width - width) / 2.0, (area_height - height) / 2.0), // Customは一旦Centerと同じ扱い
+        DomainPosition::CenterRight => (area_width - width, (area_height - height) / 2.0),
+        DomainPosition::BottomLeft => (0.0, area_height - height),
+        DomainPosition::BottomCenter => ((area_width - width) / 2.0, area_height - height),
+        DomainPosition::BottomRight => (area_width - width, area_height - height),
+    };
+    if x_pos < 0.0 { x_pos = 0.0; }
+    if y_pos < 0.0 { y_pos = 0.0; }
+    (area_x + x_pos as i32, area_y + y_pos as i32)
+}
+
+// 画像の大きさに合わせてテキストのスケールを決め、position に従って配置する
+pub fn layout_text(font: &Font, text: &str, position: &DomainPosition, (img_width, img_height): (u32, u32)) -> TextLayout {
+    layout_text_in(font, text, position, (0, 0, img_width, img_height), default_scale(text, img_height))
+}
+
+// 高さ height の画像に描くときの文字の大きさ。文字数が多いほど小さくする
+pub fn default_scale(text: &str, height: u32) -> f32 {
+    let height = height as f32;
+    let count = glyph_count(text);
+    if count > 20 {
+        height / (count as f32 / 2.5)
+    } else if count > 10 {
+        height / (count as f32 / 1.8)
+    } else {
+        height / 5.0
+    }
+}
+
+// 実際に描く文字の数。":thumbsup:" のようなステッカーは 1 文字と数える
+fn glyph_count(text: &str) -> usize {
+    split_runs(text)
+        .iter()
+        .map(|run| match run {
+            TextRun::Text(text) => text.chars().count(),
+            TextRun::Sticker(_) => 1,
+        })
+        .sum()
+}
+
+// area (x, y, width, height) の中に base_scale の大きさで配置する。幅に収まらなければ縮める
+pub fn layout_text_in(
+    font: &Font,
+    text: &str,
+    position: &DomainPosition,
+    area: Area,
+    base_scale: f32,
+) -> TextLayout {
+    let area_width = area.2 as f32;
+
+    let mut current_scale_val = base_scale;
+    if current_scale_val < 1.0 { current_scale_val = 1.0; }
+    let mut scale = Scale::uniform(current_scale_val);
+    let mut text_width = measure_width(font, text, scale);
+
+    // 幅が領域の 90% を超える場合は収まるまで縮める
+    let max_text_width_ratio = 0.90;
+    if text_width > area_width * max_text_width_ratio && text_width > 0.0 {
+        let new_scale_factor = (area_width * max_text_width_ratio) / text_width;
+        current_scale_val *= new_scale_factor;
+        if current_scale_val < 1.0 { current_scale_val = 1.0; }
+        scale = Scale::uniform(current_scale_val);
+        text_width = measure_width(font, text, scale);
+    }
+
+    let v_metrics = font.v_metrics(scale);
+    let text_height = v_metrics.ascent - v_metrics.descent;
+
+    // draw_text_mut は y を行の上端として扱い、ascent は内部で足すのでここでは足さない
+    let (x, y) = place(position, (text_width, text_height), area);
+
+    TextLayout {
+        scale,
+        x,
+        y,
+        width: text_width.ceil() as u32,
+        height: text_height.ceil() as u32,
+    }
+}
+
+const MIN_WRAP_SCALE: f32 = 8.0; // 折り返しても収まらないときに縮める下限
+
+// 単語の区切りで max_width に収まるよう行に分ける。改行 ("\n") はそのまま行の区切りにする
+// 1 単語だけで幅を超える行はそのまま残す
+fn wrap_lines(font: &Font, text: &str, scale: Scale, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if line.is_empty() || measure_width(font, &candidate, scale) <= max_width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// area の幅の 90% で折り返し、行を積んだ高さが max_height に収まるまで縮める
+// 行ごとの配置とその行の文字列を返す。行の塊は position に従って置き、各行も position の左右にそろえる
+pub fn layout_wrapped_text(
+    font: &Font,
+    text: &str,
+    position: &DomainPosition,
+    area: Area,
+    base_scale: f32,
+    max_height: f32,
+) -> Vec<(TextLayout, String)> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    let max_width = area.2 as f32 * 0.90;
+    let mut size = base_scale.max(1.0);
+    let (scale, lines, line_height) = loop {
+        let scale = Scale::uniform(size);
+        let v_metrics = font.v_metrics(scale);
+        let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+        let lines = wrap_lines(font, text, scale, max_width);
+        let fits = lines.len() as f32 * line_height <= max_height
+            && lines.iter().all(|line| measure_width(font, line, scale) <= max_width);
+        if fits || size <= MIN_WRAP_SCALE {
+            break (scale, lines, line_height);
+        }
+        size = (size * 0.9).max(MIN_WRAP_SCALE);
+    };
+
+    let v_metrics = font.v_metrics(scale);
+    let text_height = v_metrics.ascent - v_metrics.descent;
+    let widths: Vec<f32> = lines.iter().map(|line| measure_width(font, line, scale)).collect();
+    let block_width = widths.iter().cloned().fold(0.0, f32::max);
+    let block_height = (lines.len() - 1) as f32 * line_height + text_height;
+    let (left, top) = place(position, (block_width, block_height), area);
+    lines
+        .into_iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(i, (line, width))| {
+            let layout = TextLayout {
+                scale,
+                x: left + align_in_block(position, block_width, width) as i32,
+                y: top + (i as f32 * line_height) as i32,
+                width: width.ceil() as u32,
+                height: text_height.ceil() as u32,
+            };
+            (layout, line)
+        })
+        .collect()
+}
+
+// 幅 block_width の塊の中で、幅 width の行を position の左右にそろえたときの左端
+fn align_in_block(position: &DomainPosition, block_width: f32, width: f32) -> f32 {
+    match position {
+        DomainPosition::TopLeft | DomainPosition::CenterLeft | DomainPosition::BottomLeft => 0.0,
+        DomainPosition::TopRight | DomainPosition::CenterRight | DomainPosition::BottomRight => block_width - width,
+        _ => (block_width - width) / 2.0,
+    }
+}
+
+// 大きな 1 行の下に、ratio 倍の大きさの 1 行 (略語の展開など) を添える
+// 2 行をひとかたまりとして position に置き、各行も position の左右にそろえる
+// どちらかが area の幅の 90% を超えるなら、大きさの比を保ったまま両方縮める
+pub fn layout_text_with_subtitle(
+    font: &Font,
+    text: &str,
+    subtitle: &str,
+    position: &DomainPosition,
+    area: Area,
+    base_scale: f32,
+    ratio: f32,
+) -> Vec<(TextLayout, String)> {
+    let max_width = area.2 as f32 * 0.90;
+    let ratio = ratio.clamp(0.05, 1.0);
+    let mut size = base_scale.max(1.0);
+    let widest = |size: f32| measure_width(font, text, Scale::uniform(size)).max(measure_width(font, subtitle, Scale::uniform(size * ratio)));
+    if widest(size) > max_width && widest(size) > 0.0 {
+        size = (size * max_width / widest(size)).max(1.0);
+    }
+
+    let lines = [(text, Scale::uniform(size)), (subtitle, Scale::uniform((size * ratio).max(1.0)))];
+    let measured: Vec<(f32, f32)> = lines
+        .iter()
+        .map(|(line, scale)| {
+            let v_metrics = font.v_metrics(*scale);
+            (measure_width(font, line, *scale), v_metrics.ascent - v_metrics.descent)
+        })
+        .collect();
+    // 行の間は下の行の文字の高さの 2 割だけ空ける
+    let gap = measured[1].1 * 0.2;
+    let block_width = measured.iter().map(|&(width, _)| width).fold(0.0, f32::max);
+    let block_height = measured[0].1 + gap + measured[1].1;
+    let (left, top) = place(position, (block_width, block_height), area);
+    let tops = [0.0, measured[0].1 + gap];
+    lines
+        .into_iter()
+        .zip(measured)
+        .zip(tops)
+        .map(|(((line, scale), (width, height)), line_top)| {
+            let layout = TextLayout {
+                scale,
+                x: left + align_in_block(position, block_width, width) as i32,
+                y: top + line_top as i32,
+                width: width.ceil() as u32,
+                height: height.ceil() as u32,
+            };
+            (layout, line.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> Font<'static> {
+        Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap()
+    }
+
+    #[test]
+    fn test_layout_text_fits_within_image_width() {
+        let layout = layout_text(&font(), "LOOKS GOOD TO ME", &DomainPosition::Center, (200, 400));
+        assert!(layout.width as f32 <= 200.0 * 0.9 + 1.0);
+        assert!(layout.x >= 0);
+    }
+
+    #[test]
+    fn test_layout_text_positions() {
+        let font = font();
+        let left = layout_text(&font, "LGTM", &DomainPosition::TopLeft, (400, 200));
+        let center = layout_text(&font, "LGTM", &DomainPosition::Center, (400, 200));
+        let right = layout_text(&font, "LGTM", &DomainPosition::BottomRight, (400, 200));
+        assert_eq!(left.x, 0);
+        assert!(left.x < center.x && center.x < right.x);
+        assert!(left.y < center.y && center.y < right.y);
+        assert_eq!(left.scale, center.scale);
+    }
+
+    #[test]
+    fn test_layout_text_in_offsets_by_area() {
+        let font = font();
+        let layout = layout_text_in(&font, "LGTM", &DomainPosition::Center, (10, 300, 200, 60), 30.0);
+        assert_eq!(layout.scale, Scale::uniform(30.0));
+        assert!(layout.x > 10 && layout.x + layout.width as i32 <= 210);
+        assert!(layout.y >= 300 && layout.y + layout.height as i32 <= 360);
+    }
+
+    #[test]
+    fn test_layout_text_bottom_stays_inside_image() {
+        let layout = layout_text(&font(), "LGTM", &DomainPosition::BottomCenter, (400, 200));
+        assert!(layout.y + layout.height as i32 <= 200);
+    }
+
+    #[test]
+    fn test_layout_text_bounds_contain_drawn_glyphs() {
+        use image::{Rgba, RgbaImage};
+        use imageproc::drawing::draw_text_mut;
+
+        let font = font();
+        for position in [DomainPosition::TopCenter, DomainPosition::Center, DomainPosition::BottomCenter] {
+            let layout = layout_text(&font, "LGTM", &position, (400, 200));
+            let mut img = RgbaImage::from_pixel(400, 200, Rgba([0, 0, 0, 255]));
+            draw_text_mut(&mut img, Rgba([255, 255, 255, 255]), layout.x, layout.y, layout.scale, &font, "LGTM");
+            let inked: Vec<u32> = img.enumerate_pixels().filter(|(_, _, p)| p[0] > 128).map(|(_, y, _)| y).collect();
+            // 描いた文字が測った範囲に収まり、文字の上端は範囲の上端のすぐ下にある
+            // (以前は ascent の分だけ下にずれ、下寄せでは画像からはみ出していた)
+            let (top, bottom) = (*inked.iter().min().unwrap() as i32, *inked.iter().max().unwrap() as i32);
+            assert!(top >= layout.y && bottom < layout.y + layout.height as i32, "{:?}: ink {}..{} in {:?}", position, top, bottom, layout);
+            assert!(top - layout.y <= layout.height as i32 / 4, "{:?}: ink top {} in {:?}", position, top, layout);
+        }
+        // 上寄せなら文字の上端は画像の上端のすぐ下
+        let layout = layout_text(&font, "LGTM", &DomainPosition::TopCenter, (400, 200));
+        assert_eq!(layout.y, 0);
+    }
+
+    #[test]
+    fn test_default_scale_counts_stickers_as_one_glyph() {
+        // 8 文字分 ("LGTM " + ステッカー + " " + ステッカー) なので縮めない
+        assert_eq!(default_scale("LGTM :thumbsup: :tada:", 500), 100.0);
+        assert_eq!(default_scale("LGTM :thumbsup: :tada:", 500), default_scale("LGTM ab", 500));
+        // 知らないショートコードは文字のまま描くので文字数に入る
+        assert_eq!(default_scale(":not_a_sticker_name:", 500), default_scale("a".repeat(20).as_str(), 500));
+        // 日本語もバイト数ではなく文字数で数える
+        assert_eq!(default_scale("よさそう", 500), 100.0);
+    }
+
+    #[test]
+    fn test_split_runs_recognizes_known_shortcodes_only() {
+        let thumbsup = sticker_png("thumbsup").unwrap();
+        let tada = sticker_png("tada").unwrap();
+        assert_eq!(split_runs("LGTM"), vec![TextRun::Text("LGTM")]);
+        assert_eq!(
+            split_runs("LGTM :thumbsup::tada:"),
+            vec![TextRun::Text("LGTM "), TextRun::Sticker(thumbsup), TextRun::Sticker(tada)]
+        );
+        assert_eq!(split_runs("12:30 :+1:"), vec![TextRun::Text("12:30 "), TextRun::Sticker(thumbsup)]);
+        assert_eq!(split_runs(":nope: ok"), vec![TextRun::Text(":nope: ok")]);
+    }
+
+    #[test]
+    fn test_layout_text_counts_inline_stickers() {
+        let font = font();
+        let plain = layout_text_in(&font, "LGTM", &DomainPosition::Center, (0, 0, 1000, 200), 40.0);
+        let with_sticker = layout_text_in(&font, "LGTM:tada:", &DomainPosition::Center, (0, 0, 1000, 200), 40.0);
+        let size = sticker_size(&font, Scale::uniform(40.0)) as u32;
+        assert!(with_sticker.width >= plain.width + size - 2, "{} {}", plain.width, with_sticker.width);
+    }
+
+    #[test]
+    fn test_layout_wrapped_text_wraps_and_shrinks_to_fit() {
+        let font = font();
+        let area = (0, 0, 300, 300);
+        let lines = layout_wrapped_text(&font, "ONE DOES NOT SIMPLY WALK INTO MORDOR", &DomainPosition::TopCenter, area, 50.0, 100.0);
+        assert!(lines.len() > 1);
+        let joined: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).collect();
+        assert_eq!(joined.join(" "), "ONE DOES NOT SIMPLY WALK INTO MORDOR");
+        let (first, last) = (&lines[0].0, &lines[lines.len() - 1].0);
+        assert!(first.scale.y < 50.0);
+        assert!(first.y >= 0 && last.y + last.height as i32 <= 100 + 1, "{:?}", last);
+        for (layout, _) in &lines {
+            assert!(layout.width as f32 <= 270.0 + 1.0);
+            // 中央そろえ
+            assert!((layout.x + layout.width as i32 / 2 - 150).abs() <= 2, "{:?}", layout);
+        }
+
+        let bottom = layout_wrapped_text(&font, "BRACE YOURSELVES", &DomainPosition::BottomCenter, area, 40.0, 100.0);
+        let last = &bottom[bottom.len() - 1].0;
+        assert!(last.y + last.height as i32 >= 290 && last.y + last.height as i32 <= 300);
+        assert!(layout_wrapped_text(&font, "  ", &DomainPosition::TopCenter, area, 40.0, 100.0).is_empty());
+    }
+
+    #[test]
+    fn test_layout_text_with_subtitle_stacks_lines_as_a_block() {
+        let font = font();
+        let area = (0, 0, 400, 300);
+        let lines = layout_text_with_subtitle(&font, "LGTM", "Looks Good To Me", &DomainPosition::Center, area, 60.0, 0.3);
+        let [(main, main_text), (sub, sub_text)] = &lines[..] else { panic!("{:?}", lines) };
+        assert_eq!((main_text.as_str(), sub_text.as_str()), ("LGTM", "Looks Good To Me"));
+        assert_eq!(main.scale, Scale::uniform(60.0));
+        assert_eq!(sub.scale, Scale::uniform(18.0));
+        // 下の行は上の行のすぐ下、どちらも中央そろえで、塊全体が上下の中央に来る
+        assert!(sub.y >= main.y + main.height as i32 && sub.y <= main.y + main.height as i32 + 6, "{:?} {:?}", main, sub);
+        for layout in [main, sub] {
+            assert!((layout.x + layout.width as i32 / 2 - 200).abs() <= 2, "{:?}", layout);
+        }
+        assert!((main.y + sub.y + sub.height as i32 - 300).abs() <= 2);
+
+        // 長い下の行に合わせて、比を保ったまま両方縮める
+        let lines = layout_text_with_subtitle(&font, "LGTM", "Let's Get This Merged Before The Weekend", &DomainPosition::BottomLeft, area, 60.0, 0.5);
+        let (main, sub) = (&lines[0].0, &lines[1].0);
+        assert!(main.scale.y < 60.0 && (sub.scale.y / main.scale.y - 0.5).abs() < 0.01);
+        assert!(sub.width as f32 <= 360.0 + 1.0);
+        assert_eq!((main.x, sub.x), (0, 0));
+        assert!(sub.y + sub.height as i32 <= 300 && sub.y + sub.height as i32 >= 296);
+    }
+}
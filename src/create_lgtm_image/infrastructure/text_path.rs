@@ -0,0 +1,214 @@
+use super::fonts::FontSet;
+use super::text_layout::{place, Area, GlyphLayout, PlacedGlyph};
+use super::text_renderer::glyph_advance;
+use crate::domain::position::Position as DomainPosition;
+use crate::domain::text_overlay::TextPath;
+use rusttype::Scale;
+use std::f32::consts::PI;
+
+const MAX_ARC_SWEEP: f32 = PI * 1.6; // 円弧に並べる文字列が占める角度の上限
+const PATH_FILL_RATIO: f32 = 0.95; // ベジェ曲線の長さのうち文字列に使う割合
+const BEZIER_SEGMENTS: usize = 256; // 長さを測るために曲線を分割する数
+
+// 線に沿って 1 文字ずつ置く。Straight のときは None (横書きのレイアウトを使う)
+// area は線を置く範囲、base_scale は縮める前の文字の大きさ
+pub fn layout_text_on_path(
+    fonts: &FontSet,
+    text: &str,
+    path: &TextPath,
+    position: &DomainPosition,
+    area: Area,
+    base_scale: f32,
+) -> Option<GlyphLayout> {
+    match path {
+        TextPath::Straight => None,
+        TextPath::Arc { radius, angle } => Some(layout_on_arc(fonts, text, *radius, angle.to_radians(), position, area, base_scale)),
+        TextPath::Bezier(points) => Some(layout_on_bezier(fonts, text, points, area, base_scale)),
+    }
+}
+
+fn glyph_advances(fonts: &FontSet, text: &str, scale: Scale) -> Vec<f32> {
+    text.chars().map(|c| glyph_advance(fonts.for_char(c), c, scale)).collect()
+}
+
+// 文字の高さ (ascent - descent)
+fn line_height(fonts: &FontSet, scale: Scale) -> f32 {
+    let v_metrics = fonts.primary.v_metrics(scale);
+    v_metrics.ascent - v_metrics.descent
+}
+
+// 円の外側に沿わせる。上半分は時計回りに文字の頭を外へ、下半分は反時計回りに頭を中心へ向けて、
+// どちらも左から右へ読めるようにする
+fn layout_on_arc(
+    fonts: &FontSet,
+    text: &str,
+    radius_ratio: f32,
+    angle: f32,
+    position: &DomainPosition,
+    area: Area,
+    base_scale: f32,
+) -> GlyphLayout {
+    let short_side = area.2.min(area.3) as f32;
+    let mut scale = Scale::uniform(base_scale.min(short_side * 0.2).max(1.0));
+    // 円と文字が area からはみ出さない半径にする
+    let mut radius = (short_side * radius_ratio).min(short_side / 2.0 - line_height(fonts, scale)).max(1.0);
+
+    let mut advances = glyph_advances(fonts, text, scale);
+    let total: f32 = advances.iter().sum();
+    if total / radius > MAX_ARC_SWEEP {
+        let shrink = MAX_ARC_SWEEP * radius / total;
+        scale = Scale::uniform((scale.x * shrink).max(1.0));
+        advances = glyph_advances(fonts, text, scale);
+        radius = (short_side * radius_ratio).min(short_side / 2.0 - line_height(fonts, scale)).max(1.0);
+    }
+    let total: f32 = advances.iter().sum();
+
+    let extent = radius + line_height(fonts, scale);
+    let (left, top) = place(position, (extent * 2.0, extent * 2.0), area);
+    let center = (left as f32 + extent, top as f32 + extent);
+
+    let upper = angle.cos() >= 0.0;
+    // 下半分は文字の頭が円に触れるよう、ベースラインを ascent だけ外に出す
+    let baseline_radius = if upper { radius } else { radius + fonts.primary.v_metrics(scale).ascent };
+    let mut distance = -total / 2.0;
+    let glyphs = text
+        .chars()
+        .zip(advances)
+        .map(|(c, advance)| {
+            let offset = (distance + advance / 2.0) / baseline_radius;
+            distance += advance;
+            let (theta, rotation) = if upper { (angle + offset, angle + offset) } else { (angle - offset, angle - offset - PI) };
+            let anchor = (center.0 + baseline_radius * theta.sin(), center.1 - baseline_radius * theta.cos());
+            PlacedGlyph { c, anchor, angle: rotation }
+        })
+        .collect();
+    GlyphLayout { scale, glyphs }
+}
+
+fn bezier_point(points: &[(f32, f32)], t: f32) -> (f32, f32) {
+    // de Casteljau
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|w| (w[0].0 + (w[1].0 - w[0].0) * t, w[0].1 + (w[1].1 - w[0].1) * t))
+            .collect();
+    }
+    points[0]
+}
+
+// 曲線を細かい折れ線にして長さを測り、文字列を曲線の中央に置く
+fn layout_on_bezier(fonts: &FontSet, text: &str, points: &[(f32, f32)], (x, y, width, height): Area, base_scale: f32) -> GlyphLayout {
+    let points: Vec<(f32, f32)> = points
+        .iter()
+        .map(|&(px, py)| (x as f32 + px * width as f32, y as f32 + py * height as f32))
+        .collect();
+    let samples: Vec<(f32, f32)> = (0..=BEZIER_SEGMENTS)
+        .map(|i| bezier_point(&points, i as f32 / BEZIER_SEGMENTS as f32))
+        .collect();
+    let mut lengths = vec![0.0f32];
+    for w in samples.windows(2) {
+        let last = *lengths.last().unwrap_or(&0.0);
+        lengths.push(last + (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1));
+    }
+    let path_length = *lengths.last().unwrap_or(&0.0);
+
+    let mut scale = Scale::uniform(base_scale.max(1.0));
+    let mut advances = glyph_advances(fonts, text, scale);
+    let total: f32 = advances.iter().sum();
+    if total > path_length * PATH_FILL_RATIO && total > 0.0 {
+        scale = Scale::uniform((scale.x * path_length * PATH_FILL_RATIO / total).max(1.0));
+        advances = glyph_advances(fonts, text, scale);
+    }
+    let total: f32 = advances.iter().sum();
+
+    let mut distance = (path_length - total) / 2.0;
+    let glyphs = text
+        .chars()
+        .zip(advances)
+        .map(|(c, advance)| {
+            let middle = distance + advance / 2.0;
+            distance += advance;
+            // middle を含む区間の中で線形補間し、その区間の向きに回す
+            let i = lengths.partition_point(|&l| l < middle).clamp(1, samples.len() - 1);
+            let (start, end) = (samples[i - 1], samples[i]);
+            let segment = (lengths[i] - lengths[i - 1]).max(f32::EPSILON);
+            let t = ((middle - lengths[i - 1]) / segment).clamp(0.0, 1.0);
+            let anchor = (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t);
+            PlacedGlyph { c, anchor, angle: (end.1 - start.1).atan2(end.0 - start.0) }
+        })
+        .collect();
+    GlyphLayout { scale, glyphs }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusttype::Font;
+
+    fn with_fonts<T>(f: impl FnOnce(&FontSet) -> T) -> T {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        f(&FontSet { primary: &font, fallback: None })
+    }
+
+    #[test]
+    fn test_straight_path_uses_line_layout() {
+        let layout = with_fonts(|fonts| {
+            layout_text_on_path(fonts, "LGTM", &TextPath::Straight, &DomainPosition::Center, (0, 0, 400, 400), 40.0)
+        });
+        assert_eq!(layout, None);
+    }
+
+    #[test]
+    fn test_arc_top_reads_clockwise_and_bottom_counterclockwise() {
+        let area = (0, 0, 400, 400);
+        let (top, bottom) = with_fonts(|fonts| {
+            let top = TextPath::Arc { radius: 0.3, angle: 0.0 };
+            let bottom = TextPath::Arc { radius: 0.3, angle: 180.0 };
+            (
+                layout_text_on_path(fonts, "LOOKS GOOD", &top, &DomainPosition::Center, area, 30.0).unwrap(),
+                layout_text_on_path(fonts, "LOOKS GOOD", &bottom, &DomainPosition::Center, area, 30.0).unwrap(),
+            )
+        });
+        for layout in [&top, &bottom] {
+            // 左から右へ並ぶ
+            assert!(layout.glyphs.windows(2).all(|w| w[0].anchor.0 < w[1].anchor.0), "{:?}", layout.glyphs);
+        }
+        // 上の弧は両端が下がり、文字は外へ向く (左端は反時計回りに傾く)
+        let first = top.glyphs[0];
+        assert!(first.anchor.1 > top.glyphs[4].anchor.1 && first.angle < 0.0);
+        assert!(top.glyphs.iter().all(|g| g.anchor.1 < 200.0));
+        // 下の弧は両端が上がり、文字は正立に近い
+        let first = bottom.glyphs[0];
+        assert!(first.anchor.1 < bottom.glyphs[4].anchor.1 && first.angle.abs() < PI / 2.0);
+        assert!(bottom.glyphs.iter().all(|g| g.anchor.1 > 200.0));
+    }
+
+    #[test]
+    fn test_arc_is_placed_by_position_and_stays_inside() {
+        let layout = with_fonts(|fonts| {
+            let path = TextPath::Arc { radius: 0.45, angle: 0.0 };
+            layout_text_on_path(fonts, "LOOKS GOOD TO ME", &path, &DomainPosition::TopLeft, (0, 0, 600, 300), 40.0).unwrap()
+        });
+        let (x, y, width, height) = layout.bounds();
+        assert!(x + width as i32 <= 300 + layout.scale.y as i32, "{:?}", layout.bounds());
+        assert!(y >= -(layout.scale.y as i32) && y + height as i32 <= 300 + layout.scale.y as i32);
+    }
+
+    #[test]
+    fn test_bezier_follows_tangent_and_shrinks_to_fit() {
+        let layout = with_fonts(|fonts| {
+            // 左下から右上へのまっすぐな 2 次ベジェ
+            let path = TextPath::Bezier(vec![(0.0, 1.0), (0.5, 0.5), (1.0, 0.0)]);
+            layout_text_on_path(fonts, "LOOKS GOOD TO ME", &path, &DomainPosition::Center, (0, 0, 200, 200), 80.0).unwrap()
+        });
+        assert!(layout.scale.x < 80.0);
+        for glyph in &layout.glyphs {
+            assert!((glyph.angle + PI / 4.0).abs() < 0.01, "{:?}", glyph);
+            assert!((glyph.anchor.0 + glyph.anchor.1 - 200.0).abs() < 0.5);
+        }
+        // 文字列は曲線の真ん中に置かれる
+        let (first, last) = (layout.glyphs[0].anchor, layout.glyphs[layout.glyphs.len() - 1].anchor);
+        assert!(((first.0 + last.0) / 2.0 - 100.0).abs() < 10.0);
+    }
+}
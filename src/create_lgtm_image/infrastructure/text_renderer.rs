@@ -0,0 +1,237 @@
+use super::fonts::FontSet;
+use super::text_layout::{advance_width, split_runs, sticker_size, GlyphLayout, TextLayout, TextRun};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_text_mut;
+use rusttype::{point, Font, Scale};
+
+// 文字の色。Rainbow は 1 文字ごとに色相をずらす
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextPaint {
+    Solid(Rgba<u8>),
+    Rainbow { base: Rgba<u8>, hue_step: f32 },
+}
+
+impl TextPaint {
+    // index 番目 (空白を除いて数える) の文字の色
+    pub fn color_at(&self, index: usize) -> Rgba<u8> {
+        match *self {
+            TextPaint::Solid(color) => color,
+            TextPaint::Rainbow { base, hue_step } => {
+                let (hue, saturation, value) = rgb_to_hsv(base);
+                // 白や灰色でも虹色に見えるよう、彩度と明度は下げすぎない
+                let [r, g, b] = hsv_to_rgb(hue + hue_step * index as f32, saturation.max(0.75), value.max(0.75));
+                Rgba([r, g, b, base[3]])
+            }
+        }
+    }
+}
+
+fn rgb_to_hsv(color: Rgba<u8>) -> (f32, f32, f32) {
+    let [r, g, b] = [color[0], color[1], color[2]].map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, if max == 0.0 { 0.0 } else { delta / max }, max)
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r, g, b].map(|c| ((c + m) * 255.0).round() as u8)
+}
+
+// layout の位置にテキストを描く。インラインのステッカーはベースラインに乗せ、文字と同じ大きさにする
+pub fn draw_text_runs(img: &mut RgbaImage, paint: TextPaint, layout: &TextLayout, font: &Font, text: &str) {
+    let runs = split_runs(text);
+    if let ([TextRun::Text(text)], TextPaint::Solid(color)) = (&runs[..], paint) {
+        draw_text_mut(img, color, layout.x, layout.y, layout.scale, font, text);
+        return;
+    }
+
+    let baseline = layout.y as f32 + font.v_metrics(layout.scale).ascent;
+    let size = sticker_size(font, layout.scale);
+    let mut x = layout.x as f32;
+    let mut index = 0;
+    for run in runs {
+        match (run, paint) {
+            (TextRun::Text(text), TextPaint::Solid(color)) => {
+                draw_text_mut(img, color, x.round() as i32, layout.y, layout.scale, font, text);
+                x += advance_width(font, text, layout.scale);
+            }
+            (TextRun::Text(text), paint) => {
+                // 1 文字ずつ色を変えるので、draw_text_mut と同じ送り幅で 1 文字ずつ置く
+                for (c, glyph) in text.chars().zip(font.layout(text, layout.scale, point(x, baseline))) {
+                    let half_advance = glyph.unpositioned().h_metrics().advance_width / 2.0;
+                    draw_glyph(img, font, c, layout.scale, (glyph.position().x + half_advance, baseline), 0.0, paint.color_at(index));
+                    if !c.is_whitespace() {
+                        index += 1;
+                    }
+                }
+                x += advance_width(font, text, layout.scale);
+            }
+            (TextRun::Sticker(png), _) => {
+                // 同梱の PNG なのでデコードに失敗したら描かずに飛ばす
+                if let Ok(sticker) = image::load_from_memory(png) {
+                    let sticker = imageops::resize(&sticker.to_rgba8(), size as u32, size as u32, FilterType::Lanczos3);
+                    imageops::overlay(img, &sticker, x.round() as i64, (baseline - size).round() as i64);
+                }
+                x += size;
+            }
+        }
+    }
+}
+
+// 1 文字ずつ配置したテキストを描く。文字ごとに同梱フォントか代わりのフォントを選ぶ
+pub fn draw_glyph_layout(img: &mut RgbaImage, paint: TextPaint, layout: &GlyphLayout, fonts: &FontSet) {
+    let mut index = 0;
+    for glyph in &layout.glyphs {
+        draw_glyph(img, fonts.for_char(glyph.c), glyph.c, layout.scale, glyph.anchor, glyph.angle, paint.color_at(index));
+        if !glyph.c.is_whitespace() {
+            index += 1;
+        }
+    }
+}
+
+pub fn glyph_advance(font: &Font, c: char, scale: Scale) -> f32 {
+    font.glyph(c).scaled(scale).h_metrics().advance_width
+}
+
+// 1 文字を回転させて描く。anchor はその文字のベースラインの中央、angle はラジアン (時計回り)
+// 弧や縦書きのように 1 文字ずつ置くレイアウトで使う
+pub fn draw_glyph(img: &mut RgbaImage, font: &Font, c: char, scale: Scale, anchor: (f32, f32), angle: f32, color: Rgba<u8>) {
+    let glyph = font.glyph(c).scaled(scale);
+    let half_advance = glyph.h_metrics().advance_width / 2.0;
+    let glyph = glyph.positioned(point(0.0, 0.0));
+    let Some(bb) = glyph.pixel_bounding_box() else { return };
+    let (glyph_width, glyph_height) = (bb.width() as usize, bb.height() as usize);
+    let mut coverage = vec![0.0f32; glyph_width * glyph_height];
+    glyph.draw(|x, y, v| coverage[y as usize * glyph_width + x as usize] = v);
+
+    // グリフの座標 (ベースライン中央が原点) から画像の座標へ
+    let (sin, cos) = angle.sin_cos();
+    let to_image = |gx: f32, gy: f32| {
+        let (lx, ly) = (gx - half_advance, gy);
+        (anchor.0 + lx * cos - ly * sin, anchor.1 + lx * sin + ly * cos)
+    };
+    let corners = [
+        to_image(bb.min.x as f32, bb.min.y as f32),
+        to_image(bb.max.x as f32, bb.min.y as f32),
+        to_image(bb.min.x as f32, bb.max.y as f32),
+        to_image(bb.max.x as f32, bb.max.y as f32),
+    ];
+    let min_x = corners.iter().map(|p| p.0).fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+    let min_y = corners.iter().map(|p| p.1).fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+    let max_x = (corners.iter().map(|p| p.0).fold(f32::MIN, f32::max).ceil().max(0.0) as u32).min(img.width());
+    let max_y = (corners.iter().map(|p| p.1).fold(f32::MIN, f32::max).ceil().max(0.0) as u32).min(img.height());
+
+    let sample = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= glyph_width as i64 || y >= glyph_height as i64 {
+            0.0
+        } else {
+            coverage[y as usize * glyph_width + x as usize]
+        }
+    };
+    for py in min_y..max_y {
+        for px in min_x..max_x {
+            // 画素の中心を逆回転してグリフ上の位置を求め、双線形補間する
+            let (rx, ry) = (px as f32 + 0.5 - anchor.0, py as f32 + 0.5 - anchor.1);
+            let gx = rx * cos + ry * sin + half_advance - bb.min.x as f32 - 0.5;
+            let gy = -rx * sin + ry * cos - bb.min.y as f32 - 0.5;
+            let (x0, y0) = (gx.floor(), gy.floor());
+            let (fx, fy) = (gx - x0, gy - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let v = sample(x0, y0) * (1.0 - fx) * (1.0 - fy)
+                + sample(x0 + 1, y0) * fx * (1.0 - fy)
+                + sample(x0, y0 + 1) * (1.0 - fx) * fy
+                + sample(x0 + 1, y0 + 1) * fx * fy;
+            let alpha = v.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+            if alpha <= 0.0 {
+                continue;
+            }
+            let pixel = img.get_pixel_mut(px, py);
+            for i in 0..3 {
+                pixel[i] = (pixel[i] as f32 * (1.0 - alpha) + color[i] as f32 * alpha).round() as u8;
+            }
+            pixel[3] = (alpha * 255.0 + pixel[3] as f32 * (1.0 - alpha)).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::text_layout::layout_text_in;
+    use crate::domain::position::Position;
+
+    #[test]
+    fn test_draw_text_runs_places_sticker_on_baseline() {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        let mut img = RgbaImage::from_pixel(300, 100, Rgba([0, 0, 0, 255]));
+        let layout = layout_text_in(&font, "A:white_check_mark:", &Position::TopLeft, (0, 0, 300, 100), 50.0);
+        draw_text_runs(&mut img, TextPaint::Solid(Rgba([255, 255, 255, 255])), &layout, &font, "A:white_check_mark:");
+
+        // ステッカーの緑は "A" の右、ベースラインより上にだけある
+        let baseline = (layout.y as f32 + font.v_metrics(layout.scale).ascent) as u32;
+        let a_width = advance_width(&font, "A", layout.scale) as u32;
+        let green: Vec<(u32, u32)> = img
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[1] > 150 && p[0] < 150)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert!(!green.is_empty());
+        assert!(green.iter().all(|&(x, y)| x >= a_width && y <= baseline), "{:?}", green.iter().max());
+    }
+
+    #[test]
+    fn test_draw_glyph_rotates_around_baseline_center() {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        let scale = rusttype::Scale::uniform(40.0);
+        let ink = |img: &RgbaImage| -> Vec<(u32, u32)> {
+            img.enumerate_pixels().filter(|(_, _, p)| p[3] > 128).map(|(x, y, _)| (x, y)).collect()
+        };
+
+        // 回転なしなら "I" はベースライン (y = 50) より上に立つ
+        let mut upright = RgbaImage::new(100, 100);
+        draw_glyph(&mut upright, &font, 'I', scale, (50.0, 50.0), 0.0, Rgba([255, 0, 0, 255]));
+        assert!(ink(&upright).iter().all(|&(_, y)| y < 51));
+
+        // 90 度 (時計回り) 回すと右に倒れる
+        let mut rotated = RgbaImage::new(100, 100);
+        draw_glyph(&mut rotated, &font, 'I', scale, (50.0, 50.0), std::f32::consts::FRAC_PI_2, Rgba([255, 0, 0, 255]));
+        let pixels = ink(&rotated);
+        assert!(!pixels.is_empty());
+        assert!(pixels.iter().all(|&(x, _)| x >= 49), "{:?}", pixels.iter().min());
+    }
+
+    #[test]
+    fn test_rainbow_paint_shifts_hue_per_glyph() {
+        let paint = TextPaint::Rainbow { base: Rgba([255, 0, 0, 200]), hue_step: 120.0 };
+        assert_eq!(paint.color_at(0).0, [255, 0, 0, 200]);
+        assert_eq!(paint.color_at(1).0, [0, 255, 0, 200]);
+        assert_eq!(paint.color_at(2).0, [0, 0, 255, 200]);
+        assert_eq!(paint.color_at(3).0, [255, 0, 0, 200]);
+        // 白から始めても色がつく
+        let white = TextPaint::Rainbow { base: Rgba([255, 255, 255, 255]), hue_step: 30.0 };
+        assert_eq!(white.color_at(0).0, [255, 64, 64, 255]);
+        assert_eq!(TextPaint::Solid(Rgba([1, 2, 3, 4])).color_at(5).0, [1, 2, 3, 4]);
+    }
+}
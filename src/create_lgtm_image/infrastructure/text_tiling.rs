@@ -0,0 +1,112 @@
+use super::text_layout::{default_scale, layout_text_in, TextLayout};
+use super::text_renderer::{draw_text_runs, TextPaint};
+use crate::domain::position::Position as DomainPosition;
+use crate::domain::text_overlay::TileLayout;
+use image::{imageops, Rgba, RgbaImage};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use rusttype::Font;
+
+const TILE_SCALE_RATIO: f32 = 0.5; // 1 つだけ置くときの文字の大きさに対する比率
+
+// 大きさ (width, height) の透明なレイヤーに text を斜めの格子で敷き詰める
+// 行ごとに半分ずらしてレンガ状に並べる。tile.opacity はここではかけない (効果をかけてから薄くする)
+// transparent はレイヤーの地の色 (文字の色で透明にしておくと、ふちが黒ずまない)
+// 描いた文字の大きさ (px) も返す
+pub fn render_tiled_text(
+    font: &Font,
+    text: &str,
+    paint: TextPaint,
+    transparent: Rgba<u8>,
+    (width, height): (u32, u32),
+    tile: &TileLayout,
+) -> (RgbaImage, f32) {
+    let mut layer = RgbaImage::from_pixel(width, height, transparent);
+    let base_scale = default_scale(text, height) * TILE_SCALE_RATIO;
+    if text.trim().is_empty() {
+        return (layer, base_scale);
+    }
+    // 格子は回しても四隅が欠けないよう、対角線の長さの正方形の範囲に並べる
+    let side = ((width as f32).hypot(height as f32).ceil() as u32).max(1);
+    let layout = layout_text_in(font, text, &DomainPosition::TopLeft, (0, 0, side, side), base_scale);
+    let gap = layout.height as f32 * tile.spacing.max(0.0);
+    let (cell_width, cell_height) = ((layout.width as f32 + gap).max(1.0), (layout.height as f32 + gap).max(1.0));
+
+    // 文字 1 つ分のタイルだけを回しておき、回した格子の位置に貼っていく
+    // (正方形全体に描いてから回すと、大きな画像では対角線の 2 乗の画像を 2 枚作ることになるため)
+    let stamp_side = (layout.width as f32).hypot(layout.height as f32).ceil() as u32 + 2;
+    let mut stamp = RgbaImage::from_pixel(stamp_side, stamp_side, transparent);
+    let centered = |size: u32| (stamp_side as i32 - size as i32) / 2;
+    draw_text_runs(&mut stamp, paint, &TextLayout { x: centered(layout.width), y: centered(layout.height), ..layout }, font, text);
+    let angle = tile.angle.to_radians();
+    if angle != 0.0 {
+        stamp = rotate_about_center(&stamp, angle, Interpolation::Bilinear, transparent);
+    }
+
+    let (sin, cos) = angle.sin_cos();
+    let half_side = side as f32 / 2.0;
+    let mut y = 0.0;
+    let mut row = 0;
+    while y < side as f32 {
+        let mut x = if row % 2 == 1 { -cell_width / 2.0 } else { 0.0 };
+        while x < side as f32 {
+            // 正方形の中心から見た文字の中心を回して、レイヤーの中心からの位置にする
+            let dx = x + layout.width as f32 / 2.0 - half_side;
+            let dy = y + layout.height as f32 / 2.0 - half_side;
+            let center_x = width as f32 / 2.0 + dx * cos - dy * sin;
+            let center_y = height as f32 / 2.0 + dx * sin + dy * cos;
+            let left = (center_x - stamp_side as f32 / 2.0).round() as i64;
+            let top = (center_y - stamp_side as f32 / 2.0).round() as i64;
+            imageops::overlay(&mut layer, &stamp, left, top);
+            x += cell_width;
+        }
+        y += cell_height;
+        row += 1;
+    }
+    (layer, layout.scale.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> Font<'static> {
+        Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap()
+    }
+
+    fn render(text: &str, angle: f32, spacing: f32) -> RgbaImage {
+        let tile = TileLayout { angle, spacing, opacity: 1.0 };
+        let white = Rgba([255, 255, 255, 255]);
+        render_tiled_text(&font(), text, TextPaint::Solid(white), Rgba([255, 255, 255, 0]), (300, 200), &tile).0
+    }
+
+    // 縦横 3 等分した 9 マスのうち、文字の画素があるマスの数
+    fn covered_cells(layer: &RgbaImage) -> usize {
+        let (width, height) = layer.dimensions();
+        (0..9)
+            .filter(|i| {
+                let (cx, cy) = (i % 3 * width / 3, i / 3 * height / 3);
+                (cy..cy + height / 3).any(|y| (cx..cx + width / 3).any(|x| layer.get_pixel(x, y)[3] > 128))
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_tiled_text_covers_whole_image() {
+        let layer = render("LGTM", -30.0, 1.0);
+        assert_eq!(layer.dimensions(), (300, 200));
+        assert_eq!(covered_cells(&layer), 9);
+    }
+
+    #[test]
+    fn test_tiled_text_spacing_controls_density() {
+        let opaque = |layer: &RgbaImage| layer.pixels().filter(|p| p[3] > 128).count();
+        let dense = render("LGTM", 0.0, 0.2);
+        let sparse = render("LGTM", 0.0, 3.0);
+        assert!(opaque(&dense) > opaque(&sparse) * 2, "{} {}", opaque(&dense), opaque(&sparse));
+    }
+
+    #[test]
+    fn test_tiled_text_empty_text_is_transparent() {
+        assert!(render("  ", 45.0, 1.0).pixels().all(|p| p[3] == 0));
+    }
+}
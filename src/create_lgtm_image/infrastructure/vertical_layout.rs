@@ -0,0 +1,138 @@
+use super::fonts::FontSet;
+use super::text_layout::{place, Area, GlyphLayout, PlacedGlyph};
+use crate::domain::position::Position as DomainPosition;
+use rusttype::{point, Scale};
+use std::f32::consts::FRAC_PI_2;
+
+const COLUMN_PITCH: f32 = 1.25; // 列の間隔 (文字の大きさに対する比率)
+const MAX_FILL_RATIO: f32 = 0.9; // 縦横とも領域の 90% に収める
+
+// 縦書きでの文字の置き方
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VerticalForm {
+    Upright,
+    Rotated, // 長音記号・ダッシュ・括弧など。時計回りに 90 度回して縦に伸ばす
+    Corner,  // 句読点。マス目の右上に寄せる
+    Small,   // 小書きの仮名。少し右上に寄せる
+}
+
+fn vertical_form(c: char) -> VerticalForm {
+    match c {
+        'ー' | '－' | '—' | '―' | '‐' | '-' | '~' | '～' | '〜' | '…' | '‥' | '=' | '＝' | '(' | ')' | '（' | '）' | '[' | ']'
+        | '［' | '］' | '{' | '}' | '｛' | '｝' | '「' | '」' | '『' | '』' | '【' | '】' | '〈' | '〉' | '《' | '》' | '〔'
+        | '〕' => VerticalForm::Rotated,
+        '、' | '。' | '，' | '．' | ',' | '.' => VerticalForm::Corner,
+        'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ' | 'ゅ' | 'ょ' | 'ゎ' | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ' | 'ッ' | 'ャ' | 'ュ'
+        | 'ョ' | 'ヮ' | 'ヵ' | 'ヶ' => VerticalForm::Small,
+        _ => VerticalForm::Upright,
+    }
+}
+
+// 縦書きで 1 文字ずつ置く。改行で次の列 (左) に移る
+// 文字は 1 文字 1 マス (文字の大きさの正方形) で、列の塊を position に従って area の中に置く
+pub fn layout_vertical_text(fonts: &FontSet, text: &str, position: &DomainPosition, area: Area, base_scale: f32) -> GlyphLayout {
+    let columns: Vec<Vec<char>> = text.split('\n').map(|line| line.trim_end_matches('\r').chars().collect()).collect();
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0).max(1) as f32;
+    let pitch_count = 1.0 + (columns.len() as f32 - 1.0) * COLUMN_PITCH;
+
+    // 一番長い列が高さに、列の並びが幅に収まるまで縮める
+    let size = base_scale
+        .min(area.3 as f32 * MAX_FILL_RATIO / rows)
+        .min(area.2 as f32 * MAX_FILL_RATIO / pitch_count)
+        .max(1.0);
+    let scale = Scale::uniform(size);
+    let block = (size * pitch_count, size * rows);
+    let (left, top) = place(position, block, area);
+
+    let mut glyphs = Vec::new();
+    for (i, column) in columns.iter().enumerate() {
+        // 1 列目が一番右
+        let center_x = left as f32 + block.0 - size / 2.0 - i as f32 * size * COLUMN_PITCH;
+        for (j, &c) in column.iter().enumerate() {
+            let cell_center = (center_x, top as f32 + size * (j as f32 + 0.5));
+            glyphs.push(place_in_cell(fonts, c, scale, cell_center));
+        }
+    }
+    GlyphLayout { scale, glyphs }
+}
+
+fn place_in_cell(fonts: &FontSet, c: char, scale: Scale, (cx, cy): (f32, f32)) -> PlacedGlyph {
+    let font = fonts.for_char(c);
+    let v_metrics = font.v_metrics(scale);
+    // ベースラインから文字の上下の中央までの距離
+    let middle = (v_metrics.ascent + v_metrics.descent) / 2.0;
+    let size = scale.y;
+    match vertical_form(c) {
+        // 回すと文字の上が右を向くので、上下の中央が列の中央に来るよう左にずらす
+        VerticalForm::Rotated => PlacedGlyph { c, anchor: (cx - middle, cy), angle: FRAC_PI_2 },
+        VerticalForm::Corner => {
+            // 句読点はフォントによって置かれる位置が違うので、実際の形の中心をマス目の右上に合わせる
+            let glyph = font.glyph(c).scaled(scale);
+            let half_advance = glyph.h_metrics().advance_width / 2.0;
+            let target = (cx + size * 0.25, cy - size * 0.25);
+            let anchor = match glyph.positioned(point(0.0, 0.0)).pixel_bounding_box() {
+                Some(bb) => (
+                    target.0 - ((bb.min.x + bb.max.x) as f32 / 2.0 - half_advance),
+                    target.1 - (bb.min.y + bb.max.y) as f32 / 2.0,
+                ),
+                None => (cx, cy + middle),
+            };
+            PlacedGlyph { c, anchor, angle: 0.0 }
+        }
+        VerticalForm::Small => PlacedGlyph { c, anchor: (cx + size * 0.08, cy + middle - size * 0.08), angle: 0.0 },
+        VerticalForm::Upright => PlacedGlyph { c, anchor: (cx, cy + middle), angle: 0.0 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusttype::Font;
+
+    fn with_fonts<T>(f: impl FnOnce(&FontSet) -> T) -> T {
+        let font = Font::try_from_bytes(include_bytes!("../../../DejaVu_Sans/DejaVuSans-Bold.ttf")).unwrap();
+        f(&FontSet { primary: &font, fallback: None })
+    }
+
+    #[test]
+    fn test_vertical_columns_run_top_to_bottom_right_to_left() {
+        let layout = with_fonts(|fonts| layout_vertical_text(fonts, "LGTM\nOK", &DomainPosition::Center, (0, 0, 400, 400), 50.0));
+        let chars: String = layout.glyphs.iter().map(|g| g.c).collect();
+        assert_eq!(chars, "LGTMOK");
+        let (first, second) = layout.glyphs.split_at(4);
+        // 1 列目は上から下へ
+        assert!(first.windows(2).all(|w| w[0].anchor.1 < w[1].anchor.1 && w[0].anchor.0 == w[1].anchor.0));
+        // 2 列目は左に並び、上端がそろう
+        assert!(second[0].anchor.0 < first[0].anchor.0);
+        assert_eq!(second[0].anchor.1, first[0].anchor.1);
+        assert!(layout.glyphs.iter().all(|g| g.angle == 0.0));
+    }
+
+    #[test]
+    fn test_vertical_rotates_long_marks_and_moves_punctuation() {
+        let layout = with_fonts(|fonts| layout_vertical_text(fonts, "A—.", &DomainPosition::TopLeft, (0, 0, 300, 300), 40.0));
+        let [letter, dash, period] = layout.glyphs[..] else { panic!("{:?}", layout.glyphs) };
+        assert_eq!(dash.angle, FRAC_PI_2);
+        assert_eq!(period.angle, 0.0);
+        // 句読点はマス目の右上 (マス目の中央は x = 20, y = 100)
+        assert!(period.anchor.0 > letter.anchor.0);
+        assert!(period.anchor.1 < 100.0, "{:?}", period);
+    }
+
+    #[test]
+    fn test_vertical_fits_area_and_follows_position() {
+        let area = (0, 0, 300, 200);
+        let (top_left, bottom_right) = with_fonts(|fonts| {
+            (
+                layout_vertical_text(fonts, "LOOKSGOOD", &DomainPosition::TopLeft, area, 80.0),
+                layout_vertical_text(fonts, "LOOKSGOOD", &DomainPosition::BottomRight, area, 80.0),
+            )
+        });
+        // 9 文字が高さ 200 の 90% に収まるよう縮む
+        assert!(top_left.scale.y <= 20.0);
+        assert!(top_left.glyphs[0].anchor.0 < 30.0);
+        assert!(bottom_right.glyphs[0].anchor.0 > 270.0);
+        let last = bottom_right.glyphs[8].anchor.1;
+        assert!(last > 180.0 && last <= 200.0, "{}", last);
+    }
+}
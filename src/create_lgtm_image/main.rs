@@ -17,22 +17,50 @@ use infrastructure::axum_handler::{
     preview_image_handler,
     download_image_handler,
     fetch_image_handler,
+    meme_image_handler,
+    contact_sheet_handler,
+    gallery_image_handler,
     AppState,
 };
 use application::lgtm_service::LgtmService;
-use infrastructure::image_processor::DefaultImageProcessor; // LgtmServiceに渡すために必要
+use infrastructure::asset_store::LocalAssetStore;
+use infrastructure::gallery_store::LocalGalleryStore;
+use infrastructure::phrase_dictionary::PhraseDictionary;
+use infrastructure::fonts::fallback_font_from_env;
+use infrastructure::image_processor::{DecodeLimits, DefaultImageProcessor}; // LgtmServiceに渡すために必要
 
 #[tokio::main]
 async fn main() {
     let cors = CorsLayer::new()
         .allow_origin(Any)
-        .allow_headers(vec![HeaderName::from_static("content-type")]);
+        .allow_headers(vec![HeaderName::from_static("content-type")])
+        .expose_headers(vec![
+            HeaderName::from_static("x-lgtm-width"),
+            HeaderName::from_static("x-lgtm-height"),
+            HeaderName::from_static("x-lgtm-scale"),
+            HeaderName::from_static("x-lgtm-quality"),
+            HeaderName::from_static("x-lgtm-colors"),
+            HeaderName::from_static("x-lgtm-id"),
+        ]);
 
     // ImageProcessor のインスタンスを作成
-    let image_processor = Arc::new(DefaultImageProcessor::new());
+    // デコード時の寸法・メモリ上限は環境変数で調整できる
+    // LGTM_FALLBACK_FONT に日本語フォントを指定すると、判子などで同梱フォントにない文字も描ける
+    let mut image_processor = DefaultImageProcessor::with_limits(DecodeLimits::from_env());
+    if let Some(font) = fallback_font_from_env() {
+        image_processor = image_processor.with_fallback_font(font);
+    }
+    let image_processor = Arc::new(image_processor);
 
     // LgtmService のインスタンスを作成し、ImageProcessor を注入
-    let lgtm_service = Arc::new(LgtmService::new(image_processor));
+    // ロゴなどの画像は LGTM_ASSETS_DIR (デフォルト ./assets) から、text=random の言葉は LGTM_PHRASES_FILE (デフォルトは同梱の phrases.txt) から読む
+    // アップロードで作った画像は LGTM_GALLERY_DIR (デフォルト ./gallery) に残す
+    let lgtm_service = Arc::new(
+        LgtmService::new(image_processor)
+            .with_asset_store(LocalAssetStore::from_env())
+            .with_phrases(PhraseDictionary::from_env())
+            .with_gallery(LocalGalleryStore::from_env()),
+    );
 
     // AppState の初期化 (image_processor フィールドはもうない)
     let app_state = Arc::new(AppState {
@@ -46,6 +74,9 @@ async fn main() {
         .route("/preview", get(preview_image_handler))
         .route("/download", get(download_image_handler))
         .route("/fetch", post(fetch_image_handler))
+        .route("/meme", post(meme_image_handler))
+        .route("/contact-sheet", get(contact_sheet_handler))
+        .route("/gallery/:id", get(gallery_image_handler))
         .with_state(app_state)
         .layer(cors);
 